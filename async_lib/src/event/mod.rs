@@ -48,6 +48,12 @@ impl<T> EventEmitterWrapper<T> {
             inner
         )
     }
+
+    /// Same as emit, but the listeners are called when the future is awaited
+    /// instead of in a new task.
+    pub fn emit_now<Event: ToString, Args:ToString>(&self, event:Event, args:Args) -> impl Future<Output = ()> + use<T, Event, Args> {
+        parse_event(self.0.clone(), event.to_string(), args.to_string())
+    }
 }
 
 impl<T> EventEmitter for EventEmitterWrapper<T> {
@@ -125,6 +125,10 @@ impl<K: Ord, V:Send> AtomicMap<K, V> {
         let mut map = self.0.lock().unwrap();
         map.remove(key)
     }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
 }
 
 impl<K: Ord, V:Send + Clone> AtomicMap<K, V> {
@@ -137,8 +141,11 @@ impl<K: Ord, V:Send + Clone> AtomicMap<K, V> {
     }
 }
 
+/// Future taken out by the thread that finishes it.
+type BoxedFuture<T> = Pin<Box<dyn Future<Output = T> + 'static>>;
+
 #[derive(Clone)]
-pub(crate) struct AtomicFuture<T>(Arc<Mutex<Pin<Box<dyn Future<Output = T> + 'static>>>>);
+pub(crate) struct AtomicFuture<T>(Arc<Mutex<Option<BoxedFuture<T>>>>);
 
 //SAFTEY: Arc<Mutex<T>>
 unsafe impl<T> Send for AtomicFuture<T> {}
@@ -149,7 +156,7 @@ impl<T> AtomicFuture<T> {
         Self(
             Arc::new(
                 Mutex::new(
-                    Box::pin(f)
+                    Some(Box::pin(f))
                 )
             )
         )
@@ -161,7 +168,17 @@ impl<T> Future for AtomicFuture<T> {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut future = self.0.lock().unwrap();
-        future.as_mut().poll(cx)
+
+        //Another thread can be waiting on the lock after being woken while the future finished.
+        let result = match future.as_mut() {
+            Some(future) => future.as_mut().poll(cx),
+            None => return Poll::Pending
+        };
+
+        if result.is_ready() {
+            *future = None;
+        }
+        result
     }
 }
 
@@ -290,4 +307,23 @@ impl<T> AtomicList<T> {
         let list = self.0.lock().unwrap();
         list.get(index).map(|value|AtomicPtr::new(value as *const T as *mut T))
     }
+}
+
+impl<T: PartialEq> AtomicList<T> {
+    pub fn unique_push(&self, value: T) {
+        let mut list = self.0.lock().unwrap();
+        if list.contains(&value) {
+            return;
+        }
+
+        if list.len() >= list.capacity() {
+            panic!("{} list is full!", self.1)
+        }
+        list.push(value);
+    }
+
+    pub fn remove(&self, value: &T) {
+        let mut list = self.0.lock().unwrap();
+        list.retain(|item|item != value);
+    }
 }
\ No newline at end of file
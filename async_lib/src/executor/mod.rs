@@ -7,6 +7,8 @@ use tasks::*;
 use thread::*;
 
 mod atomic;
+pub(crate) mod oneshot;
+pub(crate) mod reactor;
 pub mod tasks;
 pub mod thread;
 
@@ -41,14 +43,47 @@ pub fn is_running() -> bool {
     RUNNING.load(Ordering::Relaxed)
 }
 
+/// Number of threads in the pool, zero before it is started.
+#[inline]
+pub fn thread_count() -> usize {
+    THREAD_MANAGER.size()
+}
+
+/// Threads in the pool parked waiting for work.
+#[inline]
+pub fn idle_threads() -> usize {
+    THREAD_MANAGER.parked()
+}
+
+/// Tasks that have been spawned and not finished.
+#[inline]
+pub fn task_count() -> usize {
+    TASK_MANAGER.len()
+}
+
+/// Tasks woken and waiting to be polled.
+#[inline]
+pub fn queued_tasks() -> usize {
+    TASK_MANAGER.queued()
+}
+
+/// Jobs and processes waiting for a free thread.
+#[inline]
+pub fn queued_jobs() -> usize {
+    THREAD_MANAGER.queued()
+}
+
 pub fn start_async_thread_pool(thread_count:usize) {
     assert_ne!(thread_count, 0, "Unable to initalize thread pool with zero threads!");
     
-    THREAD_MANAGER.init(thread_count);
     RUNNING.store(true, Ordering::Relaxed);
+    THREAD_MANAGER.init(thread_count);
     THREAD_MANAGER.join_all();
 }
 
 pub fn shut_down() {
     RUNNING.store(false, Ordering::Relaxed);
+
+    //Parked threads have to wake up to see they should stop.
+    THREAD_MANAGER.unpark_all();
 }
\ No newline at end of file
@@ -0,0 +1,71 @@
+/// Single value sent from one task or thread to another, waking the receiver
+/// once it is sent.
+///
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker}
+};
+
+struct Inner<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    closed: bool
+}
+
+pub(crate) struct Sender<T>(Arc<Mutex<Inner<T>>>);
+
+pub(crate) struct Receiver<T>(Arc<Mutex<Inner<T>>>);
+
+pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        value: None,
+        waker: None,
+        closed: false
+    }));
+
+    (Sender(inner.clone()), Receiver(inner))
+}
+
+impl<T> Sender<T> {
+    pub fn send(self, value:T) {
+        let waker = {
+            let mut inner = self.0.lock().unwrap();
+            inner.value = Some(value);
+            inner.waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut inner = self.0.lock().unwrap();
+            inner.closed = true;
+            inner.waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Ready with None if the sender was dropped without sending a value.
+    pub fn poll_recv(&self, cx:&Context<'_>) -> Poll<Option<T>> {
+        let mut inner = self.0.lock().unwrap();
+
+        match inner.value.take() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if inner.closed => Poll::Ready(None),
+            None => {
+                inner.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
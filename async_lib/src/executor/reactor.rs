@@ -0,0 +1,360 @@
+/// Readiness Reactor
+///
+/// Sockets are registered with an epoll instance that is waited on by its own
+/// thread, so a task that would block is only woken once its file descriptor
+/// is ready. Timers are woken from the same thread once their deadline passes.
+///
+/// Other platforms fall back to waking the task right away.
+///
+/// Regular files are never registered, as epoll does not support them, so
+/// `File` reads and writes still block the worker and wake the task right away
+/// on `WouldBlock`. Blocking file work should go through `queue_job`.
+///
+use std::{
+    io,
+    task::{Context, Poll},
+    time::Instant
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Interest {
+    Read,
+    Write
+}
+
+/// Key of a timer waiting in the reactor.
+pub(crate) type TimerKey = (Instant, u64);
+
+#[cfg(target_os = "linux")]
+pub(crate) use epoll::*;
+
+#[cfg(target_os = "linux")]
+mod epoll {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        io,
+        os::fd::{AsRawFd, RawFd},
+        sync::{
+            Arc, Mutex, LazyLock, Once,
+            atomic::{AtomicU64, Ordering}
+        },
+        task::Waker,
+        time::Instant
+    };
+    use util::log::error;
+    use super::{Interest, TimerKey};
+
+    const MAX_EVENTS: usize = 256;
+    /// Token of the eventfd used to interrupt epoll_wait.
+    const WAKE_TOKEN: u64 = 0;
+
+    static REACTOR: LazyLock<Reactor> = LazyLock::new(||Reactor::new().expect("Unable to start the I/O reactor!"));
+    static STARTED: Once = Once::new();
+
+    #[derive(Default)]
+    pub(crate) struct ScheduledIo {
+        readable: bool,
+        writable: bool,
+        reader: Option<Waker>,
+        writer: Option<Waker>
+    }
+
+    impl ScheduledIo {
+        /// Returns true if an event arrived since the last check, otherwise the
+        /// waker is kept until the next one does.
+        pub(crate) fn take_ready(&mut self, interest:Interest, waker:&Waker) -> bool {
+            let (ready, slot) = match interest {
+                Interest::Read => (&mut self.readable, &mut self.reader),
+                Interest::Write => (&mut self.writable, &mut self.writer)
+            };
+
+            if *ready {
+                *ready = false;
+                true
+            } else {
+                match slot {
+                    Some(current) if current.will_wake(waker) => {},
+                    _ => *slot = Some(waker.clone())
+                }
+                false
+            }
+        }
+    }
+
+    pub(crate) struct Reactor {
+        epoll: RawFd,
+        wake: RawFd,
+        sources: Mutex<HashMap<u64, Arc<Mutex<ScheduledIo>>>>,
+        timers: Mutex<BTreeMap<TimerKey, Waker>>,
+        next_token: AtomicU64
+    }
+
+    fn check(result:libc::c_int) -> io::Result<libc::c_int> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    impl Reactor {
+        fn new() -> io::Result<Self> {
+            let epoll = check(unsafe{ libc::epoll_create1(libc::EPOLL_CLOEXEC) })?;
+            let wake = check(unsafe{ libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) })?;
+
+            let mut event = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: WAKE_TOKEN
+            };
+            check(unsafe{ libc::epoll_ctl(epoll, libc::EPOLL_CTL_ADD, wake, &mut event) })?;
+
+            Ok(Self {
+                epoll, wake,
+                sources: Mutex::new(HashMap::new()),
+                timers: Mutex::new(BTreeMap::new()),
+                next_token: AtomicU64::new(WAKE_TOKEN + 1)
+            })
+        }
+
+        /// Milliseconds until the next timer, rounded up so it has passed once epoll_wait returns.
+        fn next_timeout(&self) -> libc::c_int {
+            match self.timers.lock().unwrap().keys().next() {
+                Some((deadline, _)) => deadline.saturating_duration_since(Instant::now())
+                    .as_micros()
+                    .div_ceil(1000)
+                    .min(libc::c_int::MAX as u128) as libc::c_int,
+                None => -1
+            }
+        }
+
+        fn run(&self) {
+            let mut events = vec![libc::epoll_event{ events: 0, u64: 0 }; MAX_EVENTS];
+
+            loop {
+                let count = unsafe{ libc::epoll_wait(self.epoll, events.as_mut_ptr(), MAX_EVENTS as libc::c_int, self.next_timeout()) };
+                if count < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        error!("Reactor Error: {}", e);
+                    }
+                    continue;
+                }
+
+                for event in &events[..count as usize] {
+                    let (flags, token) = (event.events, event.u64);
+
+                    if token == WAKE_TOKEN {
+                        let mut buffer = [0u8; 8];
+                        unsafe{ libc::read(self.wake, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+                        continue;
+                    }
+
+                    let source = match self.sources.lock().unwrap().get(&token) {
+                        Some(source) => source.clone(),
+                        None => continue
+                    };
+
+                    let closed = flags & (libc::EPOLLHUP | libc::EPOLLERR) as u32 != 0;
+                    let mut wakers = Vec::with_capacity(2);
+                    {
+                        let mut io = source.lock().unwrap();
+                        if closed || flags & (libc::EPOLLIN | libc::EPOLLRDHUP) as u32 != 0 {
+                            io.readable = true;
+                            wakers.extend(io.reader.take());
+                        }
+                        if closed || flags & libc::EPOLLOUT as u32 != 0 {
+                            io.writable = true;
+                            wakers.extend(io.writer.take());
+                        }
+                    }
+
+                    for waker in wakers {
+                        waker.wake();
+                    }
+                }
+
+                self.fire_timers();
+            }
+        }
+
+        fn fire_timers(&self) {
+            let now = Instant::now();
+            let expired: Vec<Waker> = {
+                let mut timers = self.timers.lock().unwrap();
+                let later = timers.split_off(&(now, u64::MAX));
+                std::mem::replace(&mut *timers, later).into_values().collect()
+            };
+
+            for waker in expired {
+                waker.wake();
+            }
+        }
+
+        /// Interrupts epoll_wait, so the timeout is worked out again.
+        fn notify(&self) {
+            let value: u64 = 1;
+            unsafe{ libc::write(self.wake, &value as *const u64 as *const libc::c_void, 8) };
+        }
+    }
+
+    fn reactor() -> &'static Reactor {
+        let reactor = &*REACTOR;
+
+        STARTED.call_once(||{
+            std::thread::Builder::new()
+                .name("reactor".to_string())
+                .spawn(||REACTOR.run())
+                .expect("Unable to spawn the reactor thread!");
+        });
+
+        reactor
+    }
+
+    /// Wakes the waker once the deadline has passed, replacing any timer already set by the key.
+    pub(crate) fn set_timer(key:&mut Option<TimerKey>, deadline:Instant, waker:&Waker) {
+        let reactor = reactor();
+        let mut timers = reactor.timers.lock().unwrap();
+
+        let id = match key.take() {
+            Some(old) => {
+                timers.remove(&old);
+                old.1
+            },
+            None => reactor.next_token.fetch_add(1, Ordering::Relaxed)
+        };
+        let new_key = (deadline, id);
+        let first = timers.keys().next().is_none_or(|next|new_key < *next);
+
+        timers.insert(new_key, waker.clone());
+        *key = Some(new_key);
+        drop(timers);
+
+        if first {
+            reactor.notify();
+        }
+    }
+
+    pub(crate) fn cancel_timer(key:&mut Option<TimerKey>) {
+        if let Some(key) = key.take() {
+            reactor().timers.lock().unwrap().remove(&key);
+        }
+    }
+
+    /// Moves a timer that is already waiting to the new deadline.
+    pub(crate) fn move_timer(key:&mut Option<TimerKey>, deadline:Instant) {
+        let reactor = match key {
+            Some(_) => reactor(),
+            None => return
+        };
+
+        let mut timers = reactor.timers.lock().unwrap();
+        let old = key.take().unwrap();
+        if let Some(waker) = timers.remove(&old) {
+            drop(timers);
+            set_timer(key, deadline, &waker);
+        }
+    }
+
+    /// File descriptor registered with the reactor until dropped.
+    pub(crate) struct Registration {
+        fd: RawFd,
+        token: u64,
+        io: Arc<Mutex<ScheduledIo>>
+    }
+
+    impl Registration {
+        pub(crate) fn new(source:&impl AsRawFd) -> io::Result<Self> {
+            let fd = source.as_raw_fd();
+            let reactor = reactor();
+            let token = reactor.next_token.fetch_add(1, Ordering::Relaxed);
+            let io = Arc::new(Mutex::new(ScheduledIo::default()));
+            reactor.sources.lock().unwrap().insert(token, io.clone());
+
+            //Edge triggered, so readiness is only reported again once new data arrives.
+            let mut event = libc::epoll_event {
+                events: (libc::EPOLLIN | libc::EPOLLOUT | libc::EPOLLRDHUP | libc::EPOLLET) as u32,
+                u64: token
+            };
+
+            if let Err(e) = check(unsafe{ libc::epoll_ctl(reactor.epoll, libc::EPOLL_CTL_ADD, fd, &mut event) }) {
+                reactor.sources.lock().unwrap().remove(&token);
+                return Err(e);
+            }
+
+            Ok(Self { fd, token, io })
+        }
+
+        pub(crate) fn io(&self) -> &Mutex<ScheduledIo> {
+            &self.io
+        }
+    }
+
+    impl Drop for Registration {
+        fn drop(&mut self) {
+            let reactor = reactor();
+            reactor.sources.lock().unwrap().remove(&self.token);
+            unsafe{ libc::epoll_ctl(reactor.epoll, libc::EPOLL_CTL_DEL, self.fd, std::ptr::null_mut()) };
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) use fallback::*;
+
+#[cfg(not(target_os = "linux"))]
+mod fallback {
+    use std::{
+        io,
+        sync::Mutex,
+        task::Waker,
+        time::Instant
+    };
+    use super::{Interest, TimerKey};
+
+    pub(crate) struct ScheduledIo;
+
+    impl ScheduledIo {
+        pub(crate) fn take_ready(&mut self, _:Interest, waker:&Waker) -> bool {
+            waker.wake_by_ref();
+            false
+        }
+    }
+
+    pub(crate) fn set_timer(_:&mut Option<TimerKey>, _:Instant, waker:&Waker) {
+        waker.wake_by_ref();
+    }
+
+    pub(crate) fn cancel_timer(_:&mut Option<TimerKey>) {}
+
+    pub(crate) fn move_timer(_:&mut Option<TimerKey>, _:Instant) {}
+
+    pub(crate) struct Registration(Mutex<ScheduledIo>);
+
+    impl Registration {
+        pub(crate) fn new<T>(_:&T) -> io::Result<Self> {
+            Ok(Self(Mutex::new(ScheduledIo)))
+        }
+
+        pub(crate) fn io(&self) -> &Mutex<ScheduledIo> {
+            &self.0
+        }
+    }
+}
+
+impl Registration {
+    /// Runs the operation until it does not return WouldBlock, or until the
+    /// task has to wait for the file descriptor to be ready.
+    pub(crate) fn poll_io<R>(&self, cx:&Context<'_>, interest:Interest, mut op:impl FnMut() -> io::Result<R>) -> Poll<io::Result<R>> {
+        loop {
+            match op() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    //An event that arrived after the attempt means it can be tried again.
+                    if !self.io().lock().unwrap().take_ready(interest, cx.waker()) {
+                        return Poll::Pending;
+                    }
+                },
+                result => return Poll::Ready(result)
+            }
+        }
+    }
+}
@@ -9,6 +9,7 @@ use std::{
     },
     task::{Context, Poll, Wake, Waker}
 };
+use util::log::debug;
 use super::{
     atomic::*,
     thread::{THREAD_MANAGER, ThreadProcess},
@@ -101,6 +102,16 @@ impl TaskHandler {
         self.task_queue.is_empty()
     }
 
+    /// Tasks that have been spawned and not finished.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Tasks woken and waiting to be polled.
+    pub fn queued(&self) -> usize {
+        self.task_queue.len()
+    }
+
     pub fn update_queue_capacity(&self, capcity:usize) {
         self.task_queue.set_capacity(capcity);
     }
@@ -131,7 +142,8 @@ impl TaskHandler {
     }
 
     pub fn run_all_tasks(&self) {
-        while let Some(task_id) = self.task_queue.pop() {
+        //Tasks that wake themselves would otherwise keep this from returning after shut down.
+        while RUNNING.load(Ordering::Relaxed) && let Some(task_id) = self.task_queue.pop() {
             let task = match self.tasks.get_mut(&task_id) {
                 Some(task) => task,
                 None => continue
@@ -159,7 +171,7 @@ impl TaskHandler {
         }
     }
 
-    fn unpark(&self) {
+    pub(crate) fn unpark(&self) {
         if let Some(id) = self.thread_id.try_unwrap() {
             THREAD_MANAGER.unpark(*id);
         }
@@ -169,7 +181,7 @@ impl TaskHandler {
         self.thread_id.set(Some(id));
 
         return move || {
-            println!("{id} Started");
+            debug!("{id} Started");
 
             while RUNNING.load(Ordering::Relaxed) {
                 self.idle_if_empty();
@@ -193,6 +205,10 @@ impl TaskWaker {
 
     fn wake_task(&self) {
         self.queue.unique_push(self.task);
+
+        //Wakers are called from the reactor, so the threads running tasks may be parked.
+        TASK_MANAGER.unpark();
+        THREAD_MANAGER.unpark_one();
     }
 }
 
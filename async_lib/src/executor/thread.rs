@@ -1,13 +1,14 @@
 use std::{
     sync::{
-        atomic::{AtomicPtr, Ordering},
-        mpsc::{channel, Receiver, TryRecvError},
+        atomic::Ordering,
         LazyLock
     },
-    thread::{Builder, JoinHandle},
+    thread::{Builder, JoinHandle, Thread},
 };
+use util::log::{debug, error};
 use super::{
     atomic::{AtomicList, AtomicQueue},
+    oneshot::{channel, Receiver},
     tasks::TASK_MANAGER,
     RUNNING, DEFAULT_QUEUE_SIZE
 };
@@ -32,7 +33,8 @@ pub(crate) fn thread_main(id:usize) {
 #[derive(Clone)]
 pub(crate) struct ThreadManager {
     pool: AtomicList<JoinHandle<()>>,
-    park: AtomicList<AtomicPtr<JoinHandle<()>>>,
+    threads: AtomicList<Thread>,
+    park: AtomicList<usize>,
     queue: AtomicQueue<Box<dyn ThreadProcess>>
 }
 
@@ -43,6 +45,7 @@ impl ThreadManager {
     pub fn new(queue_size:usize) -> Self {
         Self {
             pool: AtomicList::new("Thread Pool", 0),
+            threads: AtomicList::new("Threads", 0),
             park: AtomicList::new("Thread Park", 0),
             queue: AtomicQueue::new("Thread Job", queue_size)
         }
@@ -52,6 +55,7 @@ impl ThreadManager {
         assert_eq!(self.pool.get_capacity(), 0, "Thread Pool is Already Initalized!");
 
         self.pool.set_capacity(size);
+        self.threads.set_capacity(size);
         self.park.set_capacity(size);
 
         let handle = Builder::new()
@@ -59,7 +63,8 @@ impl ThreadManager {
                 .spawn(TASK_MANAGER.thread_main(0))
                 .unwrap();
 
-        println!("Spawned 0");
+        debug!("Spawned 0");
+        self.threads.push(handle.thread().clone());
         self.pool.push(handle);
 
         for id in 1..size {
@@ -68,7 +73,8 @@ impl ThreadManager {
                 .spawn(move||thread_main(id))
                 .unwrap();
 
-            println!("Spawned {}", id);
+            debug!("Spawned {}", id);
+            self.threads.push(handle.thread().clone());
             self.pool.push(handle);
         }
     }
@@ -77,38 +83,62 @@ impl ThreadManager {
         self.queue.set_capacity(capcity);
     }
 
+    pub fn size(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Threads parked waiting for work.
+    pub fn parked(&self) -> usize {
+        self.park.len()
+    }
+
+    /// Jobs and processes waiting for a thread.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
     fn next_process(&self) -> Option<Box<dyn ThreadProcess>> {
         self.queue.pop()
     }
 
     pub fn park(&self, id:usize) {
-        if let Some(ptr) = self.pool.get(id) {
-            self.park.push(ptr);
-        }
+        self.park.unique_push(id);
         std::thread::park();
+        self.park.remove(&id);
     }
 
     pub fn unpark(&self, id:usize) {
-        if let Some(ptr) = self.pool.get(id) {
-            //SAFTEY: Handle has 'static lifetime
-            let thread = unsafe{ &*ptr.load(Ordering::Relaxed) }.thread();
-            thread.unpark();
+        if let Some(ptr) = self.threads.get(id) {
+            //SAFTEY: Threads are never removed from the list
+            unsafe{ &*ptr.load(Ordering::Relaxed) }.unpark();
         }
     }
 
-    pub fn queue_process(&self, func: impl ThreadProcess) {
-        self.queue.push(Box::new(func));
+    /// Wakes a single parked thread, if any are waiting for work.
+    pub fn unpark_one(&self) {
+        if let Some(id) = self.park.pop() {
+            self.unpark(id);
+        }
+    }
 
-        if let Some(handle) = self.park.pop() {
-            //SAFTEY: Handle has 'static lifetime
-            let thread = unsafe{ &*handle.load(Ordering::Relaxed) }.thread();
-            thread.unpark();
+    /// Wakes every thread, even those already being joined.
+    pub fn unpark_all(&self) {
+        for id in 0..self.threads.len() {
+            if let Some(ptr) = self.threads.get(id) {
+                //SAFTEY: Threads are never removed from the list
+                unsafe{ &*ptr.load(Ordering::Relaxed) }.unpark();
+            }
         }
     }
 
+    pub fn queue_process(&self, func: impl ThreadProcess) {
+        self.queue.push(Box::new(func));
+        self.unpark_one();
+    }
+
     pub fn queue_job<T:Send + 'static>(&self, func: impl ThreadJob<T>) -> Job<T>{
         let (sender, receiver) = channel::<T>();
-        self.queue_process(move||sender.send(func()).unwrap());
+        self.queue_process(move||sender.send(func()));
         Job(receiver)
     }
 
@@ -117,9 +147,9 @@ impl ThreadManager {
             let name = handle.thread().name().unwrap_or("Anonymous").to_string();
 
             if let Err(e) = handle.join() {
-                println!("Thread {name} paniced with error:\n{:?}", e);
+                error!("Thread {name} paniced with error:\n{:?}", e);
             } else {
-                println!("Thread {name} closed gracefully!");
+                debug!("Thread {name} closed gracefully!");
             }
         }
     }
@@ -131,15 +161,7 @@ impl<T:Send> Future for Job<T> {
     type Output = T;
 
     fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
-        match self.0.try_recv() {
-            Ok(value) => std::task::Poll::Ready(value),
-            Err(e) => if e == TryRecvError::Empty {
-                cx.waker().wake_by_ref();
-                std::task::Poll::Pending
-            } else {
-                panic!("Actor disconected!")
-            }
-        }
+        self.0.poll_recv(cx).map(|value|value.expect("Actor disconected!"))
     }
 }
 
@@ -62,13 +62,13 @@ impl AsyncBuffer {
     /// # Safety
     /// Data may be uninitalized
     unsafe fn unfilled(&self) -> &[u8] {
-        unsafe { self.buf.get_unchecked(self.filled+1..).assume_init_ref() }
+        unsafe { self.buf.get_unchecked(self.filled..).assume_init_ref() }
     }
 
     /// # Safety
     /// Data may be uninitalized
     unsafe fn unfilled_mut(&mut self) -> &mut [u8] {
-        unsafe { self.buf.get_unchecked_mut(self.filled+1..).assume_init_mut() }
+        unsafe { self.buf.get_unchecked_mut(self.filled..).assume_init_mut() }
     }
 
     pub fn clear(&mut self) {
@@ -92,6 +92,10 @@ impl AsyncBuffer {
 
     #[async_fn]
     pub fn poll_read_more<R: AsyncRead>(mut self:Pin<&mut Self>, cx:&mut Context<'_>, reader:&mut R) -> Poll<io::Result<usize>> {
+        if self.remaining() == 0 && self.pos > 0 {
+            self.backshift();
+        }
+
         //SAFETY: Only Writting to buffer
         let buf = unsafe{ self.unfilled_mut() };
         let amt = match pin!(reader.read(buf)).poll(cx) {
@@ -107,7 +111,7 @@ impl AsyncBuffer {
 }
 
 impl AsyncRead for AsyncBuffer {
-    fn poll_read(self:Pin<&mut Self>, _cx: &mut std::task::Context<'_> ,buf: &mut [u8]) -> Poll<io::Result<usize> > {
+    fn poll_read(mut self:Pin<&mut Self>, _cx: &mut std::task::Context<'_> ,buf: &mut [u8]) -> Poll<io::Result<usize> > {
         let amt = cmp::min(buf.len(), self.size());
 
         //SAFTEY: Copying owned data without any type checking
@@ -120,6 +124,7 @@ impl AsyncRead for AsyncBuffer {
                 );
         }
 
+        self.consume(amt);
         Poll::Ready(Ok(amt))
     }
 }
@@ -20,46 +20,74 @@ impl<R: AsyncRead + Unpin> AsyncBufReader<R> {
         }
     }
 
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            buf: super::AsyncBuffer::with_capacitry(capacity),
+            inner
+        }
+    }
+
     #[async_fn]
     pub fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buffer:&mut [u8]) -> Poll<io::Result<usize>> {
         let Self{ buf, inner} = &mut (*self);
 
-        if pin!(buf.read_more(inner)).poll(cx).is_ready() {
-            Pin::new(&mut buf.read(buffer)).poll(cx)
-        } else {
-            Poll::Pending
+        if buf.size() == 0 && let Poll::Pending = pin!(buf.read_more(inner)).poll(cx) {
+            return Poll::Pending;
         }
+
+        Pin::new(&mut buf.read(buffer)).poll(cx)
     }
 
     #[async_fn]
     pub fn poll_read_vectored(mut self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &mut [io::IoSliceMut<'_>]) -> Poll<io::Result<usize>> {
         let Self{ buf, inner} = &mut (*self);
 
-        if pin!(buf.read_more(inner)).poll(cx).is_ready() {
-            pin!(buf.read_vectored(bufs)).poll(cx)
-        } else {
-            Poll::Pending
+        if buf.size() == 0 && let Poll::Pending = pin!(buf.read_more(inner)).poll(cx) {
+            return Poll::Pending;
         }
+
+        pin!(buf.read_vectored(bufs)).poll(cx)
+    }
+
+    /// Reads from the inner reader into the buffer even if the buffer still has data.
+    /// 
+    /// Returns the number of bytes added, zero meaning the inner reader has reached its end.
+    #[async_fn]
+    pub fn poll_read_more(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let Self{ buf, inner} = &mut (*self);
+
+        if buf.size() == buf.capacity() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                "Read Buffer is full!"
+            )));
+        }
+
+        pin!(buf.read_more(inner)).poll(cx)
     }
 
     pub fn poll_fill_buf(mut self: Pin<&mut Self>, cx:&mut Context<'_>) -> Poll<io::Result<&[u8]>> {
         let Self{ buf, inner} = &mut (*self);
 
-        if pin!(buf.read_more(inner)).poll(cx).is_ready() {
-            //SAFETY: No race because we buf is owned.
-            let slice = unsafe{ buf.mut_buffer() };
-            Poll::Ready(Ok(
-                //SAFETY: Pointing to owned data.
-                unsafe{
-                    std::slice::from_raw_parts(
-                        slice.as_mut_ptr(),
-                        slice.len()
-                    )
-                }
-            ))
-        } else {
-            Poll::Pending
+        if buf.size() == 0 {
+            match pin!(buf.read_more(inner)).poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(_)) => {}
+            }
         }
+
+        //SAFETY: No race because we buf is owned.
+        let slice = unsafe{ buf.mut_buffer() };
+        Poll::Ready(Ok(
+            //SAFETY: Pointing to owned data.
+            unsafe{
+                std::slice::from_raw_parts(
+                    slice.as_mut_ptr(),
+                    slice.len()
+                )
+            }
+        ))
     }
 
     pub fn fill_buf(&mut self) -> impl Future<Output = io::Result<&[u8]>> {
@@ -81,6 +109,22 @@ impl<R: AsyncRead + Unpin> AsyncBufReader<R> {
     pub fn buffer(&self) -> &[u8] {
         self.buf.buffer()
     }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
 }
 
 impl<R: AsyncRead> Stream for AsyncBufReader<R> {
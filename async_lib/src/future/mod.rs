@@ -4,6 +4,7 @@ pub mod fs;
 pub mod io;
 pub mod net;
 mod promise;
+pub mod time;
 
 pub use promise::Promise;
 
@@ -1,4 +1,69 @@
 pub use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+/// Implements AsyncRead and AsyncWrite for a socket, waiting on the reactor
+/// instead of polling again while the socket would block.
+macro_rules! registered_io {
+    ($name:ident) => {
+        impl crate::io::AsyncRead for $name {
+            fn poll_read(self:Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+                use std::io::Read;
+                let this = self.get_mut();
+                this.registration.poll_io(cx, Interest::Read, ||(&this.io).read(buf))
+            }
+        }
+
+        impl crate::io::AsyncWrite for $name {
+            fn poll_write(self:Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+                use std::io::Write;
+                let this = self.get_mut();
+                this.registration.poll_io(cx, Interest::Write, ||(&this.io).write(buf))
+            }
+
+            fn poll_flush(self:Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+                use std::io::Write;
+                let this = self.get_mut();
+                this.registration.poll_io(cx, Interest::Write, ||(&this.io).flush())
+            }
+
+            fn poll_close(self:Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+                self.poll_flush(cx)
+            }
+        }
+    };
+}
 
 mod tcp;
 pub use tcp::*;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+pub use unix::*;
+
+/// Blocks until one of the listeners has a connection waiting or the timeout
+/// has passed, for accept loops running on their own thread.
+#[cfg(unix)]
+pub fn wait_for_connection(listeners:&[&dyn std::os::fd::AsRawFd], timeout:Duration) -> crate::io::Result<bool> {
+    let mut fds: Vec<libc::pollfd> = listeners.iter()
+        .map(|listener|libc::pollfd{ fd: listener.as_raw_fd(), events: libc::POLLIN, revents: 0 })
+        .collect();
+
+    match unsafe{ libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout.as_millis() as libc::c_int) } {
+        count if count < 0 => {
+            let e = crate::io::Error::last_os_error();
+            if e.kind() == crate::io::ErrorKind::Interrupted {
+                Ok(false)
+            } else {
+                Err(e)
+            }
+        },
+        count => Ok(count > 0)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn wait_for_connection<T>(_:&[&T], timeout:Duration) -> crate::io::Result<bool> {
+    std::thread::sleep(timeout.min(Duration::from_millis(1)));
+    Ok(true)
+}
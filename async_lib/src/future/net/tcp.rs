@@ -1,18 +1,18 @@
 use std::{
     net,
-    time::Duration,
     task::{Context, Poll},
     async_iter::AsyncIterator,
     pin::Pin
 };
 use crate::io;
-use async_lib_macros::{deref_inner_async, async_fn};
+use async_lib_macros::async_fn;
+use crate::executor::reactor::{Interest, Registration};
 
 const STOP_BLOCK_ATTEMPT:u8 = 10;
-const READ_TIMEOUT:Duration = Duration::from_millis(500);
-const WRITE_TIMEOUT:Duration = Duration::from_secs(1);
 
 pub struct TcpListener {
+    //Dropped first, so it is removed from the reactor before the socket is closed.
+    registration: Registration,
     io: net::TcpListener,
     nonblocking: io::Result<bool>
 }
@@ -24,6 +24,44 @@ impl TcpListener {
         )
     }
 
+    /// Binds an IPv6 socket that does not also accept IPv4 connections, so an
+    /// IPv4 address can be bound to the same port.
+    #[cfg(unix)]
+    pub fn bind_v6_only(addr: net::SocketAddrV6) -> io::Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        let fd = unsafe { libc::socket(libc::AF_INET6, libc::SOCK_STREAM, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        //SAFETY: The socket was just created, so nothing else owns it and it is closed if binding fails.
+        let io = unsafe { net::TcpListener::from_raw_fd(fd) };
+
+        let on: libc::c_int = 1;
+        let size = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let ptr = &on as *const libc::c_int as *const libc::c_void;
+        if unsafe { libc::setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, ptr, size) } != 0
+            || unsafe { libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR, ptr, size) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut sockaddr: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+        sockaddr.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+        sockaddr.sin6_port = addr.port().to_be();
+        sockaddr.sin6_flowinfo = addr.flowinfo();
+        sockaddr.sin6_addr = libc::in6_addr { s6_addr: addr.ip().octets() };
+        sockaddr.sin6_scope_id = addr.scope_id();
+
+        let ptr = &sockaddr as *const libc::sockaddr_in6 as *const libc::sockaddr;
+        let size = std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
+        if unsafe { libc::bind(fd, ptr, size) } != 0 || unsafe { libc::listen(fd, 128) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Self::from(io)
+    }
+
     fn from(io: net::TcpListener) -> io::Result<Self> {
         let mut nonblocking = Ok(false);
 
@@ -37,6 +75,7 @@ impl TcpListener {
         }
         
         Ok(Self{
+            registration: Registration::new(&io)?,
             io,
             nonblocking
         })
@@ -63,20 +102,11 @@ impl TcpListener {
 
     #[async_fn]
     pub fn poll_accept(self: Pin<&mut Self>, cx:&Context<'_>) -> Poll<io::Result<(TcpStream, super::SocketAddr)>> {
-        match self.io.accept() {
-            Ok((inner, addr)) => Poll::Ready(
-                TcpStream::from(inner).map(|stream|{
-                    (stream, addr)
-                })
-            ),
-            Err(e) => if *(self.nonblocking.as_ref().unwrap_or(&false))
-                                && e.kind() == io::ErrorKind::WouldBlock {
-                cx.waker().wake_by_ref();
-                Poll::Pending
-            } else {
-                Poll::Ready(Err(e))
-            }
+        if !*(self.nonblocking.as_ref().unwrap_or(&false)) {
+            return Poll::Ready(self.sync_accept());
         }
+
+        self.registration.poll_io(cx, Interest::Read, ||self.sync_accept())
     }
 
     pub fn sync_accept(&self) -> io::Result<(TcpStream, super::SocketAddr)> {
@@ -94,8 +124,10 @@ impl TcpListener {
     }
 
     pub fn try_clone(&self) -> io::Result<Self> {
+        let io = self.io.try_clone()?;
         Ok(Self{
-            io: self.io.try_clone()?,
+            registration: Registration::new(&io)?,
+            io,
             nonblocking: self.nonblocking.as_ref()
                 .map(|r|*r)
                 .map_err(|e|io::Error::new(
@@ -125,8 +157,8 @@ impl<'a> AsyncIterator for Incoming<'a> {
     }
 }
 
-#[deref_inner_async(Read, Write)]
 pub struct TcpStream {
+    registration: Registration,
     io: net::TcpStream
 }
 
@@ -148,28 +180,29 @@ impl TcpStream {
             }
         }
 
-        let mut attmpt:u8 = 0;
-        while let Err(e) = io.set_read_timeout(Some(READ_TIMEOUT)) {
-            attmpt += 1;
-            if attmpt > STOP_BLOCK_ATTEMPT {
-                return Err(e)
-            }
-        }
+        Ok(Self{
+            registration: Registration::new(&io)?,
+            io
+        })
+    }
 
-        let mut attmpt:u8 = 0;
-        while let Err(e) = io.set_write_timeout(Some(WRITE_TIMEOUT)) {
-            attmpt += 1;
-            if attmpt > STOP_BLOCK_ATTEMPT {
-                return Err(e)
-            }
-        }
+    pub fn local_addr(&self) -> io::Result<super::SocketAddr> {
+        self.io.local_addr()
+    }
 
-        Ok(Self{io})
+    pub fn peer_addr(&self) -> io::Result<super::SocketAddr> {
+        self.io.peer_addr()
     }
 
     pub fn try_clone(&self) -> io::Result<Self> {
-        Ok(Self{
-            io: self.io.try_clone()?
-        })
+        Self::from(self.io.try_clone()?)
     }
-}
\ No newline at end of file
+}
+
+registered_io!(TcpStream);
+
+impl std::os::fd::AsRawFd for TcpListener {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.io.as_raw_fd()
+    }
+}
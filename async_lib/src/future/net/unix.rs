@@ -0,0 +1,158 @@
+use std::{
+    os::unix::net,
+    path::Path,
+    task::{Context, Poll},
+    async_iter::AsyncIterator,
+    pin::Pin
+};
+use crate::io;
+use async_lib_macros::async_fn;
+use crate::executor::reactor::{Interest, Registration};
+
+const STOP_BLOCK_ATTEMPT:u8 = 10;
+
+pub use std::os::unix::net::SocketAddr as UnixSocketAddr;
+
+pub struct UnixListener {
+    //Dropped first, so it is removed from the reactor before the socket is closed.
+    registration: Registration,
+    io: net::UnixListener,
+    nonblocking: io::Result<bool>
+}
+
+impl UnixListener {
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from(
+            net::UnixListener::bind(path)?
+        )
+    }
+
+    fn from(io: net::UnixListener) -> io::Result<Self> {
+        let mut nonblocking = Ok(false);
+
+        let mut attmpt:u8 = 0;
+        while let Err(e) = io.set_nonblocking(true) {
+            attmpt += 1;
+            if attmpt > STOP_BLOCK_ATTEMPT {
+                nonblocking = Err(e);
+                break;
+            }
+        }
+
+        Ok(Self{
+            registration: Registration::new(&io)?,
+            io,
+            nonblocking
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        self.io.local_addr()
+    }
+
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()>{
+        match &self.nonblocking {
+            Ok(_) => {
+                self.nonblocking = Ok(nonblocking);
+                Ok(())
+            },
+            Err(e) => Err(
+                io::Error::new(
+                    e.kind(),
+                    e.to_string()
+                )
+            )
+        }
+    }
+
+    #[async_fn]
+    pub fn poll_accept(self: Pin<&mut Self>, cx:&Context<'_>) -> Poll<io::Result<(UnixStream, UnixSocketAddr)>> {
+        if !*(self.nonblocking.as_ref().unwrap_or(&false)) {
+            return Poll::Ready(self.sync_accept());
+        }
+
+        self.registration.poll_io(cx, Interest::Read, ||self.sync_accept())
+    }
+
+    pub fn sync_accept(&self) -> io::Result<(UnixStream, UnixSocketAddr)> {
+        match self.io.accept() {
+            Ok((inner, addr)) => Ok((
+                UnixStream::from(inner)?,
+                addr
+            )),
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn incoming(&mut self) -> UnixIncoming<'_> {
+        UnixIncoming { listener: self }
+    }
+
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let io = self.io.try_clone()?;
+        Ok(Self{
+            registration: Registration::new(&io)?,
+            io,
+            nonblocking: self.nonblocking.as_ref()
+                .map(|r|*r)
+                .map_err(|e|io::Error::new(
+                    e.kind(),
+                    e.to_string()
+                ))
+        })
+    }
+}
+
+pub struct UnixIncoming<'a> {
+    listener: &'a mut UnixListener
+}
+
+impl<'a> AsyncIterator for UnixIncoming<'a> {
+    type Item = io::Result<UnixStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<io::Result<UnixStream>>> {
+        let pin = Pin::new(&mut *self.listener);
+        if let Poll::Ready(result) = pin.poll_accept(cx) {
+            Poll::Ready(Some(
+                result.map(|conn|conn.0)
+            ))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub struct UnixStream {
+    registration: Registration,
+    io: net::UnixStream
+}
+
+impl UnixStream {
+    fn from(io: net::UnixStream) -> io::Result<Self> {
+        let mut attmpt:u8 = 0;
+        while let Err(e) = io.set_nonblocking(true) {
+            attmpt += 1;
+            if attmpt > STOP_BLOCK_ATTEMPT {
+                return Err(e)
+            }
+        }
+
+        Ok(Self{
+            registration: Registration::new(&io)?,
+            io
+        })
+    }
+
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Self::from(self.io.try_clone()?)
+    }
+}
+
+
+registered_io!(UnixStream);
+
+impl std::os::fd::AsRawFd for UnixListener {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.io.as_raw_fd()
+    }
+}
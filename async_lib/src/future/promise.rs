@@ -1,11 +1,11 @@
 use std::{
-    sync::{
-        mpsc::{Receiver, TryRecvError, channel}
-    },
     task::{Context, Poll},
     fmt
 };
-use crate::spawn_task;
+use crate::{
+    spawn_task,
+    executor::oneshot::{Receiver, channel}
+};
 
 pub struct Promise<R>(Receiver<R>);
 
@@ -13,7 +13,7 @@ impl<R:'static> Promise<R> {
 
     pub fn new(callback: impl FnOnce() -> R + 'static) -> Self {
         let (sender, receiver) = channel::<R>();
-        spawn_task(async move{sender.send(callback()).unwrap();} );
+        spawn_task(async move{sender.send(callback());} );
         
         Self(
             receiver
@@ -22,7 +22,7 @@ impl<R:'static> Promise<R> {
 
     pub fn future(future: impl Future<Output = R> + 'static) -> Self {
         let (sender, receiver) = channel::<R>();
-        spawn_task(async move{sender.send(future.await).unwrap()});
+        spawn_task(async move{sender.send(future.await)});
 
         Self(
             receiver
@@ -31,7 +31,7 @@ impl<R:'static> Promise<R> {
 
     pub fn callback(callback: impl FnOnce(Box<dyn FnOnce(R)>) + 'static) -> Self {
         let (sender, receiver) = channel::<R>();
-        let res= move |value:R| sender.send(value).unwrap();
+        let res= move |value:R| sender.send(value);
 
         spawn_task(async{callback(Box::new(res))});
         
@@ -67,15 +67,7 @@ impl<R:'static> Future for Promise<R> {
     type Output = R;
 
     fn poll(self: std::pin::Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<R> {
-        match self.0.try_recv() {
-            Ok(t) => Poll::Ready(t),
-            Err(e) => if e == TryRecvError::Empty {
-                ctx.waker().wake_by_ref();
-                Poll::Pending
-            } else {
-                panic!("Promise was disconnected from Executor!")
-            }
-        }
+        self.0.poll_recv(ctx).map(|value|value.expect("Promise was disconnected from Executor!"))
     }
 }
 
@@ -0,0 +1,95 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant}
+};
+use crate::executor::reactor::{self, TimerKey};
+
+pub struct Sleep {
+    deadline: Instant,
+    timer: Option<TimerKey>
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn is_elapsed(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    pub fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+        reactor::move_timer(&mut self.timer, deadline);
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if this.is_elapsed() {
+            reactor::cancel_timer(&mut this.timer);
+            Poll::Ready(())
+        } else {
+            reactor::set_timer(&mut this.timer, this.deadline, cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        reactor::cancel_timer(&mut self.timer);
+    }
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now() + duration)
+}
+
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep { deadline, timer: None }
+}
+
+pub struct Timeout<F: Future> {
+    future: F,
+    sleep: Sleep
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = io::Result<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        //SAFETY: future is never moved out of self.
+        let this = unsafe{ self.get_unchecked_mut() };
+        let future = unsafe{ Pin::new_unchecked(&mut this.future) };
+
+        if let Poll::Ready(value) = future.poll(cx) {
+            Poll::Ready(Ok(value))
+        } else if Pin::new(&mut this.sleep).poll(cx).is_ready() {
+            Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Future timed out!"
+            )))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Resolves to an Err of kind TimedOut if the future is not ready before the duration has passed.
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    timeout_at(Instant::now() + duration, future)
+}
+
+pub fn timeout_at<F: Future>(deadline: Instant, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: sleep_until(deadline)
+    }
+}
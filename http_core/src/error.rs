@@ -75,6 +75,7 @@ pub enum HttpErrorKind {
     Locked,
     FailedDependency,
     TooEarly,
+    UpgradeRequired,
     PreconditionRequired,
     TooManyRequests,
     RequestHeaderFieldsTooLarge,
@@ -134,6 +135,7 @@ impl HttpErrorKind {
             Self::Locked => "Locked",
             Self::FailedDependency => "Failed Dependency",
             Self::TooEarly => "Too Early",
+            Self::UpgradeRequired => "Upgrade Required",
             Self::PreconditionRequired => "Precondition Required",
             Self::TooManyRequests => "Too Many Requests",
             Self::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
@@ -195,6 +197,7 @@ impl Into<HttpStatus> for HttpErrorKind {
             Self::Locked => HttpStatus::Locked,
             Self::FailedDependency => HttpStatus::FailedDependency,
             Self::TooEarly => HttpStatus::TooEarly,
+            Self::UpgradeRequired => HttpStatus::UpgradeRequired,
             Self::PreconditionRequired => HttpStatus::PreconditionRequired,
             Self::TooManyRequests => HttpStatus::TooManyRequests,
             Self::RequestHeaderFieldsTooLarge => HttpStatus::RequestHeaderFieldsTooLarge,
@@ -5,6 +5,7 @@
 /// 
 pub use name::HeaderName;
 pub use value::HeaderValue;
+pub use types::{ExpectType, ExpectParamType};
 use std::collections::HashMap;
 use std::collections::hash_map::Iter;
 
@@ -42,6 +43,12 @@ impl Headers {
         )
     }
 
+    pub fn remove(&mut self, key:&str) -> Option<HeaderValue> {
+        self.0.remove(
+            &HeaderName::from(key)
+        )
+    }
+
     pub fn clear(&mut self) {
         self.0.clear();
     }
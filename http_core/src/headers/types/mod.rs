@@ -30,6 +30,7 @@ macro_rules! header_type_imports {
 }
 
 pub use super::{HttpHeader, HeaderName};
+use std::collections::HashMap;
 
 mod accept_value;
 mod age;
@@ -303,6 +304,8 @@ impl<'a> ContentRangeType<'a> {
     }
 }
 
+*/
+
 pub enum ExpectType<'a> {
     Contune,
     Extension(&'a str, ExpectParamType<'a>)
@@ -317,7 +320,7 @@ pub enum ExpectParamType<'a> {
 impl<'a> ExpectType<'a> {
     pub fn parse(string:&'a str) -> Self {
         let mut token = string.trim();
-        if token.to_ascii_uppercase().trim() == "100-continue" {
+        if token.eq_ignore_ascii_case("100-continue") {
             return ExpectType::Contune
         }
         let params:ExpectParamType<'a>;
@@ -337,7 +340,7 @@ impl<'a> ExpectType<'a> {
                         for str in value.split(";") {
                             let lines: Vec<_> = str.split("=").collect();
 
-                            let key = lines.get(0).unwrap().trim();
+                            let key = lines.first().unwrap().trim();
                             let value = lines.get(1).unwrap_or(&"").trim();
 
                             map.insert(key, value);
@@ -356,6 +359,7 @@ impl<'a> ExpectType<'a> {
     }
 }
 
+/*
 pub enum WildCardList<'a> {
     WildCard,
     List(Vec<&'a str>)
@@ -2,9 +2,12 @@
 pub mod error;
 pub mod headers;
 pub mod method;
+pub mod rate_limit;
 pub mod request;
 pub mod response;
+pub mod sse;
 pub mod status;
+pub mod upgrade;
 pub mod url;
 
 pub mod result {
@@ -14,6 +17,7 @@ pub mod result {
 }
 
 pub mod version {
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
     pub struct Version {
         pub major: u8,
         pub minor: u8
@@ -0,0 +1,331 @@
+/// Rate Limiting
+///
+/// Each client gets a token bucket that holds up to the limit and refills over
+/// the window, so short bursts are allowed while the average stays below it.
+///
+/// https://datatracker.ietf.org/doc/draft-ietf-httpapi-ratelimit-headers/
+///
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Mutex, Once,
+        atomic::{AtomicU32, AtomicU64, Ordering}
+    },
+    time::{Duration, Instant}
+};
+use async_lib::{
+    executor::{is_running, spawn_task},
+    net::TcpStream,
+    time::sleep
+};
+use crate::{
+    error::{HttpError, HttpErrorKind},
+    headers::Headers,
+    request::RequestBuilder
+};
+
+/// Picks the bucket a request is counted against, None leaves the request unlimited.
+pub type KeyFunction = fn(&RequestBuilder<TcpStream>) -> Option<String>;
+
+/// Counts requests by the ip address of the client.
+///
+/// Requests over a Unix socket have no address, and are not limited.
+pub fn client_ip(req:&RequestBuilder<TcpStream>) -> Option<String> {
+    req.peer_addr.map(|addr|addr.ip().to_string())
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant
+}
+
+pub struct RateLimiter {
+    limit: AtomicU32,
+    /// Milliseconds, kept as an atomic so it can be changed while running.
+    window: AtomicU64,
+    default: (u32, Duration),
+    key: KeyFunction,
+    buckets: Mutex<BTreeMap<String, Bucket>>,
+    eviction: Once
+}
+
+impl RateLimiter {
+    /// Allows limit requests per window from each client ip.
+    pub const fn new(limit:u32, window:Duration) -> Self {
+        Self::with_key(limit, window, client_ip)
+    }
+
+    pub const fn with_key(limit:u32, window:Duration, key:KeyFunction) -> Self {
+        Self {
+            limit: AtomicU32::new(limit),
+            window: AtomicU64::new(window.as_millis() as u64),
+            default: (limit, window),
+            key,
+            buckets: Mutex::new(BTreeMap::new()),
+            eviction: Once::new()
+        }
+    }
+
+    pub fn max_requests(&self) -> u32 {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window.load(Ordering::Relaxed))
+    }
+
+    /// Changes the limit while running, None goes back to the value it was created with.
+    ///
+    /// Clients keep the tokens they have left, up to the new limit.
+    pub fn set_limit(&self, limit:Option<u32>, window:Option<Duration>) {
+        let (default_limit, default_window) = self.default;
+        self.limit.store(limit.unwrap_or(default_limit), Ordering::Relaxed);
+        self.window.store(window.unwrap_or(default_window).as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Tokens added back to a bucket each second.
+    fn rate(&self) -> f64 {
+        self.max_requests() as f64 / self.window().as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn refill(&self, bucket:&mut Bucket, now:Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate()).min(self.max_requests() as f64);
+        bucket.updated = now;
+    }
+
+    /// Takes a token from the bucket of the client, None if the request has no key.
+    pub fn check(&'static self, req:&RequestBuilder<TcpStream>) -> Option<RateLimitStatus> {
+        let key = (self.key)(req)?;
+        self.start_eviction();
+
+        Some(self.take(key, Instant::now()))
+    }
+
+    fn take(&self, key:String, now:Instant) -> RateLimitStatus {
+        let limit = self.max_requests();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: limit as f64,
+            updated: now
+        });
+        self.refill(bucket, now);
+
+        let limited = bucket.tokens < 1.0;
+        if !limited {
+            bucket.tokens -= 1.0;
+        }
+
+        let rate = self.rate();
+        RateLimitStatus {
+            limit,
+            remaining: bucket.tokens.floor() as u32,
+            reset: seconds((limit as f64 - bucket.tokens) / rate),
+            window: self.window().as_secs(),
+            retry_after: limited.then(||seconds((1.0 - bucket.tokens) / rate).max(1))
+        }
+    }
+
+    /// Counts the request, failing with Too Many Requests once the client is over the limit.
+    ///
+    /// The status is kept on the request, so the headers can be added to the response.
+    /// When more than one limiter applies, the one with the least remaining is kept.
+    pub fn limit(&'static self, req:&mut RequestBuilder<TcpStream>) -> Result<(), HttpError> {
+        let status = match self.check(req) {
+            Some(status) => status,
+            None => return Ok(())
+        };
+
+        let limited = status.is_limited();
+        if req.rate_limit.as_ref().is_none_or(|current|status.is_stricter(current)) {
+            req.rate_limit = Some(status);
+        }
+
+        if limited {
+            Err(HttpError::new(HttpErrorKind::TooManyRequests, "Too many requests, try again later!"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Removes the buckets that have filled back up, as they are the same as a new one.
+    fn evict(&self) {
+        let now = Instant::now();
+        let full = self.window();
+
+        self.buckets.lock().unwrap()
+            .retain(|_, bucket|now.saturating_duration_since(bucket.updated) < full);
+    }
+
+    fn start_eviction(&'static self) {
+        self.eviction.call_once(||spawn_task(async move {
+            while is_running() {
+                sleep(self.window()).await;
+                self.evict();
+            }
+        }));
+    }
+}
+
+fn seconds(value:f64) -> u64 {
+    value.max(0.0).ceil() as u64
+}
+
+/// Quota left for a client after a request.
+#[derive(Clone, Debug)]
+pub struct RateLimitStatus {
+    limit: u32,
+    remaining: u32,
+    reset: u64,
+    window: u64,
+    retry_after: Option<u64>
+}
+
+impl RateLimitStatus {
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Seconds until the quota is full again.
+    pub fn reset(&self) -> u64 {
+        self.reset
+    }
+
+    /// Seconds until the next request is allowed, None if the request was allowed.
+    pub fn retry_after(&self) -> Option<u64> {
+        self.retry_after
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.retry_after.is_some()
+    }
+
+    fn is_stricter(&self, other:&Self) -> bool {
+        (self.is_limited() && !other.is_limited())
+            || (self.is_limited() == other.is_limited() && self.remaining < other.remaining)
+    }
+
+    /// Sets the RateLimit headers, and Retry-After if the request was limited.
+    pub fn set_headers(&self, headers:&mut Headers) {
+        headers.set("RateLimit-Limit", self.limit.to_string().as_str());
+        headers.set("RateLimit-Remaining", self.remaining.to_string().as_str());
+        headers.set("RateLimit-Reset", self.reset.to_string().as_str());
+        headers.set("RateLimit-Policy", format!("{};w={}", self.limit, self.window).as_str());
+
+        if let Some(seconds) = self.retry_after {
+            headers.set("Retry-After", seconds.to_string().as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn status(remaining:u32, retry_after:Option<u64>) -> RateLimitStatus {
+        RateLimitStatus {
+            limit: 10,
+            remaining,
+            reset: 0,
+            window: 10,
+            retry_after
+        }
+    }
+
+    #[test]
+    fn burst_to_empty() {
+        let limiter = RateLimiter::new(10, SECOND * 10);
+        let now = Instant::now();
+
+        for remaining in (0..10).rev() {
+            let status = limiter.take("client".to_string(), now);
+            assert!(!status.is_limited());
+            assert_eq!(status.remaining(), remaining);
+        }
+
+        let status = limiter.take("client".to_string(), now);
+        assert!(status.is_limited());
+        assert_eq!(status.remaining(), 0);
+        assert_eq!(status.reset(), 10);
+        assert_eq!(status.retry_after(), Some(1));
+
+        //Each client has its own bucket.
+        assert_eq!(limiter.take("other".to_string(), now).remaining(), 9);
+    }
+
+    #[test]
+    fn refill_after_half_window() {
+        let limiter = RateLimiter::new(10, SECOND * 10);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            limiter.take("client".to_string(), now);
+        }
+
+        let status = limiter.take("client".to_string(), now + SECOND * 5);
+        assert!(!status.is_limited());
+        assert_eq!(status.remaining(), 4);
+        assert_eq!(status.reset(), 6);
+
+        //Never refills past the limit.
+        let status = limiter.take("client".to_string(), now + SECOND * 60);
+        assert_eq!(status.remaining(), 9);
+    }
+
+    #[test]
+    fn set_limit_clamps_tokens() {
+        let limiter = RateLimiter::new(10, SECOND * 10);
+        let now = Instant::now();
+
+        limiter.take("client".to_string(), now);
+        limiter.take("client".to_string(), now);
+
+        limiter.set_limit(Some(4), None);
+        let status = limiter.take("client".to_string(), now);
+        assert_eq!(status.limit(), 4);
+        assert_eq!(status.remaining(), 3);
+
+        limiter.set_limit(None, None);
+        assert_eq!(limiter.max_requests(), 10);
+        assert_eq!(limiter.window(), SECOND * 10);
+        assert_eq!(limiter.take("client".to_string(), now).remaining(), 2);
+    }
+
+    #[test]
+    fn retry_after_rounding() {
+        //One token every 1.5 seconds.
+        let limiter = RateLimiter::new(2, SECOND * 3);
+        let now = Instant::now();
+
+        limiter.take("client".to_string(), now);
+        limiter.take("client".to_string(), now);
+        assert_eq!(limiter.take("client".to_string(), now).retry_after(), Some(2));
+
+        //Almost a full token is still rounded up to a second.
+        let limiter = RateLimiter::new(10, SECOND * 10);
+        for _ in 0..10 {
+            limiter.take("client".to_string(), now);
+        }
+        let status = limiter.take("client".to_string(), now + Duration::from_millis(999));
+        assert!(status.is_limited());
+        assert_eq!(status.retry_after(), Some(1));
+    }
+
+    #[test]
+    fn stricter_status() {
+        assert!(status(5, Some(1)).is_stricter(&status(0, None)));
+        assert!(!status(0, None).is_stricter(&status(5, Some(1))));
+
+        assert!(status(2, None).is_stricter(&status(3, None)));
+        assert!(!status(3, None).is_stricter(&status(2, None)));
+        assert!(!status(3, None).is_stricter(&status(3, None)));
+
+        assert!(status(0, Some(2)).is_stricter(&status(1, Some(1))));
+    }
+}
@@ -3,16 +3,21 @@ use crate::{
     headers::Headers,
     method::Method,
     version::Version,
-    error::HttpError
+    error::{HttpError, ValidHttpError},
+    rate_limit::RateLimitStatus
 };
 use util::json::{JsonValue, JsonRef};
 use async_lib::{
-    io::{AsyncRead, AsyncBufReader},
+    io::AsyncRead,
     net::TcpStream
 };
 use std::{
     collections::HashMap,
     fmt,
+    net::SocketAddr,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll}
 };
 
 pub enum BodyDataType {
@@ -60,35 +65,108 @@ pub struct FileData {
     pub data: Vec<u8>
 }
 
+/// Source of a request body.
+/// 
+/// Implemented by each protocol so the body is only read from the
+/// connection once it is needed, and so the protocol knows where the
+/// body ends.
+pub trait BodySource {
+    fn poll_next_chunk(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, HttpError>>>;
+
+    /// Header fields sent after the body, only available once the body is done.
+    fn take_trailers(self: Pin<&mut Self>) -> Option<Headers> {
+        None
+    }
+
+    /// False while the client is still waiting to be told to send the body.
+    fn is_started(&self) -> bool {
+        true
+    }
+}
+
 pub struct RequestBuilder<STREAM: AsyncRead> {
     pub url:Url,
     pub version:Version,
     pub method: Method,
     pub headers: Headers,
-    buffer: Option<AsyncBufReader<*mut STREAM>>,
-    body_used:bool
+    pub trailers: Headers,
+    /// Address of the listener the request came in on, None for a Unix socket.
+    pub local_addr: Option<SocketAddr>,
+    /// Address of the client, None for a Unix socket.
+    pub peer_addr: Option<SocketAddr>,
+    /// Quota left for the client, set by the rate limiters the request passed through.
+    pub rate_limit: Option<RateLimitStatus>,
+    source: Option<Pin<Box<dyn BodySource>>>,
+    buffer: Vec<u8>,
+    body_used:bool,
+    stream: PhantomData<*mut STREAM>
 }
 
 impl<S: AsyncRead> RequestBuilder<S> {
-    pub fn new(url:Url, method:Method, headers:Headers, version:Version, stream:Option<*mut S>) -> Self{
+    pub fn new(url:Url, method:Method, headers:Headers, version:Version, body:Option<Pin<Box<dyn BodySource>>>) -> Self{
         Self {
             url, method, headers,
             version,
-            buffer: stream.map(|s|AsyncBufReader::new(s)),
-            body_used: false
+            trailers: Headers::new(),
+            local_addr: None,
+            peer_addr: None,
+            rate_limit: None,
+            source: body,
+            buffer: Vec::new(),
+            body_used: false,
+            stream: PhantomData
         }
     }
 
-    pub fn body(&mut self) -> Result<Option<&[u8]>, &'static str> {
+    pub fn has_body(&self) -> bool {
+        self.source.is_some()
+    }
+
+    async fn next_chunk(&mut self) -> Option<Result<Vec<u8>, HttpError>> {
+        let source = self.source.as_mut()?;
+
+        let next = std::future::poll_fn(|cx|source.as_mut().poll_next_chunk(cx)).await;
+        if next.is_none() && let Some(trailers) = source.as_mut().take_trailers() {
+            self.trailers = trailers;
+        }
+
+        next
+    }
+
+    pub async fn body(&mut self) -> Result<Option<&[u8]>, HttpError> {
         if self.body_used {
-            Err("Request Body is already used!")
-        } else {
-            self.body_used = false;
-            match &self.buffer {
-                Some(reader) => Ok(Some(reader.buffer())),
-                None => Ok(None)
-            }
+            return "Request Body is already used!".send();
+        }
+
+        self.body_used = true;
+        if self.source.is_none() {
+            return Ok(None);
+        }
+
+        while let Some(chunk) = self.next_chunk().await {
+            self.buffer.extend(chunk?);
         }
+
+        Ok(Some(&self.buffer))
+    }
+
+    /// Reads and discards any of the body the handler did not read,
+    /// leaving the connection at the start of the next request.
+    /// 
+    /// Fails if the client was never told to send the body, as there is
+    /// no way to know if it will still be sent.
+    pub async fn drain_body(&mut self) -> Result<(), HttpError> {
+        if let Some(source) = &self.source && !source.is_started() {
+            self.source = None;
+            return "Request body was never sent!".send();
+        }
+
+        while let Some(chunk) = self.next_chunk().await {
+            chunk?;
+        }
+
+        self.source = None;
+        Ok(())
     }
 }
 
@@ -138,12 +216,34 @@ impl<P> Request<P>{
         unsafe{ &(*self.builder).method }
     }
 
-    pub fn body(&mut self) -> Result<Option<&[u8]>, &'static str> {
-        unsafe{ (*self.builder).body() }
+    pub fn trailers(&self) -> &Headers {
+        unsafe{ &(*self.builder).trailers }
+    }
+
+    pub fn local_addr(&self) -> Option<&SocketAddr> {
+        unsafe{ (*self.builder).local_addr.as_ref() }
+    }
+
+    pub fn peer_addr(&self) -> Option<&SocketAddr> {
+        unsafe{ (*self.builder).peer_addr.as_ref() }
+    }
+
+    pub fn rate_limit(&self) -> Option<&RateLimitStatus> {
+        unsafe{ (*self.builder).rate_limit.as_ref() }
+    }
+
+    /// Id of the last server-sent event the client received before reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.headers().get("Last-Event-ID")
+            .and_then(|value|value.ref_str().ok())
+    }
+
+    pub async fn body(&mut self) -> Result<Option<&[u8]>, HttpError> {
+        unsafe{ (*self.builder).body().await }
     }
 
-    pub fn data(&mut self) -> Result<Option<BodyData>, &'static str> {
-        match self.body()? {
+    pub async fn data(&mut self) -> Result<Option<BodyData>, HttpError> {
+        match self.body().await? {
             Some(_) => todo!("Parse Body Data"),
             None => Ok(None)
         }
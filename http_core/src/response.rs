@@ -2,10 +2,17 @@ use crate::{
     headers::Headers,
     error::{HttpError, ValidHttpError},
     status::HttpStatus,
-    result::Result
+    result::Result,
+    upgrade::Upgrade
 };
 use util::json::{JsonValue, stringify};
-use std::{collections::LinkedList, fmt};
+use async_lib::io::Stream;
+use std::{
+    collections::LinkedList,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll}
+};
 use html::Node;
 
 enum ResponseError {
@@ -75,11 +82,27 @@ impl From<char> for Chunk {
     }
 }
 
+/// Body written after any buffered chunks, when its length is not known up front.
+/// 
+/// Implemented for every Stream of chunks.
+pub trait BodyStream {
+    fn poll_next_chunk(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Chunk>>;
+}
+
+impl<S, T> BodyStream for S where S: Stream<Item = T>, T: Into<Chunk> {
+    fn poll_next_chunk(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Chunk>> {
+        self.poll_next(cx).map(|option|option.map(|value|value.into()))
+    }
+}
+
 pub struct Response {
     pub status: HttpStatus,
     pub headers: Headers,
     pub body: LinkedList<Chunk>,
-    pub sent: bool
+    pub stream: Option<Pin<Box<dyn BodyStream>>>,
+    pub sent: bool,
+    /// Takes over the connection after a 101 Switching Protocols response.
+    pub upgrade: Option<Upgrade>
 }
 
 #[allow(dead_code)]
@@ -89,7 +112,9 @@ impl Response {
             status: status,
             headers: headers.unwrap_or(Headers::new()),
             body: LinkedList::new(),
-            sent: false
+            stream: None,
+            sent: false,
+            upgrade: None
         }
     }
 
@@ -133,6 +158,15 @@ impl Response {
         }
     }
 
+    pub fn stream<S, T>(&mut self, stream:S) -> Result<&mut Self> where S: Stream<Item = T> + 'static, T: Into<Chunk> {
+        if self.sent {
+            ResponseError::ResponseSent.send()
+        } else {
+            self.stream = Some(Box::pin(stream));
+            Ok(self)
+        }
+    }
+
     pub fn from<T>(body:T) -> Self where T: Into<Chunk>{
         let mut chunks = LinkedList::new();
         chunks.push_back(body.into());
@@ -140,7 +174,9 @@ impl Response {
             status: HttpStatus::Ok,
             headers: Headers::new(),
             body: chunks,
-            sent: false
+            stream: None,
+            sent: false,
+            upgrade: None
         }
     }
 
@@ -153,7 +189,9 @@ impl Response {
         Self {
             status: HttpStatus::Ok,
             headers, body,
-            sent: false
+            stream: None,
+            sent: false,
+            upgrade: None
         }
     }
 
@@ -166,7 +204,9 @@ impl Response {
         Self {
             status: HttpStatus::Ok,
             headers, body,
-            sent: false
+            stream: None,
+            sent: false,
+            upgrade: None
         }
     }
 
@@ -181,7 +221,20 @@ impl Response {
             status: kind.into(),
             headers: Headers::new(),
             body,
-            sent: false
+            stream: None,
+            sent: false,
+            upgrade: None
+        }
+    }
+
+    pub fn from_stream<S, T>(stream:S) -> Self where S: Stream<Item = T> + 'static, T: Into<Chunk> {
+        Self {
+            status: HttpStatus::Ok,
+            headers: Headers::new(),
+            body: LinkedList::new(),
+            stream: Some(Box::pin(stream)),
+            sent: false,
+            upgrade: None
         }
     }
 
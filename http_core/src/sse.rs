@@ -0,0 +1,326 @@
+/// Server-Sent Events
+///
+/// https://html.spec.whatwg.org/multipage/server-sent-events.html
+///
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant}
+};
+use async_lib::{
+    io::Stream,
+    time::{Sleep, sleep}
+};
+use crate::response::Response;
+
+/// Time without an event before a comment is sent to keep the connection open.
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Single event sent to the client.
+#[derive(Clone, Debug, Default)]
+pub struct Event {
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>
+}
+
+/// Line breaks would start a new field, so they are removed from single line fields.
+fn single_line(value:&str) -> String {
+    value.chars().filter(|c|*c != '\n' && *c != '\r').collect()
+}
+
+impl Event {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Event only containing a comment, which is ignored by the client.
+    pub fn comment(text:&str) -> Self {
+        Self {
+            comment: Some(String::from(text)),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the event type, the client dispatches "message" if it is not set.
+    pub fn event(mut self, name:&str) -> Self {
+        self.event = Some(single_line(name));
+        self
+    }
+
+    /// Appends to the event data, each line is sent in its own data field.
+    pub fn data(mut self, data:&str) -> Self {
+        match &mut self.data {
+            Some(value) => {
+                value.push('\n');
+                value.push_str(data);
+            },
+            None => self.data = Some(String::from(data))
+        }
+        self
+    }
+
+    /// Sets the id the client sends back in Last-Event-ID when reconnecting.
+    pub fn id(mut self, id:&str) -> Self {
+        self.id = Some(single_line(id).replace('\0', ""));
+        self
+    }
+
+    /// Sets how long the client waits before reconnecting.
+    pub fn retry(mut self, delay:Duration) -> Self {
+        self.retry = Some(delay);
+        self
+    }
+}
+
+/// Lines can end in CRLF, LF or CR.
+fn split_lines(text:&str) -> impl Iterator<Item = &str> {
+    text.split("\r\n").flat_map(|line|line.split(['\n', '\r']))
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(comment) = &self.comment {
+            for line in split_lines(comment) {
+                writeln!(f, ": {}", line)?;
+            }
+        }
+
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {}", event)?;
+        }
+
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {}", id)?;
+        }
+
+        if let Some(retry) = &self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+
+        if let Some(data) = &self.data {
+            for line in split_lines(data) {
+                writeln!(f, "data: {}", line)?;
+            }
+        }
+
+        writeln!(f)
+    }
+}
+
+/// Formats each event and sends a comment whenever the stream has been idle
+/// longer then the keep alive.
+pub struct EventStream<S: Stream<Item = Event>> {
+    inner: Pin<Box<S>>,
+    keep_alive: Option<(Duration, Sleep)>
+}
+
+impl<S: Stream<Item = Event>> EventStream<S> {
+    pub fn new(inner:S, keep_alive:Option<Duration>) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            keep_alive: keep_alive.map(|interval|(interval, sleep(interval)))
+        }
+    }
+}
+
+impl<S: Stream<Item = Event>> Stream for EventStream<S> {
+    type Item = String;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                if let Some((interval, timer)) = &mut this.keep_alive {
+                    timer.reset(Instant::now() + *interval);
+                }
+
+                Poll::Ready(Some(event.to_string()))
+            },
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => match &mut this.keep_alive {
+                Some((interval, timer)) => match Pin::new(&mut *timer).poll(cx) {
+                    Poll::Ready(()) => {
+                        timer.reset(Instant::now() + *interval);
+                        Poll::Ready(Some(String::from(": keep-alive\n\n")))
+                    },
+                    Poll::Pending => Poll::Pending
+                },
+                None => Poll::Pending
+            }
+        }
+    }
+}
+
+struct Channel {
+    queue: VecDeque<Event>,
+    senders: usize,
+    closed: bool,
+    waker: Option<Waker>
+}
+
+/// Sends events to a response created from the matching EventReceiver.
+///
+/// The stream ends once every sender has been dropped.
+pub struct EventSender(Arc<Mutex<Channel>>);
+
+impl EventSender {
+    /// Queues the event, returning false if the client has disconnected.
+    pub fn send(&self, event:Event) -> bool {
+        let mut channel = self.0.lock().unwrap();
+        if channel.closed {
+            false
+        } else {
+            channel.queue.push_back(event);
+            if let Some(waker) = channel.waker.take() {
+                waker.wake();
+            }
+            true
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.lock().unwrap().closed
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.0.lock().unwrap().senders += 1;
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let mut channel = self.0.lock().unwrap();
+        channel.senders -= 1;
+
+        //The stream ends once the last sender is gone.
+        if channel.senders == 0 && let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+pub struct EventReceiver(Arc<Mutex<Channel>>);
+
+impl Stream for EventReceiver {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut channel = self.0.lock().unwrap();
+
+        match channel.queue.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if channel.senders == 0 => Poll::Ready(None),
+            None => {
+                channel.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().closed = true;
+    }
+}
+
+/// Creates a sender that can keep writing events to a response after the handler returns.
+pub fn channel() -> (EventSender, EventReceiver) {
+    let channel = Arc::new(Mutex::new(Channel {
+        queue: VecDeque::new(),
+        senders: 1,
+        closed: false,
+        waker: None
+    }));
+
+    (EventSender(channel.clone()), EventReceiver(channel))
+}
+
+impl Response {
+    /// Response that stays open sending each event as it is ready, with a
+    /// comment sent after the default keep alive without any events.
+    pub fn from_events<S>(events:S) -> Self where S: Stream<Item = Event> + 'static {
+        Self::from_events_with_keep_alive(events, Some(DEFAULT_KEEP_ALIVE))
+    }
+
+    pub fn from_events_with_keep_alive<S>(events:S, keep_alive:Option<Duration>) -> Self where S: Stream<Item = Event> + 'static {
+        let mut resp = Self::from_stream(EventStream::new(events, keep_alive));
+        resp.headers.set("Content-Type", "text/event-stream");
+        resp.headers.set("Cache-Control", "no-cache");
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn poll_next<S: Stream + Unpin>(stream:&mut S) -> Poll<Option<S::Item>> {
+        Pin::new(stream).poll_next(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn data_lines() {
+        assert_eq!(Event::new().data("hello").to_string(), "data: hello\n\n");
+        assert_eq!(Event::new().data("a").data("b").to_string(), "data: a\ndata: b\n\n");
+        assert_eq!(Event::new().data("a\r\nb\nc\rd").to_string(), "data: a\ndata: b\ndata: c\ndata: d\n\n");
+        assert_eq!(Event::new().data("a\n\nb").to_string(), "data: a\ndata: \ndata: b\n\n");
+        assert_eq!(Event::new().data("").to_string(), "data: \n\n");
+    }
+
+    #[test]
+    fn fields() {
+        let event = Event::new()
+            .event("update")
+            .id("42")
+            .retry(Duration::from_secs(3))
+            .data("{}");
+        assert_eq!(event.to_string(), "event: update\nid: 42\nretry: 3000\ndata: {}\n\n");
+
+        //Line breaks and NULL would change what the client reads.
+        let event = Event::new().event("up\r\ndate").id("4\n2\0");
+        assert_eq!(event.to_string(), "event: update\nid: 42\n\n");
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(Event::comment("hi").to_string(), ": hi\n\n");
+        assert_eq!(Event::comment("a\r\nb\rc").to_string(), ": a\n: b\n: c\n\n");
+        assert_eq!(Event::comment("note").data("x").to_string(), ": note\ndata: x\n\n");
+    }
+
+    #[test]
+    fn keep_alive() {
+        let (sender, receiver) = channel();
+        let mut stream = EventStream::new(receiver, Some(Duration::ZERO));
+        assert_eq!(poll_next(&mut stream), Poll::Ready(Some(String::from(": keep-alive\n\n"))));
+
+        sender.send(Event::new().data("x"));
+        assert_eq!(poll_next(&mut stream), Poll::Ready(Some(String::from("data: x\n\n"))));
+
+        drop(sender);
+        assert_eq!(poll_next(&mut stream), Poll::Ready(None));
+    }
+
+    #[test]
+    fn no_keep_alive() {
+        let (sender, receiver) = channel();
+        let mut stream = EventStream::new(receiver, None);
+        assert_eq!(poll_next(&mut stream), Poll::Pending);
+        assert!(sender.send(Event::new()));
+
+        drop(stream);
+        assert!(!sender.send(Event::new()));
+        assert!(sender.is_closed());
+    }
+}
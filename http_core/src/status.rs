@@ -60,6 +60,7 @@ pub enum HttpStatus {
     Locked,
     FailedDependency,
     TooEarly,
+    UpgradeRequired,
     PreconditionRequired,
     TooManyRequests,
     RequestHeaderFieldsTooLarge,
@@ -137,6 +138,7 @@ impl HttpStatus {
             Self::Locked => "LOCKED",
             Self::FailedDependency => "FAILED DEPENDENCY",
             Self::TooEarly => "TOO EARLY",
+            Self::UpgradeRequired => "UPGRADE REQUIRED",
             Self::PreconditionRequired => "PRECONDITION REQUIRED",
             Self::TooManyRequests => "TOO MANY REQUESTS",
             Self::RequestHeaderFieldsTooLarge => "REQUEST HEADER FIELDS TOO LARGE",
@@ -214,6 +216,7 @@ impl HttpStatus {
             Self::Locked => 423,
             Self::FailedDependency => 424,
             Self::TooEarly => 425,
+            Self::UpgradeRequired => 426,
             Self::PreconditionRequired => 428,
             Self::TooManyRequests => 429,
             Self::RequestHeaderFieldsTooLarge => 431,
@@ -0,0 +1,85 @@
+/// Protocol Upgrades
+/// 
+/// RFC-9110 7.8
+/// https://datatracker.ietf.org/doc/html/rfc9110#section-7.8
+/// 
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll}
+};
+use async_lib::io::{AsyncRead, AsyncWrite};
+
+/// Called with the connection once the 101 Switching Protocols response has been sent.
+pub type Upgrade = Box<dyn FnOnce(Upgraded) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Object safe version of AsyncRead + AsyncWrite, so any connection can be handed over.
+trait RawStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+impl<T: AsyncRead + AsyncWrite> RawStream for T {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        AsyncRead::poll_read(self, cx, buf)
+    }
+
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
+
+/// Connection taken over after an upgrade.
+/// 
+/// Anything the client sent after the request that was already buffered is
+/// read first.
+pub struct Upgraded {
+    buffered: Vec<u8>,
+    io: Pin<Box<dyn RawStream>>
+}
+
+impl Upgraded {
+    pub fn new<S: AsyncRead + AsyncWrite + 'static>(buffered:Vec<u8>, io:S) -> Self {
+        Self {
+            buffered,
+            io: Box::pin(io)
+        }
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if !self.buffered.is_empty() {
+            let amt = buf.len().min(self.buffered.len());
+            buf[..amt].copy_from_slice(&self.buffered[..amt]);
+            self.buffered.drain(..amt);
+            return Poll::Ready(Ok(amt));
+        }
+
+        self.io.as_mut().poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.io.as_mut().poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io.as_mut().poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io.as_mut().poll_close(cx)
+    }
+}
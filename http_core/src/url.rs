@@ -5,8 +5,13 @@
 ///////////////////////////////////////////////////////////////
 /// URL = "http:" "//" host [: port] [abs_path ["?" query]] ["#" hash]
 ///////////////////////////////////////////////////////////////
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::Ipv6Addr
+};
 
+const HTTP_DEFAULT:u16 = 80;
 const HTTPS_DEFAULT:u16 = 443;
 
 #[derive(Debug)]
@@ -49,7 +54,7 @@ impl TryFrom<String> for Protocol {
     type Error = String;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.to_uppercase().as_str() {
-            "HTTPS" => Ok(Self::Http),
+            "HTTPS" => Ok(Self::Https),
             "HTTP" => Ok(Self::Http),
             _ => Err(format!("{} is not an accepted protocol!", value))
         }
@@ -59,63 +64,37 @@ impl TryFrom<String> for Protocol {
 #[derive(Debug)]
 pub enum Hostname {
     Ipv4(u8, u8, u8, u8),
+    Ipv6(Ipv6Addr),
     Text(String),
     None
 }
 
 fn get_ipv4(value:&str) -> Option<Hostname> {
-    let list: Vec<_> = value.split(".").collect();
+    let list: Vec<u8> = value.split(".")
+        .map(|part|part.parse().ok())
+        .collect::<Option<_>>()?;
 
-    if list.len() != 4 {
-        return None
+    match list[..] {
+        [first, second, third, fourth] => Some(Hostname::Ipv4(first, second, third, fourth)),
+        _ => None
     }
+}
+
+/// IPv6 addresses are wrapped in brackets inside a url.
+/// 
+/// RFC-3986 3.2.2
+/// https://datatracker.ietf.org/doc/html/rfc3986#section-3.2.2
+fn get_ipv6(value:&str) -> Option<Hostname> {
+    let value = value.strip_prefix('[')
+        .and_then(|value|value.strip_suffix(']'))
+        .unwrap_or(value);
 
-    let first:u8 = match list.get(0) {
-        Some(text) =>{
-            match text.parse() {
-                Ok(number) => number,
-                Err(_) => return None
-            }
-        },
-        None => return None
-    };
-
-    let second:u8 = match list.get(0) {
-        Some(text) =>{
-            match text.parse() {
-                Ok(number) => number,
-                Err(_) => return None
-            }
-        },
-        None => return None
-    };
-
-    let third:u8 = match list.get(0) {
-        Some(text) =>{
-            match text.parse() {
-                Ok(number) => number,
-                Err(_) => return None
-            }
-        },
-        None => return None
-    };
-
-    let fourth:u8 = match list.get(0) {
-        Some(text) =>{
-            match text.parse() {
-                Ok(number) => number,
-                Err(_) => return None
-            }
-        },
-        None => return None
-    };
-
-    Some(Hostname::Ipv4(first, second, third, fourth))
+    value.parse().ok().map(Hostname::Ipv6)
 }
 
 impl From<String> for Hostname {
     fn from(value: String) -> Hostname {
-        get_ipv4(&value).unwrap_or(
+        get_ipv4(&value).or_else(||get_ipv6(&value)).unwrap_or(
             Hostname::Text(value)
         )
     }
@@ -123,12 +102,23 @@ impl From<String> for Hostname {
 
 impl From<&str> for Hostname {
     fn from(value: &str) -> Hostname {
-        get_ipv4(&value).unwrap_or(
+        get_ipv4(&value).or_else(||get_ipv6(value)).unwrap_or(
             Hostname::Text(value.to_string())
         )
     }
 }
 
+impl fmt::Display for Hostname {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ipv4(first, second, third, fourth) => write!(f, "{}.{}.{}.{}", first, second, third, fourth),
+            Self::Ipv6(addr) => write!(f, "[{}]", addr),
+            Self::Text(value) => write!(f, "{}", value),
+            Self::None => Ok(())
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Url {
     pub hashvalue: String,
@@ -170,6 +160,14 @@ impl Url {
         self.password = Some(password);
     }
 
+    /// Hostname and port, with the port left off when it is the default for the protocol.
+    pub fn host(&self) -> String {
+        match (&self.protocol, self.port) {
+            (Protocol::Http, HTTP_DEFAULT) | (Protocol::Https, HTTPS_DEFAULT) => self.hostname.to_string(),
+            _ => format!("{}:{}", self.hostname, self.port)
+        }
+    }
+
     pub fn pathname(&self) -> String {
         String::from("/") + 
             &self.path.join("/")
@@ -17,9 +17,10 @@ pub(crate) struct Path {
 
 pub(crate) struct RouterAttributes {
     pub(crate) path: Path,
-    //default = "All"
-    pub(crate) methods:String
-    
+    //default = "ALL", every method except OPTIONS
+    pub(crate) methods:String,
+    //default = None, path to a static RateLimiter
+    pub(crate) rate_limit:Option<syn::Path>
 }
 
 impl Parse for RouterAttributes {
@@ -38,6 +39,8 @@ impl Parse for RouterAttributes {
         let trailing = map.get_bool("path_trailing").unwrap_or(false);
         let insensitive = map.get_bool("path_insensitive").unwrap_or(true);
         let methods = map.get_string("methods").unwrap_or(String::from("ALL"));
+        let rate_limit = map.get_string("rate_limit").ok()
+            .map(|name|syn::parse_str(&name).expect("rate_limit must be the path to a RateLimiter!"));
 
         return Ok(Self{
             path: Path {
@@ -46,13 +49,14 @@ impl Parse for RouterAttributes {
                 trailing,
                 insensitive
             },
-            methods
+            methods,
+            rate_limit
         })
     }
 }
 
 impl Path {
-    pub fn build_pattern<Name:fmt::Display>(&self, name:Name) -> (syn::Ident, TokenStream, TokenStream) {
+    pub fn build_pattern<Name:fmt::Display>(&self, name:Name, methods:&syn::Ident, limit:&TokenStream) -> (syn::Ident, TokenStream, TokenStream) {
         let pattern_name = syn::Ident::new(
             &format!("{}Pattern", name),
             Span::call_site()
@@ -93,10 +97,17 @@ impl Path {
             },
             quote! {
             if let Some(caps) = #pattern_name.captures(&req.url.pathname()) {
-                let (_, list) = caps.extract() as (&str, [&str; #path_size]);
-                let param = #param_name::new(list);
+                //HEAD is answered by GET routes with the body left off.
+                if #methods.contains(&req.method) || (req.method == http::types::Method::HEAD
+                    && #methods.contains(&http::types::Method::GET)) {
+                    let (_, list) = caps.extract() as (&str, [&str; #path_size]);
+                    let param = #param_name::new(list);
+                    #limit
 
-                return #handler_name(req.build(param)).await.map(|rsp|Some(rsp));
+                    return #handler_name(req.build(param)).await.map(|rsp|Some(rsp));
+                }
+
+                allow.extend_from_slice(#methods);
             }
         }
         )
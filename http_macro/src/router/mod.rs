@@ -6,8 +6,46 @@ use inputs::RouterAttributes;
 
 mod inputs;
 
+/// OPTIONS is left out so it is answered by the server unless a route asks for it.
+const ALL_METHODS: [&str; 8] = ["GET", "HEAD", "POST", "PUT", "DELETE", "TRACE", "CONNECT", "PATCH"];
+const VALID_METHODS: [&str; 9] = ["OPTIONS", "GET", "HEAD", "POST", "PUT", "DELETE", "TRACE", "CONNECT", "PATCH"];
+
+/// Builds the list of methods from a comma seperated string, or "ALL".
+fn build_methods(methods:&str) -> TokenStream {
+    let list: Vec<String> = if methods.trim().eq_ignore_ascii_case("ALL") {
+        ALL_METHODS.iter().map(|str|str.to_string()).collect()
+    } else {
+        methods.split(',')
+            .map(|str|str.trim().to_ascii_uppercase())
+            .filter(|str|!str.is_empty())
+            .inspect(|str|if !VALID_METHODS.contains(&str.as_str()) {
+                panic!("{} is not a valid method!", str)
+            })
+            .collect()
+    };
+
+    let list = list.iter().map(|str|syn::Ident::new(str, Span::call_site()));
+    quote!( &[ #( http::types::Method::#list ),* ] )
+}
+
+/// Counts the request against the limiter, returning Too Many Requests once over it.
+pub(crate) fn build_limit(rate_limit:Option<&syn::Path>) -> TokenStream {
+    match rate_limit {
+        Some(limiter) => quote!( #limiter.limit(req)?; ),
+        None => TokenStream::new()
+    }
+}
+
+/// Limiter of the router, so the server can tell which ones it has.
+fn build_limiter(rate_limit:Option<&syn::Path>) -> TokenStream {
+    match rate_limit {
+        Some(limiter) => quote!( Some(&#limiter) ),
+        None => quote!( None )
+    }
+}
+
 pub fn build(attributes:proc_macro::TokenStream, data:proc_macro::TokenStream) -> TokenStream {
-    let RouterAttributes{path, methods} = parse(attributes).unwrap();
+    let RouterAttributes{path, methods, rate_limit} = parse(attributes).unwrap();
     let handler = parse::<ItemFn>(data).unwrap();
 
     let name = &handler.sig.ident;
@@ -17,7 +55,14 @@ pub fn build(attributes:proc_macro::TokenStream, data:proc_macro::TokenStream) -
         Span::call_site()
     );
 
-    let (handler_name, path, match_capture) = path.build_pattern(&name); 
+    let methods = build_methods(&methods);
+    let limit = build_limit(rate_limit.as_ref());
+    let limiter_name = syn::Ident::new(
+        &format!("{}RateLimiter", name),
+        Span::call_site()
+    );
+    let limiter = build_limiter(rate_limit.as_ref());
+    let (handler_name, path, match_capture) = path.build_pattern(&name, &methods_name, &limit);
 
     let public = &handler.vis;
     let hand_attr:Vec<_> = handler.sig.inputs.iter().collect();
@@ -27,13 +72,19 @@ pub fn build(attributes:proc_macro::TokenStream, data:proc_macro::TokenStream) -
     let async_call = &handler.sig.asyncness;
 
     quote! {
-        const #methods_name:&'static str = #methods;
+        const #methods_name:&'static [http::types::Method] = #methods;
         #path
 
         #async_call fn #handler_name #hand_genics( #(#hand_attr),* ) #hand_return #hand_block
+
+        #[allow(non_snake_case)]
+        #public fn #limiter_name() -> Option<&'static http::types::rate_limit::RateLimiter> {
+            #limiter
+        }
         
+        //Adds the route's methods to allow when the path matches but the method does not.
         #[allow(non_snake_case)]
-        #public #async_call fn #name(req:&mut http::server::RequestBuilder<http::async_net::TcpStream>) -> http::Result<Option<http::Response>> {
+        #public #async_call fn #name(req:&mut http::server::RequestBuilder<http::async_net::TcpStream>, allow:&mut Vec<http::types::Method>) -> http::Result<Option<http::Response>> {
             #match_capture
 
             Ok(None)
@@ -1,37 +1,163 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use super::inputs::ServerAttributes;
+use super::inputs::{ServerAttributes, ServerArguments};
 
-fn build_handler(routers:&Vec<syn::Ident>) -> TokenStream {
-    let mut handle_router = quote!();
+fn build_handler(routers:&Vec<syn::Ident>, rate_limit:Option<&syn::Path>) -> TokenStream {
+    //Counted before the routers, so requests that match no route are limited as well.
+    let mut handle_router = match rate_limit {
+        Some(limiter) => quote!( #limiter.limit(builder)?; ),
+        None => quote!()
+    };
 
     for r in routers {
+        let name = r.to_string();
+
         handle_router.extend(quote!{
-            if let Some(resp) = #r(builder).await? {
+            if let Some(resp) = #r(builder, allow).await? {
+                http::server::emit(http::server::ServerEvent::Routing, http::server::EventPayload {
+                    router: Some(#name.to_string()),
+                    ..http::server::EventPayload::request(builder)
+                });
                 return Ok(resp)
             }
         });
     }
 
     quote!{
-        async fn handler(builder: &mut http::server::RequestBuilder<http::async_net::TcpStream>) -> http::Result<http::Response> {
+        /// Routes that match the path but not the method add their methods to `allow`.
+        async fn handler(builder: &mut http::server::RequestBuilder<http::async_net::TcpStream>, allow: &mut Vec<http::types::Method>) -> http::Result<http::Response> {
             #handle_router
             use http::types::ValidHttpError;
 
-            http::HttpErrorKind::NotFound.send()
+            if allow.is_empty() {
+                http::HttpErrorKind::NotFound.send()
+            } else if builder.method == http::types::Method::OPTIONS {
+                let mut resp = http::Response::new(http::types::HttpStatus::NoContent, None);
+                resp.headers.set("Allow", Self::allow_header(allow).as_str());
+                Ok(resp)
+            } else {
+                http::HttpErrorKind::MethodNotAllowed.send()
+            }
+        }
+
+        /// GET routes also answer HEAD, so HEAD is listed with them.
+        ///
+        /// RFC-9110 10.2.1
+        fn allow_header(allow: &[http::types::Method]) -> String {
+            let mut list: Vec<&str> = Vec::new();
+            for method in allow.iter().chain(&[http::types::Method::OPTIONS]) {
+                if !list.contains(&method.to_str()) {
+                    list.push(method.to_str());
+                }
+
+                if *method == http::types::Method::GET && !list.contains(&"HEAD") {
+                    list.push(http::types::Method::HEAD.to_str());
+                }
+            }
+
+            list.join(", ")
         }
     }
 }
 
-pub fn build_server(att:ServerAttributes, hostname:&str, port:u16, threads:usize) -> TokenStream {
+pub fn build_server(att:ServerAttributes, args:&ServerArguments) -> TokenStream {
+    let ServerArguments{
+        hostname, port, threads,
+        keep_alive_timeout, keep_alive_max,
+        tls_cert, tls_key, socket, addresses,
+        shutdown_timeout,
+        header_timeout, body_timeout, write_timeout,
+        max_request_line, max_header_bytes, max_headers, max_uri,
+        max_connections, retry_after,
+        rate_limit, rate_limit_max, rate_limit_window,
+        events,
+        log_level, log_output, log_max_size, log_rotate_interval, log_keep,
+        access_log, access_log_format,
+        console,
+        ..
+    } = args;
     let name = att.name;
-    let handler = build_handler(&att.routers, );
+    let handler = build_handler(&att.routers, rate_limit.as_ref());
 
     let error_handler = match &att.err_handler {
         Some(func) => quote!(#func( builder.build(e) ).await ),
         None => quote!(http::Response::from_error(e) )
     }; 
 
+    let tls_cert = match tls_cert {
+        Some(str) => quote!(Some(#str.to_string())),
+        None => quote!(None)
+    };
+
+    let tls_key = match tls_key {
+        Some(str) => quote!(Some(#str.to_string())),
+        None => quote!(None)
+    };
+
+    let socket = match socket {
+        Some(str) => quote!(Some(#str.to_string())),
+        None => quote!(None)
+    };
+
+    let retry_after = match retry_after {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None)
+    };
+
+    let rate_limiter = match rate_limit {
+        Some(limiter) => quote!(Some(&#limiter)),
+        None => quote!(None)
+    };
+
+    let router_limiters = att.routers.iter().map(|r|syn::Ident::new(
+        &format!("{}RateLimiter", r),
+        r.span()
+    ));
+
+    let rate_limit_max = match rate_limit_max {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None)
+    };
+
+    let rate_limit_window = match rate_limit_window {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None)
+    };
+
+    let event_listeners = match events {
+        Some(register) => quote!(Some(#register)),
+        None => quote!(None)
+    };
+
+    //Checked when the arguments were parsed.
+    let log_level = {
+        let mut name = log_level.clone();
+        name[..1].make_ascii_uppercase();
+        syn::Ident::new(&name, proc_macro2::Span::call_site())
+    };
+
+    let log_max_size = match log_max_size {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None)
+    };
+
+    let log_rotate_interval = match log_rotate_interval {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None)
+    };
+
+    let access_log = match access_log {
+        Some(str) => quote!(Some(http::log::Output::from(#str))),
+        None => quote!(None)
+    };
+
+    //Checked when the arguments were parsed.
+    let access_log_format = match access_log_format.as_str() {
+        "common" => quote!(Common),
+        "json" => quote!(Json),
+        _ => quote!(Combined)
+    };
+
     let struct_start = if att.public {
         quote!{pub struct}
     } else {
@@ -43,7 +169,26 @@ pub fn build_server(att:ServerAttributes, hostname:&str, port:u16, threads:usize
         #struct_start #name{
             hostname:String,
             port: u16,
-            threads: usize
+            threads: usize,
+            keep_alive_timeout: std::time::Duration,
+            keep_alive_max: usize,
+            tls_cert: Option<String>,
+            tls_key: Option<String>,
+            socket: Option<String>,
+            addresses: Vec<String>,
+            shutdown_timeout: std::time::Duration,
+            header_timeout: std::time::Duration,
+            body_timeout: std::time::Duration,
+            write_timeout: std::time::Duration,
+            limits: http::server::Limits,
+            max_connections: usize,
+            retry_after: Option<u64>,
+            rate_limit_max: Option<u32>,
+            rate_limit_window: Option<std::time::Duration>,
+            logging: http::log::LogConfig,
+            access_log: Option<http::server::AccessLog>,
+            console: bool,
+            static_dirs: Vec<http::server::StaticDir>
         }
 
         impl #name {
@@ -54,9 +199,52 @@ pub fn build_server(att:ServerAttributes, hostname:&str, port:u16, threads:usize
 
             fn new(opts:http::server::ServerOpts) -> Self {
                 Self {
-                    hostname: opts.hostname.unwrap_or(#hostname.to_string()),
-                    port: opts.port.unwrap_or(#port),
-                    threads: opts.threads.unwrap_or(#threads)
+                    hostname: opts.listen.hostname.unwrap_or(#hostname.to_string()),
+                    port: opts.listen.port.unwrap_or(#port),
+                    threads: opts.threads.unwrap_or(#threads),
+                    keep_alive_timeout: std::time::Duration::from_secs(
+                        opts.timeouts.keep_alive.unwrap_or(#keep_alive_timeout)
+                    ),
+                    keep_alive_max: opts.limits.keep_alive_max.unwrap_or(#keep_alive_max),
+                    tls_cert: opts.tls.cert.or(#tls_cert),
+                    tls_key: opts.tls.key.or(#tls_key),
+                    socket: opts.listen.socket.or(#socket),
+                    addresses: opts.listen.addresses.unwrap_or_else(||vec![#(#addresses.to_string()),*]),
+                    shutdown_timeout: std::time::Duration::from_secs(
+                        opts.timeouts.shutdown.unwrap_or(#shutdown_timeout)
+                    ),
+                    header_timeout: std::time::Duration::from_secs(
+                        opts.timeouts.header.unwrap_or(#header_timeout)
+                    ),
+                    body_timeout: std::time::Duration::from_secs(
+                        opts.timeouts.body.unwrap_or(#body_timeout)
+                    ),
+                    write_timeout: std::time::Duration::from_secs(
+                        opts.timeouts.write.unwrap_or(#write_timeout)
+                    ),
+                    limits: http::server::Limits {
+                        max_request_line: opts.limits.max_request_line.unwrap_or(#max_request_line),
+                        max_header_bytes: opts.limits.max_header_bytes.unwrap_or(#max_header_bytes),
+                        max_headers: opts.limits.max_headers.unwrap_or(#max_headers),
+                        max_uri: opts.limits.max_uri.unwrap_or(#max_uri)
+                    },
+                    max_connections: opts.listen.max_connections.unwrap_or(#max_connections),
+                    retry_after: opts.listen.retry_after.or(#retry_after),
+                    rate_limit_max: opts.limits.rate_limit_max.or(#rate_limit_max),
+                    rate_limit_window: opts.limits.rate_limit_window.or(#rate_limit_window).map(std::time::Duration::from_secs),
+                    logging: http::log::LogConfig {
+                        level: opts.log.level.unwrap_or(http::log::Level::#log_level),
+                        outputs: opts.log.outputs.unwrap_or_else(||vec![#(http::log::Output::from(#log_output)),*]),
+                        max_size: opts.log.max_size.or(#log_max_size),
+                        rotate_interval: opts.log.rotate_interval.or(#log_rotate_interval).map(std::time::Duration::from_secs),
+                        keep: opts.log.keep.unwrap_or(#log_keep)
+                    },
+                    access_log: opts.log.access_log.or(#access_log).map(|output|http::server::AccessLog {
+                        output,
+                        format: opts.log.access_log_format.unwrap_or(http::server::AccessLogFormat::#access_log_format)
+                    }),
+                    console: opts.console.unwrap_or(#console),
+                    static_dirs: opts.static_dirs.unwrap_or_default()
                 }
             }
 
@@ -72,11 +260,122 @@ pub fn build_server(att:ServerAttributes, hostname:&str, port:u16, threads:usize
                 self.threads
             }
 
+            fn keep_alive_timeout(&self) -> std::time::Duration {
+                self.keep_alive_timeout
+            }
+
+            fn keep_alive_max(&self) -> usize {
+                self.keep_alive_max
+            }
+
+            fn tls_cert(&self) -> Option<&str> {
+                self.tls_cert.as_deref()
+            }
+
+            fn tls_key(&self) -> Option<&str> {
+                self.tls_key.as_deref()
+            }
+
+            fn socket(&self) -> Option<&str> {
+                self.socket.as_deref()
+            }
+
+            fn addresses(&self) -> &[String] {
+                &self.addresses
+            }
+
+            fn shutdown_timeout(&self) -> std::time::Duration {
+                self.shutdown_timeout
+            }
+
+            fn header_timeout(&self) -> std::time::Duration {
+                self.header_timeout
+            }
+
+            fn body_timeout(&self) -> std::time::Duration {
+                self.body_timeout
+            }
+
+            fn write_timeout(&self) -> std::time::Duration {
+                self.write_timeout
+            }
+
+            fn limits(&self) -> http::server::Limits {
+                self.limits
+            }
+
+            fn max_connections(&self) -> usize {
+                self.max_connections
+            }
+
+            fn retry_after(&self) -> Option<u64> {
+                self.retry_after
+            }
+
+            fn rate_limiter(&self) -> Option<&'static http::types::rate_limit::RateLimiter> {
+                #rate_limiter
+            }
+
+            fn router_limiters(&self) -> Vec<&'static http::types::rate_limit::RateLimiter> {
+                [ #( #router_limiters() ),* ].into_iter().flatten().collect()
+            }
+
+            fn rate_limit_max(&self) -> Option<u32> {
+                self.rate_limit_max
+            }
+
+            fn rate_limit_window(&self) -> Option<std::time::Duration> {
+                self.rate_limit_window
+            }
+
+            fn logging(&self) -> &http::log::LogConfig {
+                &self.logging
+            }
+
+            fn access_log(&self) -> Option<&http::server::AccessLog> {
+                self.access_log.as_ref()
+            }
+
+            fn console(&self) -> bool {
+                self.console
+            }
+
+            fn static_dirs(&self) -> &[http::server::StaticDir] {
+                &self.static_dirs
+            }
+
+            fn event_listeners(&self) -> Option<fn(&mut http::server::ServerEvents)> {
+                #event_listeners
+            }
+
             async fn handle_request(&self, mut builder: &mut http::server::RequestBuilder<http::async_net::TcpStream>) -> http::Response {
-                match Self::handler(&mut builder).await {
+                let mut allow = Vec::new();
+
+                let mut resp = match Self::handler(&mut builder, &mut allow).await {
                     Ok(resp) => resp,
-                    Err(e) => #error_handler
+                    Err(e) => {
+                        let status: http::types::HttpStatus = e.kind.clone().into();
+                        http::server::emit(http::server::ServerEvent::Error, http::server::EventPayload {
+                            status: Some(status.code()),
+                            message: Some(e.message.clone()),
+                            ..http::server::EventPayload::request(builder)
+                        });
+                        let mut resp = #error_handler;
+
+                        //RFC-9110 15.5.6
+                        if resp.status.code() == 405 && !allow.is_empty() {
+                            resp.headers.set("Allow", Self::allow_header(&allow).as_str());
+                        }
+
+                        resp
+                    }
+                };
+
+                if let Some(status) = &builder.rate_limit {
+                    status.set_headers(&mut resp.headers);
                 }
+
+                resp
             }
         }
     }
@@ -10,12 +10,86 @@ pub(crate) struct ServerArguments {
     //default = "127.0.0.1"
     pub(crate) hostname: String,
     //default = 3
-    pub(crate) threads: usize
+    pub(crate) threads: usize,
+    //default = 5
+    pub(crate) keep_alive_timeout: u64,
+    //default = 100
+    pub(crate) keep_alive_max: usize,
+    //default = None
+    pub(crate) tls_cert: Option<String>,
+    //default = None
+    pub(crate) tls_key: Option<String>,
+    //default = None
+    pub(crate) socket: Option<String>,
+    //default = []
+    pub(crate) addresses: Vec<String>,
+    //default = 10
+    pub(crate) shutdown_timeout: u64,
+    //default = 10
+    pub(crate) header_timeout: u64,
+    //default = 30
+    pub(crate) body_timeout: u64,
+    //default = 30
+    pub(crate) write_timeout: u64,
+    //default = 8192
+    pub(crate) max_request_line: usize,
+    //default = 16384
+    pub(crate) max_header_bytes: usize,
+    //default = 100
+    pub(crate) max_headers: usize,
+    //default = 8000
+    pub(crate) max_uri: usize,
+    //default = 0
+    pub(crate) max_connections: usize,
+    //default = None
+    pub(crate) retry_after: Option<u64>,
+    //default = None, path to a static RateLimiter
+    pub(crate) rate_limit: Option<syn::Path>,
+    //default = None, uses the limit of the RateLimiter
+    pub(crate) rate_limit_max: Option<u32>,
+    //default = None, uses the window of the RateLimiter
+    pub(crate) rate_limit_window: Option<u64>,
+    //default = None, path to a fn(&mut ServerEvents) that adds the listeners
+    pub(crate) events: Option<syn::Path>,
+    //default = "info"
+    pub(crate) log_level: String,
+    //default = ["stdout"]
+    pub(crate) log_output: Vec<String>,
+    //default = None
+    pub(crate) log_max_size: Option<u64>,
+    //default = None
+    pub(crate) log_rotate_interval: Option<u64>,
+    //default = 5
+    pub(crate) log_keep: usize,
+    //default = None, stdout, stderr or a file path
+    pub(crate) access_log: Option<String>,
+    //default = "combined"
+    pub(crate) access_log_format: String,
+    //default = true
+    pub(crate) console: bool
 }
 
 const DEFAULT_PORT:u16 = 5000;
 const DEFAULT_HOSTNAME:&'static str = "127.0.0.1";
 const DEFAULT_THREADS:usize = 3;
+const DEFAULT_KEEP_ALIVE_TIMEOUT:u64 = 5;
+const DEFAULT_KEEP_ALIVE_MAX:usize = 100;
+const DEFAULT_SHUTDOWN_TIMEOUT:u64 = 10;
+const DEFAULT_HEADER_TIMEOUT:u64 = 10;
+const DEFAULT_BODY_TIMEOUT:u64 = 30;
+const DEFAULT_WRITE_TIMEOUT:u64 = 30;
+const DEFAULT_MAX_REQUEST_LINE:usize = 8192;
+const DEFAULT_MAX_HEADER_BYTES:usize = 16384;
+const DEFAULT_MAX_HEADERS:usize = 100;
+const DEFAULT_MAX_URI:usize = 8000;
+const DEFAULT_MAX_CONNECTIONS:usize = 0;
+const DEFAULT_LOG_LEVEL:&str = "info";
+const DEFAULT_LOG_OUTPUT:&str = "stdout";
+const DEFAULT_LOG_KEEP:usize = 5;
+const LOG_LEVELS: [&str; 5] = ["critical", "error", "warning", "info", "debug"];
+const DEFAULT_ACCESS_LOG_FORMAT:&str = "combined";
+const ACCESS_LOG_FORMATS: [&str; 3] = ["common", "combined", "json"];
+const DEFAULT_CONSOLE:bool = true;
 
 impl Parse for ServerArguments {
     fn parse(input:ParseStream) -> syn::Result<Self> {
@@ -29,20 +103,139 @@ impl Parse for ServerArguments {
             .unwrap_or(String::from(DEFAULT_HOSTNAME));
         let threads = map.get_usize("threads")
             .unwrap_or(DEFAULT_THREADS);
+        let keep_alive_timeout = map.get_u64("keep_alive_timeout")
+            .unwrap_or(DEFAULT_KEEP_ALIVE_TIMEOUT);
+        let keep_alive_max = map.get_usize("keep_alive_max")
+            .unwrap_or(DEFAULT_KEEP_ALIVE_MAX);
+        let tls_cert = map.get_string("tls_cert")
+            .ok();
+        let tls_key = map.get_string("tls_key")
+            .ok();
+        let socket = map.get_string("socket")
+            .ok();
+        let addresses = map.get_string("addresses")
+            .map(|list|split_list(&list))
+            .unwrap_or_default();
+        let shutdown_timeout = map.get_u64("shutdown_timeout")
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+        let header_timeout = map.get_u64("header_timeout")
+            .unwrap_or(DEFAULT_HEADER_TIMEOUT);
+        let body_timeout = map.get_u64("body_timeout")
+            .unwrap_or(DEFAULT_BODY_TIMEOUT);
+        let write_timeout = map.get_u64("write_timeout")
+            .unwrap_or(DEFAULT_WRITE_TIMEOUT);
+        let max_request_line = map.get_usize("max_request_line")
+            .unwrap_or(DEFAULT_MAX_REQUEST_LINE);
+        let max_header_bytes = map.get_usize("max_header_bytes")
+            .unwrap_or(DEFAULT_MAX_HEADER_BYTES);
+        let max_headers = map.get_usize("max_headers")
+            .unwrap_or(DEFAULT_MAX_HEADERS);
+        let max_uri = map.get_usize("max_uri")
+            .unwrap_or(DEFAULT_MAX_URI);
+        let max_connections = map.get_usize("max_connections")
+            .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+        let retry_after = map.get_u64("retry_after")
+            .ok();
+        let rate_limit = map.get_string("rate_limit")
+            .ok()
+            .map(|name|syn::parse_str(&name).expect("rate_limit must be the path to a RateLimiter!"));
+        let rate_limit_max = map.get_u64("rate_limit_max")
+            .ok()
+            .map(|max|u32::try_from(max).expect("rate_limit_max must fit in a u32!"));
+        let rate_limit_window = map.get_u64("rate_limit_window")
+            .ok();
+        let events = map.get_string("events")
+            .ok()
+            .map(|name|syn::parse_str(&name).expect("events must be the path to a function!"));
+        let log_level = map.get_string("log_level")
+            .map(|level|level.to_ascii_lowercase())
+            .unwrap_or(String::from(DEFAULT_LOG_LEVEL));
+        if !LOG_LEVELS.contains(&log_level.as_str()) {
+            panic!("{} is not a valid log level!", log_level);
+        }
+        let log_output = map.get_string("log_output")
+            .map(|list|split_list(&list))
+            .unwrap_or(vec![DEFAULT_LOG_OUTPUT.to_string()]);
+        let log_max_size = map.get_u64("log_max_size")
+            .ok();
+        let log_rotate_interval = map.get_u64("log_rotate_interval")
+            .ok();
+        let log_keep = map.get_usize("log_keep")
+            .unwrap_or(DEFAULT_LOG_KEEP);
+        let access_log = map.get_string("access_log")
+            .ok();
+        let access_log_format = map.get_string("access_log_format")
+            .map(|format|format.to_ascii_lowercase())
+            .unwrap_or(String::from(DEFAULT_ACCESS_LOG_FORMAT));
+        if !ACCESS_LOG_FORMATS.contains(&access_log_format.as_str()) {
+            panic!("{} is not a valid access log format!", access_log_format);
+        }
+        let console = map.get_bool("console")
+            .unwrap_or(DEFAULT_CONSOLE);
 
         Ok(
-            Self { config, port, hostname, threads }
+            Self {
+                config, port, hostname, threads,
+                keep_alive_timeout, keep_alive_max,
+                tls_cert, tls_key, socket, addresses,
+                shutdown_timeout,
+                header_timeout, body_timeout, write_timeout,
+                max_request_line, max_header_bytes, max_headers, max_uri,
+                max_connections, retry_after,
+                rate_limit, rate_limit_max, rate_limit_window,
+                events,
+                log_level, log_output, log_max_size, log_rotate_interval, log_keep,
+                access_log, access_log_format,
+                console
+            }
         )
     }
 }
 
+/// Comma seperated list of addresses or log outputs.
+fn split_list(value:&str) -> Vec<String> {
+    value.split(",")
+        .map(|s|s.trim())
+        .filter(|s|!s.is_empty())
+        .map(|s|s.to_string())
+        .collect()
+}
+
 pub(crate) fn parse_server_arguments(input:TokenStream) -> ServerArguments {
     if input.is_empty() {
         ServerArguments {
             config: None,
             port: DEFAULT_PORT,
             hostname: DEFAULT_HOSTNAME.to_string(),
-            threads: DEFAULT_THREADS
+            threads: DEFAULT_THREADS,
+            keep_alive_timeout: DEFAULT_KEEP_ALIVE_TIMEOUT,
+            keep_alive_max: DEFAULT_KEEP_ALIVE_MAX,
+            tls_cert: None,
+            tls_key: None,
+            socket: None,
+            addresses: Vec::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            header_timeout: DEFAULT_HEADER_TIMEOUT,
+            body_timeout: DEFAULT_BODY_TIMEOUT,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            max_request_line: DEFAULT_MAX_REQUEST_LINE,
+            max_header_bytes: DEFAULT_MAX_HEADER_BYTES,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_uri: DEFAULT_MAX_URI,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            retry_after: None,
+            rate_limit: None,
+            rate_limit_max: None,
+            rate_limit_window: None,
+            events: None,
+            log_level: DEFAULT_LOG_LEVEL.to_string(),
+            log_output: vec![DEFAULT_LOG_OUTPUT.to_string()],
+            log_max_size: None,
+            log_rotate_interval: None,
+            log_keep: DEFAULT_LOG_KEEP,
+            access_log: None,
+            access_log_format: DEFAULT_ACCESS_LOG_FORMAT.to_string(),
+            console: DEFAULT_CONSOLE
         }
     } else {
         syn::parse::<ServerArguments>(input).unwrap()
@@ -1,37 +1,37 @@
-use inputs::{parse_server_arguments, parse_server_attributes, ServerArguments};
+use inputs::{parse_server_arguments, parse_server_attributes};
 use builder::build_server;
 
 mod inputs;
 mod builder;
 
 pub fn build(args: proc_macro::TokenStream, attr: proc_macro::TokenStream) -> proc_macro2::TokenStream {
-    let ServerArguments{hostname, config, port, threads}
-        = parse_server_arguments(args);
+    let args = parse_server_arguments(args);
     let attributes = parse_server_attributes(attr);
     let name = attributes.name.clone();
 
-    let config = match config {
+    let config = match &args.config {
         Some(str) => quote::quote!(Some(#str)),
         None => quote::quote!(None)
     };
 
     let server = build_server(
         attributes,
-        &hostname,
-        port,
-        threads
+        &args
     );
 
     quote::quote! {
         #server
 
         fn main() {
-            use http::server::{Server, ServerOpts, get_server_opts};
+            use http::server::{Server, get_server_opts};
 
-            let opts = get_server_opts(#config).unwrap();
-            let server = #name::new(opts);
+            let result = get_server_opts(#config)
+                .and_then(|opts|#name::new(opts).start());
 
-            server.start().unwrap();
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
         }
 
     }
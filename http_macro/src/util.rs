@@ -65,6 +65,7 @@ impl InputParser {
     get_value!(bool, Bool, "boolean");
     get_value!(u16, Int, "u16", |x: &syn::LitInt|x.base10_parse());
     get_value!(usize, Int, "usize", |x: &syn::LitInt|x.base10_parse());
+    get_value!(u64, Int, "u64", |x: &syn::LitInt|x.base10_parse());
 }
 
 pub fn snake_case<Name:ToString>(name:Name) -> String {
@@ -0,0 +1,301 @@
+/// Access Log
+///
+/// A line is written for each response once it has been sent, or once the
+/// client has stopped waiting for it.
+///
+/// https://httpd.apache.org/docs/current/logs.html#accesslog
+///
+use std::{
+    fmt,
+    io,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering}
+    },
+    time::{Duration, Instant, SystemTime}
+};
+use http_core::{
+    headers::Headers,
+    request::RequestBuilder,
+    version::Version
+};
+use async_lib::io::AsyncRead;
+use util::log::{LogConfig, LogWriter, Output, UtcTime};
+use crate::events::{self, EventPayload, ServerEvent};
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static WRITER: Mutex<AccessWriter> = Mutex::new(None);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessLogFormat {
+    /// host - - [time] "request" status bytes
+    Common,
+    /// Common followed by "referer" "user-agent" and the seconds taken.
+    Combined,
+    /// One JSON object per line.
+    Json
+}
+
+impl FromStr for AccessLogFormat {
+    type Err = String;
+
+    fn from_str(value:&str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "common" => Ok(Self::Common),
+            "combined" => Ok(Self::Combined),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown access log format \"{}\"!", value))
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AccessLog {
+    pub format: AccessLogFormat,
+    pub output: Output
+}
+
+pub(crate) type AccessWriter = Option<(AccessLogFormat, LogWriter)>;
+
+/// Opens the output, files are rotated using the same settings as the server log.
+pub(crate) fn open(config:Option<&AccessLog>, logging:&LogConfig) -> io::Result<AccessWriter> {
+    match config {
+        Some(config) => Ok(Some((config.format, LogWriter::open(std::slice::from_ref(&config.output), logging)?))),
+        None => Ok(None)
+    }
+}
+
+/// Replaces the output with one returned by open, None turns the access log off.
+pub(crate) fn set_writer(writer:AccessWriter) {
+    ENABLED.store(writer.is_some(), Ordering::Relaxed);
+    *WRITER.lock().unwrap() = writer;
+}
+
+struct Record {
+    remote: Option<SocketAddr>,
+    method: String,
+    path: String,
+    version: Version,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    time: SystemTime,
+    start: Instant,
+    status: Option<u16>,
+    bytes: u64
+}
+
+/// Written to the access log and sent as a response event when dropped, if a
+/// response was started.
+pub(crate) struct AccessEntry(Option<Box<Record>>);
+
+fn header(headers:&Headers, name:&str) -> Option<String> {
+    headers.get(name)
+        .and_then(|value|value.ref_str().ok())
+        .map(|str|str.to_string())
+}
+
+impl AccessEntry {
+    pub(crate) fn new<S: AsyncRead>(req:&RequestBuilder<S>) -> Self {
+        if events::is_listening() {
+            events::emit(ServerEvent::Request, EventPayload::request(req));
+        } else if !ENABLED.load(Ordering::Relaxed) {
+            return Self(None);
+        }
+
+        Self(Some(Box::new(Record {
+            remote: req.peer_addr,
+            method: req.method.to_str().to_string(),
+            path: req.url.pathname(),
+            version: req.version,
+            referer: header(&req.headers, "Referer"),
+            user_agent: header(&req.headers, "User-Agent"),
+            time: SystemTime::now(),
+            start: Instant::now(),
+            status: None,
+            bytes: 0
+        })))
+    }
+
+    pub(crate) fn status(&mut self, code:u16) {
+        if let Some(record) = &mut self.0 {
+            record.status = Some(code);
+        }
+    }
+
+    /// Bytes of the response body that were sent.
+    pub(crate) fn sent(&mut self, bytes:u64) {
+        if let Some(record) = &mut self.0 {
+            record.bytes += bytes;
+        }
+    }
+}
+
+impl Drop for AccessEntry {
+    fn drop(&mut self) {
+        let Some(record) = self.0.take() else {
+            return;
+        };
+        let Some(status) = record.status else {
+            return;
+        };
+
+        let duration = record.start.elapsed();
+        if let Some((format, writer)) = WRITER.lock().unwrap().as_mut() {
+            writer.write_line(&record.format(*format, status, duration));
+        }
+
+        events::emit(ServerEvent::Response, EventPayload {
+            peer: record.remote,
+            method: Some(record.method),
+            path: Some(record.path),
+            status: Some(status),
+            duration: Some(duration),
+            ..EventPayload::default()
+        });
+    }
+}
+
+/// Values in quotes have any quotes or control characters escaped.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// JSON string, or null.
+pub(crate) struct JsonString<'a>(pub Option<&'a str>);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(str) => write!(f, "\"{}\"", Quoted(str)),
+            None => f.write_str("null")
+        }
+    }
+}
+
+impl Record {
+    fn format(&self, format:AccessLogFormat, status:u16, duration:Duration) -> String {
+        let seconds = duration.as_secs_f64();
+        let remote = self.remote.map(|addr|addr.ip().to_string());
+
+        if format == AccessLogFormat::Json {
+            return format!(
+                "{{\"time\":\"{}\",\"remote_addr\":{},\"method\":{},\"url\":{},\"version\":\"{}\",\"status\":{},\"bytes\":{},\"referer\":{},\"user_agent\":{},\"duration\":{:.3}}}\n",
+                UtcTime::from(self.time),
+                JsonString(remote.as_deref()),
+                JsonString(Some(&self.method)),
+                JsonString(Some(&self.path)),
+                self.version.to_string(),
+                status,
+                self.bytes,
+                JsonString(self.referer.as_deref()),
+                JsonString(self.user_agent.as_deref()),
+                seconds
+            );
+        }
+
+        let time = UtcTime::from(self.time);
+        let mut line = format!(
+            "{} - - [{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000] \"{} {} {}\" {} {}",
+            remote.as_deref().unwrap_or("-"),
+            time.day, MONTHS[time.month as usize - 1], time.year,
+            time.hour, time.minute, time.second,
+            self.method, Quoted(&self.path), self.version.to_string(),
+            status,
+            if self.bytes == 0 { String::from("-") } else { self.bytes.to_string() }
+        );
+
+        if format == AccessLogFormat::Combined {
+            line += &format!(
+                " \"{}\" \"{}\" {:.3}",
+                Quoted(self.referer.as_deref().unwrap_or("-")),
+                Quoted(self.user_agent.as_deref().unwrap_or("-")),
+                seconds
+            );
+        }
+
+        line.push('\n');
+        line
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(referer:Option<&str>, user_agent:Option<&str>, bytes:u64) -> Record {
+        Record {
+            remote: Some("127.0.0.1:8080".parse().unwrap()),
+            method: "GET".to_string(),
+            path: "/say \"hi\"".to_string(),
+            version: Version::new(),
+            referer: referer.map(String::from),
+            user_agent: user_agent.map(String::from),
+            time: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000),
+            start: Instant::now(),
+            status: None,
+            bytes
+        }
+    }
+
+    const DURATION: Duration = Duration::from_millis(1500);
+
+    #[test]
+    fn common_format() {
+        assert_eq!(
+            record(None, None, 1234).format(AccessLogFormat::Common, 200, DURATION),
+            "127.0.0.1 - - [09/Sep/2001:01:46:40 +0000] \"GET /say \\\"hi\\\" HTTP/1.1\" 200 1234\n"
+        );
+
+        let mut record = record(None, None, 0);
+        record.remote = None;
+        assert_eq!(
+            record.format(AccessLogFormat::Common, 304, DURATION),
+            "- - - [09/Sep/2001:01:46:40 +0000] \"GET /say \\\"hi\\\" HTTP/1.1\" 304 -\n"
+        );
+    }
+
+    #[test]
+    fn combined_format() {
+        assert_eq!(
+            record(Some("http://example.com/"), Some("curl/8.0 \"test\""), 12).format(AccessLogFormat::Combined, 200, DURATION),
+            "127.0.0.1 - - [09/Sep/2001:01:46:40 +0000] \"GET /say \\\"hi\\\" HTTP/1.1\" 200 12 \"http://example.com/\" \"curl/8.0 \\\"test\\\"\" 1.500\n"
+        );
+
+        assert_eq!(
+            record(None, None, 12).format(AccessLogFormat::Combined, 404, DURATION),
+            "127.0.0.1 - - [09/Sep/2001:01:46:40 +0000] \"GET /say \\\"hi\\\" HTTP/1.1\" 404 12 \"-\" \"-\" 1.500\n"
+        );
+    }
+
+    #[test]
+    fn json_format() {
+        assert_eq!(
+            record(None, Some("tab\there\\"), 0).format(AccessLogFormat::Json, 500, DURATION),
+            "{\"time\":\"2001-09-09T01:46:40.000Z\",\"remote_addr\":\"127.0.0.1\",\"method\":\"GET\",\"url\":\"/say \\\"hi\\\"\",\"version\":\"HTTP/1.1\",\"status\":500,\"bytes\":0,\"referer\":null,\"user_agent\":\"tab\\u0009here\\\\\",\"duration\":1.500}\n"
+        );
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!("Combined".parse(), Ok(AccessLogFormat::Combined));
+        assert_eq!(" json ".parse(), Ok(AccessLogFormat::Json));
+        assert!("apache".parse::<AccessLogFormat>().is_err());
+    }
+}
@@ -0,0 +1,292 @@
+/// Config File
+///
+/// TOML with the options split into [listen], [tls], [limits], [timeouts] and
+/// [log] tables, and a [[static]] table for each static directory:
+///
+/// ```toml
+/// threads = 4
+///
+/// [listen]
+/// addresses = ["0.0.0.0:8080", "[::]:8080"]
+///
+/// [log]
+/// level = "info"
+/// outputs = ["stdout", "logs/server.log"]
+///
+/// [[static]]
+/// path = "/assets"
+/// dir = "./public"
+/// ```
+///
+use std::{fmt, str::FromStr};
+use util::{
+    log::Output,
+    toml::{self, Position, TomlError, TomlItem, TomlTable}
+};
+use crate::{
+    ServerOpts, StaticDir,
+    options::{ListenOpts, TlsOpts, LimitOpts, TimeoutOpts, LogOpts}
+};
+
+type Result<T> = std::result::Result<T, TomlError>;
+
+/// Options read from a config file.
+#[derive(Debug)]
+pub struct ConfigFile {
+    pub opts: ServerOpts,
+    /// Keys that are not options, and were ignored.
+    pub warnings: Vec<TomlError>
+}
+
+/// Config file that could not be parsed, or has an invalid value.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub file: String,
+    pub error: TomlError
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.error)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn string(item:&TomlItem) -> Result<String> {
+    item.as_str().map(String::from)
+}
+
+fn strings(item:&TomlItem) -> Result<Vec<String>> {
+    item.as_array()?.iter()
+        .map(string)
+        .collect()
+}
+
+fn integer<T: TryFrom<i64>>(item:&TomlItem) -> Result<T> {
+    let value = item.as_integer()?;
+    T::try_from(value)
+        .map_err(|_|TomlError::new(format!("{} is out of range", value), item.position))
+}
+
+/// Strings parsed with FromStr, such as the log level.
+fn parsed<T: FromStr<Err = String>>(item:&TomlItem) -> Result<T> {
+    item.as_str()?.parse()
+        .map_err(|e|TomlError::new(e, item.position))
+}
+
+fn unknown(section:&str, key:&str, position:Position) -> TomlError {
+    match section {
+        "" => TomlError::new(format!("Unknown key \"{}\"", key), position),
+        section => TomlError::new(format!("Unknown key \"{}\" in [{}]", key, section), position)
+    }
+}
+
+fn read_listen(table:&TomlTable, warnings:&mut Vec<TomlError>) -> Result<ListenOpts> {
+    let mut opts = ListenOpts::default();
+
+    for (key, position, item) in table.iter() {
+        match key {
+            "hostname" => opts.hostname = Some(string(item)?),
+            "port" => opts.port = Some(integer(item)?),
+            "addresses" => opts.addresses = Some(strings(item)?),
+            "socket" => opts.socket = Some(string(item)?),
+            "max_connections" => opts.max_connections = Some(integer(item)?),
+            "retry_after" => opts.retry_after = Some(integer(item)?),
+            _ => warnings.push(unknown("listen", key, position))
+        }
+    }
+
+    Ok(opts)
+}
+
+fn read_tls(table:&TomlTable, position:Position, warnings:&mut Vec<TomlError>) -> Result<TlsOpts> {
+    let mut opts = TlsOpts::default();
+
+    for (key, position, item) in table.iter() {
+        match key {
+            "cert" => opts.cert = Some(string(item)?),
+            "key" => opts.key = Some(string(item)?),
+            _ => warnings.push(unknown("tls", key, position))
+        }
+    }
+
+    if opts.cert.is_some() != opts.key.is_some() {
+        return Err(TomlError::new("Both cert and key are needed to use TLS", position));
+    }
+
+    Ok(opts)
+}
+
+fn read_limits(table:&TomlTable, warnings:&mut Vec<TomlError>) -> Result<LimitOpts> {
+    let mut opts = LimitOpts::default();
+
+    for (key, position, item) in table.iter() {
+        match key {
+            "max_request_line" => opts.max_request_line = Some(integer(item)?),
+            "max_header_bytes" => opts.max_header_bytes = Some(integer(item)?),
+            "max_headers" => opts.max_headers = Some(integer(item)?),
+            "max_uri" => opts.max_uri = Some(integer(item)?),
+            "keep_alive_max" => opts.keep_alive_max = Some(integer(item)?),
+            "rate_limit_max" => opts.rate_limit_max = Some(integer(item)?),
+            "rate_limit_window" => opts.rate_limit_window = Some(integer(item)?),
+            _ => warnings.push(unknown("limits", key, position))
+        }
+    }
+
+    Ok(opts)
+}
+
+fn read_timeouts(table:&TomlTable, warnings:&mut Vec<TomlError>) -> Result<TimeoutOpts> {
+    let mut opts = TimeoutOpts::default();
+
+    for (key, position, item) in table.iter() {
+        match key {
+            "keep_alive" => opts.keep_alive = Some(integer(item)?),
+            "header" => opts.header = Some(integer(item)?),
+            "body" => opts.body = Some(integer(item)?),
+            "write" => opts.write = Some(integer(item)?),
+            "shutdown" => opts.shutdown = Some(integer(item)?),
+            _ => warnings.push(unknown("timeouts", key, position))
+        }
+    }
+
+    Ok(opts)
+}
+
+fn read_log(table:&TomlTable, warnings:&mut Vec<TomlError>) -> Result<LogOpts> {
+    let mut opts = LogOpts::default();
+
+    for (key, position, item) in table.iter() {
+        match key {
+            "level" => opts.level = Some(parsed(item)?),
+            "outputs" => opts.outputs = Some(strings(item)?.iter().map(|output|Output::from(output.as_str())).collect()),
+            "max_size" => opts.max_size = Some(integer(item)?),
+            "rotate_interval" => opts.rotate_interval = Some(integer(item)?),
+            "keep" => opts.keep = Some(integer(item)?),
+            "access_log" => opts.access_log = Some(Output::from(item.as_str()?)),
+            "access_log_format" => opts.access_log_format = Some(parsed(item)?),
+            _ => warnings.push(unknown("log", key, position))
+        }
+    }
+
+    Ok(opts)
+}
+
+fn read_static_dir(item:&TomlItem, warnings:&mut Vec<TomlError>) -> Result<StaticDir> {
+    let mut path = None;
+    let mut dir = None;
+
+    for (key, position, item) in item.as_table()?.iter() {
+        match key {
+            "path" => {
+                let value = string(item)?;
+                if !value.starts_with('/') {
+                    return Err(TomlError::new("Path should start with /", item.position));
+                }
+                path = Some(value);
+            },
+            "dir" => dir = Some(string(item)?),
+            _ => warnings.push(unknown("static", key, position))
+        }
+    }
+
+    match (path, dir) {
+        (Some(path), Some(dir)) => Ok(StaticDir { path, dir }),
+        (None, _) => Err(TomlError::new("Missing key \"path\" in [[static]]", item.position)),
+        (_, None) => Err(TomlError::new("Missing key \"dir\" in [[static]]", item.position))
+    }
+}
+
+/// Fails on the first invalid value, keys that are not options are returned as warnings.
+pub fn parse_config(text:&str) -> Result<ConfigFile> {
+    let root = toml::parse(text)?;
+    let mut opts = ServerOpts::none();
+    let mut warnings = Vec::new();
+
+    for (key, position, item) in root.iter() {
+        match key {
+            "threads" => match integer(item)? {
+                0 => return Err(TomlError::new("Threads should be more than zero", item.position)),
+                value => opts.threads = Some(value)
+            },
+            "console" => opts.console = Some(item.as_bool()?),
+            "listen" => opts.listen = read_listen(item.as_table()?, &mut warnings)?,
+            "tls" => opts.tls = read_tls(item.as_table()?, item.position, &mut warnings)?,
+            "limits" => opts.limits = read_limits(item.as_table()?, &mut warnings)?,
+            "timeouts" => opts.timeouts = read_timeouts(item.as_table()?, &mut warnings)?,
+            "log" => opts.log = read_log(item.as_table()?, &mut warnings)?,
+            "static" => opts.static_dirs = Some(
+                item.as_array()?.iter()
+                    .map(|dir|read_static_dir(dir, &mut warnings))
+                    .collect::<Result<_>>()?
+            ),
+            _ => warnings.push(unknown("", key, position))
+        }
+    }
+
+    Ok(ConfigFile { opts, warnings })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use util::log::Level;
+    use crate::AccessLogFormat;
+
+    #[test]
+    fn read_sections() {
+        let config = parse_config(concat!(
+            "threads = 4\n",
+            "[listen]\n",
+            "addresses = [\"0.0.0.0:8080\", \"[::]:8080\"]\n",
+            "max_connections = 100\n",
+            "[limits]\n",
+            "max_uri = 2048\n",
+            "[timeouts]\n",
+            "keep_alive = 10\n",
+            "[log]\n",
+            "level = \"debug\"\n",
+            "outputs = [\"stdout\", \"server.log\"]\n",
+            "access_log_format = \"json\"\n",
+            "[[static]]\n",
+            "path = \"/assets\"\n",
+            "dir = \"./public\"\n"
+        )).unwrap();
+        let opts = config.opts;
+
+        assert!(config.warnings.is_empty());
+        assert_eq!(opts.threads, Some(4));
+        assert_eq!(opts.listen.addresses, Some(vec!["0.0.0.0:8080".to_string(), "[::]:8080".to_string()]));
+        assert_eq!(opts.listen.max_connections, Some(100));
+        assert_eq!(opts.limits.max_uri, Some(2048));
+        assert_eq!(opts.timeouts.keep_alive, Some(10));
+        assert_eq!(opts.log.level, Some(Level::Debug));
+        assert_eq!(opts.log.outputs, Some(vec![Output::Stdout, Output::File("server.log".to_string())]));
+        assert_eq!(opts.log.access_log_format, Some(AccessLogFormat::Json));
+        assert_eq!(opts.static_dirs, Some(vec![StaticDir::new("/assets", "./public")]));
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let config = parse_config("thread = 4\n[limits]\nmax_url = 10\n").unwrap();
+        let warnings: Vec<String> = config.warnings.iter().map(|e|e.to_string()).collect();
+
+        assert_eq!(warnings, [
+            "Unknown key \"thread\" at line 1, column 1",
+            "Unknown key \"max_url\" in [limits] at line 3, column 1"
+        ]);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let error = |text|parse_config(text).unwrap_err().to_string();
+
+        assert_eq!(error("[listen]\nport = 70000"), "70000 is out of range at line 2, column 8");
+        assert_eq!(error("[listen]\nport = \"80\""), "Expected an integer, found string at line 2, column 8");
+        assert_eq!(error("[log]\nlevel = \"loud\""), "Unknown log level \"loud\"! at line 2, column 9");
+        assert_eq!(error("threads = 0"), "Threads should be more than zero at line 1, column 11");
+        assert_eq!(error("[tls]\ncert = \"a.pem\""), "Both cert and key are needed to use TLS at line 1, column 1");
+        assert_eq!(error("[[static]]\npath = \"/\""), "Missing key \"dir\" in [[static]] at line 1, column 1");
+    }
+}
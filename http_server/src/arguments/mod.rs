@@ -1,48 +1,244 @@
+/// Command Line
+///
+/// Options are given as `--port 8080` or `--port=8080`, and the older `port=8080`
+/// is still accepted. Each option can also be set with an environment variable,
+/// named HTTP_ followed by the option, such as HTTP_PORT.
+///
+/// When an option is set more than once, the first of these is used:
+/// 1. Command line
+/// 2. Environment variable
+/// 3. Config file
+/// 4. `#[server]` attribute
+///
 use std::{
     io::{Result, ErrorKind},
-    fs::read_to_string
+    fmt::Display,
+    fs::read_to_string,
+    path::Path,
+    str::FromStr
 };
-use crate::ServerOpts;
+use util::log::Output;
+use crate::{ServerOpts, StaticDir};
+
+mod config;
+pub use config::{ConfigError, ConfigFile, parse_config};
 
 pub(crate) struct CommandLineArguments {
-    pub port:Option<u16>,
-    pub hostname:Option<String>,
-    pub threads:Option<usize>,
+    pub opts:ServerOpts,
     pub config:Option<String>
 }
 
-pub(crate) fn get_cmd_line_args() -> CommandLineArguments {
-    let mut port = None;
-    let mut hostname = None;
-    let mut threads = None;
-    let mut config = None;
-
-    for input in std::env::args() {
-        if let Some(index) = input.find("=") {
-            let key = &input[..index];
-            let value = &input[index+1..];
-
-            match key.to_ascii_lowercase().as_str() {
-                "port" => {
-                    port = Some(value.parse().unwrap())
-                },
-                "hostname" => {
-                    hostname = Some(value.to_owned())
-                },
-                "config" => {
-                    config = Some(value.to_owned())
-                },
-                "threads" => {
-                    threads = Some(value.parse().unwrap())
-                },
-                key => {
-                    panic!("Unknown command line argument: {key}!")
+pub(crate) enum CommandLine {
+    Run(Box<CommandLineArguments>),
+    Help,
+    Version
+}
+
+type Setter = fn(&mut CommandLineArguments, &str) -> std::result::Result<(), String>;
+
+struct CliOption {
+    name: &'static str,
+    value: &'static str,
+    help: &'static str,
+    set: Setter
+}
+
+macro_rules! option {
+    ($name:literal, $value:literal, $help:literal, |$args:ident, $input:ident| $set:expr) => {
+        CliOption {
+            name: $name,
+            value: $value,
+            help: $help,
+            set: |$args, $input| {
+                $set;
+                Ok(())
+            }
+        }
+    };
+}
+
+const OPTIONS: &[CliOption] = &[
+    option!("config", "FILE", "TOML config file to read", |args, value| args.config = Some(value.to_owned())),
+    option!("hostname", "HOST", "Hostname to listen on", |args, value| args.opts.listen.hostname = Some(value.to_owned())),
+    option!("port", "PORT", "Port to listen on", |args, value| args.opts.listen.port = Some(parse(value)?)),
+    option!("addresses", "HOST:PORT,..", "Addresses to listen on, instead of the hostname and port", |args, value| args.opts.listen.addresses = Some(split_list(value))),
+    option!("socket", "PATH", "Unix domain socket to listen on", |args, value| args.opts.listen.socket = Some(value.to_owned())),
+    option!("threads", "COUNT", "Threads in the pool", |args, value| args.opts.threads = Some(threads(value)?)),
+    option!("console", "BOOL", "Read commands from stdin", |args, value| args.opts.console = Some(parse(value)?)),
+    option!("tls_cert", "PATH", "PEM certificate chain", |args, value| args.opts.tls.cert = Some(value.to_owned())),
+    option!("tls_key", "PATH", "PEM private key", |args, value| args.opts.tls.key = Some(value.to_owned())),
+    option!("max_connections", "COUNT", "Connections served at once, zero for no limit", |args, value| args.opts.listen.max_connections = Some(parse(value)?)),
+    option!("retry_after", "SECS", "Retry-After sent to connections over the limit", |args, value| args.opts.listen.retry_after = Some(parse(value)?)),
+    option!("keep_alive_timeout", "SECS", "Time an idle connection is kept open", |args, value| args.opts.timeouts.keep_alive = Some(parse(value)?)),
+    option!("keep_alive_max", "COUNT", "Requests allowed on a single connection", |args, value| args.opts.limits.keep_alive_max = Some(parse(value)?)),
+    option!("shutdown_timeout", "SECS", "Time connections are given to finish when shutting down", |args, value| args.opts.timeouts.shutdown = Some(parse(value)?)),
+    option!("header_timeout", "SECS", "Time allowed to receive the request headers", |args, value| args.opts.timeouts.header = Some(parse(value)?)),
+    option!("body_timeout", "SECS", "Time the request body can go without data", |args, value| args.opts.timeouts.body = Some(parse(value)?)),
+    option!("write_timeout", "SECS", "Time allowed to send the response", |args, value| args.opts.timeouts.write = Some(parse(value)?)),
+    option!("max_request_line", "BYTES", "Bytes allowed in the request line", |args, value| args.opts.limits.max_request_line = Some(parse(value)?)),
+    option!("max_header_bytes", "BYTES", "Bytes allowed in all of the request headers", |args, value| args.opts.limits.max_header_bytes = Some(parse(value)?)),
+    option!("max_headers", "COUNT", "Headers allowed in a request", |args, value| args.opts.limits.max_headers = Some(parse(value)?)),
+    option!("max_uri", "BYTES", "Bytes allowed in the request uri", |args, value| args.opts.limits.max_uri = Some(parse(value)?)),
+    option!("rate_limit_max", "COUNT", "Requests each client can make per window", |args, value| args.opts.limits.rate_limit_max = Some(parse(value)?)),
+    option!("rate_limit_window", "SECS", "Time the rate limit is counted over", |args, value| args.opts.limits.rate_limit_window = Some(parse(value)?)),
+    option!("log_level", "LEVEL", "critical, error, warning, info or debug", |args, value| args.opts.log.level = Some(parse(value)?)),
+    option!("log_output", "OUTPUT,..", "stdout, stderr or file paths to log to", |args, value| args.opts.log.outputs = Some(split_outputs(value))),
+    option!("log_max_size", "BYTES", "Size a log file is rotated at", |args, value| args.opts.log.max_size = Some(parse(value)?)),
+    option!("log_rotate_interval", "SECS", "Time a log file is rotated after", |args, value| args.opts.log.rotate_interval = Some(parse(value)?)),
+    option!("log_keep", "COUNT", "Rotated log files kept", |args, value| args.opts.log.keep = Some(parse(value)?)),
+    option!("access_log", "OUTPUT", "stdout, stderr or a file path to write the access log to", |args, value| args.opts.log.access_log = Some(Output::from(value))),
+    option!("access_log_format", "FORMAT", "common, combined or json", |args, value| args.opts.log.access_log_format = Some(parse(value)?)),
+    option!("static_dirs", "PATH=DIR,..", "Directories for the routers to serve files from", |args, value| args.opts.static_dirs = Some(split_static_dirs(value)?))
+];
+
+fn parse<T: FromStr<Err: Display>>(value:&str) -> std::result::Result<T, String> {
+    value.parse().map_err(|e:T::Err|e.to_string())
+}
+
+/// The thread pool can not start without any threads.
+fn threads(value:&str) -> std::result::Result<usize, String> {
+    match parse(value)? {
+        0 => Err(String::from("Threads should be more than zero")),
+        count => Ok(count)
+    }
+}
+
+/// Dashes and underscores are both accepted, so --log-level is the same as --log_level.
+fn find_option(name:&str) -> std::result::Result<&'static CliOption, String> {
+    let name = name.to_ascii_lowercase().replace('-', "_");
+
+    OPTIONS.iter()
+        .find(|option|option.name == name)
+        .ok_or_else(||format!("Unknown option --{}, run with --help to list the options!", name.replace('_', "-")))
+}
+
+fn set_option(args:&mut CommandLineArguments, option:&CliOption, source:&str, value:&str) -> std::result::Result<(), String> {
+    (option.set)(args, value)
+        .map_err(|e|format!("Invalid value \"{}\" for {}: {}", value, source, e))
+}
+
+fn read_args(mut input:impl Iterator<Item = String>) -> std::result::Result<CommandLine, String> {
+    let mut args = CommandLineArguments {
+        opts: ServerOpts::none(),
+        config: None
+    };
+
+    while let Some(arg) = input.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(CommandLine::Help),
+            "-V" | "--version" => return Ok(CommandLine::Version),
+            _ => {}
+        }
+
+        let (name, value) = match arg.strip_prefix("--") {
+            Some(flag) => match flag.split_once("=") {
+                Some((name, value)) => (name.to_owned(), value.to_owned()),
+                None => {
+                    let value = input.next()
+                        .ok_or_else(||format!("Missing value for --{}!", flag))?;
+                    (flag.to_owned(), value)
                 }
+            },
+            None => match arg.split_once("=") {
+                Some((name, value)) => (name.to_owned(), value.to_owned()),
+                None => return Err(format!("Unexpected argument \"{}\", run with --help to list the options!", arg))
             }
+        };
+
+        let option = find_option(&name)?;
+        set_option(&mut args, option, &format!("--{}", name), &value)?;
+    }
+
+    Ok(CommandLine::Run(Box::new(args)))
+}
+
+fn read_env() -> std::result::Result<CommandLineArguments, String> {
+    let mut args = CommandLineArguments {
+        opts: ServerOpts::none(),
+        config: None
+    };
+
+    for option in OPTIONS {
+        let name = format!("HTTP_{}", option.name.to_ascii_uppercase());
+        if let Ok(value) = std::env::var(&name) {
+            set_option(&mut args, option, &name, &value)?;
         }
     }
 
-    CommandLineArguments{ port, hostname, config, threads}
+    Ok(args)
+}
+
+/// Options from the command line, with the environment variables filling in the ones not given there.
+pub(crate) fn get_cmd_line_args() -> std::result::Result<CommandLine, String> {
+    let args = match read_args(std::env::args().skip(1))? {
+        CommandLine::Run(args) => args,
+        other => return Ok(other)
+    };
+    let env = read_env()?;
+
+    Ok(CommandLine::Run(Box::new(CommandLineArguments {
+        opts: args.opts.or(env.opts),
+        config: args.config.or(env.config)
+    })))
+}
+
+fn program_name() -> String {
+    std::env::args().next()
+        .as_deref()
+        .and_then(|path|Path::new(path).file_name()?.to_str().map(String::from))
+        .unwrap_or_else(||"server".to_string())
+}
+
+pub(crate) fn version() -> String {
+    format!("{} {}", program_name(), env!("CARGO_PKG_VERSION"))
+}
+
+pub(crate) fn help() -> String {
+    let flags: Vec<(String, &str)> = OPTIONS.iter()
+        .map(|option|(format!("--{} <{}>", option.name.replace('_', "-"), option.value), option.help))
+        .chain([
+            ("-h, --help".to_string(), "Print this help"),
+            ("-V, --version".to_string(), "Print the version")
+        ])
+        .collect();
+    let width = flags.iter().map(|(flag, _)|flag.len()).max().unwrap_or(0);
+
+    let mut help = format!("Usage: {} [OPTIONS]\n\nOptions:\n", program_name());
+    for (flag, text) in flags {
+        help.push_str(&format!("  {:width$}  {}\n", flag, text));
+    }
+    help.push_str(concat!(
+        "\nEach option can also be set with an HTTP_ environment variable, such as HTTP_PORT.\n",
+        "The command line is used first, then the environment, the config file and last\n",
+        "the #[server] attribute.\n"
+    ));
+
+    help
+}
+
+/// Comma seperated list, such as "0.0.0.0:80,[::]:80".
+fn split_list(value:&str) -> Vec<String> {
+    value.split(",")
+        .map(|s|s.trim())
+        .filter(|s|!s.is_empty())
+        .map(|s|s.to_string())
+        .collect()
+}
+
+fn split_outputs(value:&str) -> Vec<Output> {
+    split_list(value).iter()
+        .map(|output|Output::from(output.as_str()))
+        .collect()
+}
+
+/// Comma seperated list of "path=dir", such as "/assets=./public".
+fn split_static_dirs(value:&str) -> std::result::Result<Vec<StaticDir>, String> {
+    split_list(value).iter()
+        .map(|entry|match entry.split_once("=") {
+            Some((path, dir)) => Ok(StaticDir::new(path.trim(), dir.trim())),
+            None => Err(format!("Static directory {entry} should be path=dir!"))
+        })
+        .collect()
 }
 
 fn open_config_file(name:&str) -> Result<Option<String>> {
@@ -55,36 +251,55 @@ fn open_config_file(name:&str) -> Result<Option<String>> {
     }
 }
 
-pub(crate) fn read_config_file(name:&str)-> Result<Option<ServerOpts>> {
-    open_config_file(name).map(|opt|opt.map(|buffer|{
-        let mut port = None;
-        let mut hostname = None;
-        let mut threads = None;
-
-        for line in buffer.split("\n") {
-            let mut parts = line.split("=");
-
-            if let Some(key) = parts.next().map(|s|s.trim()) {
-                let value = parts.next()
-                    .map(|s|s.trim())
-                    .unwrap_or("");
-
-                match key {
-                    "port" => match value.parse() {
-                        Ok(value) => port = Some(value),
-                        Err(_) => println!("Unable to set {} to port value!", value)
-                    },
-                    "hostname" => hostname = Some(value.to_string()),
-                    "threads" => match value.parse() {
-                        Ok(value) => threads = Some(value),
-                        Err(_) => println!("Unable to set {} to threads value!", value)
-                    },
-                    _ => println!("Unkown property \"{}\" and will be ignored!", key)
-                }
-            }
+/// Reads a TOML config file, None if it does not exist.
+///
+/// Invalid files are returned as an InvalidData error, with a ConfigError inside.
+pub(crate) fn read_config_file(name:&str) -> Result<Option<ConfigFile>> {
+    match open_config_file(name)? {
+        Some(buffer) => parse_config(&buffer)
+            .map(Some)
+            .map_err(|error|std::io::Error::new(ErrorKind::InvalidData, ConfigError {
+                file: name.to_string(),
+                error
+            })),
+        None => Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(list:&[&str]) -> std::result::Result<CommandLine, String> {
+        read_args(list.iter().map(|arg|arg.to_string()))
+    }
+
+    fn opts(list:&[&str]) -> ServerOpts {
+        match read(list) {
+            Ok(CommandLine::Run(args)) => args.opts,
+            _ => panic!("Expected options from {:?}", list)
         }
+    }
 
-        ServerOpts { port, hostname, threads }
-    }))
-}
+    #[test]
+    fn flags() {
+        assert_eq!(opts(&["--port", "8080"]).listen.port, Some(8080));
+        assert_eq!(opts(&["--port=8080"]).listen.port, Some(8080));
+        assert_eq!(opts(&["port=8080"]).listen.port, Some(8080));
+        assert_eq!(opts(&["--keep-alive-max", "5"]).limits.keep_alive_max, Some(5));
+        assert_eq!(opts(&["--log_level=debug"]).log.level, Some(util::log::Level::Debug));
+        assert!(matches!(read(&["--port", "80", "--help"]), Ok(CommandLine::Help)));
+        assert!(matches!(read(&["-V"]), Ok(CommandLine::Version)));
+    }
 
+    #[test]
+    fn errors() {
+        let error = |list:&[&str]|read(list).err().unwrap();
+
+        assert_eq!(error(&["--prot", "80"]), "Unknown option --prot, run with --help to list the options!");
+        assert_eq!(error(&["--port"]), "Missing value for --port!");
+        assert_eq!(error(&["--port", "http"]), "Invalid value \"http\" for --port: invalid digit found in string");
+        assert_eq!(error(&["--threads", "0"]), "Invalid value \"0\" for --threads: Threads should be more than zero");
+        assert_eq!(error(&["8080"]), "Unexpected argument \"8080\", run with --help to list the options!");
+    }
+}
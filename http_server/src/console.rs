@@ -0,0 +1,169 @@
+/// Operator Console
+///
+/// Reads commands from stdin on its own thread, so the server can be checked
+/// on and managed while it is running. Enter "help" for the list of commands.
+///
+use std::{
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering}
+    },
+    time::{Duration, Instant}
+};
+use async_lib::executor::{
+    idle_threads, queued_jobs, queued_tasks, spawn_task, task_count, thread_count,
+    thread::ThreadProcess
+};
+use util::log::{self, error, info, Level};
+use crate::{
+    Server,
+    reload::{current, reload_config},
+    shutdown::{active_connections, drain, drained, is_draining, is_shutting_down, shut_down_gracefully}
+};
+
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+/// Time and request count of the last status, used for the current rate.
+static LAST_STATUS: Mutex<Option<(Instant, u64)>> = Mutex::new(None);
+
+const HELP: &str = "\
+Commands:
+  status           Uptime, open connections and requests per second
+  connections      Number of open connections
+  threads          Threads in the pool and how many are idle
+  queue            Tasks and jobs waiting to be run
+  loglevel [lvl]   Shows or sets the log level (critical, error, warning, info, debug)
+  reload           Reads the config file again, the same as SIGHUP
+  drain            Stops accepting new connections and closes idle ones, then
+                   reports once every connection is closed
+  quit             Shuts down once open connections are closed
+  help             Shows this message";
+
+/// Starts the uptime from now.
+pub(crate) fn mark_started() {
+    LazyLock::force(&STARTED);
+}
+
+pub(crate) fn uptime() -> Duration {
+    STARTED.elapsed()
+}
+
+#[inline]
+pub(crate) fn count_request() {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Hours, minutes and seconds, such as 1h 02m 03s.
+fn format_duration(duration:Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn per_second(count:u64, duration:Duration) -> f64 {
+    count as f64 / duration.as_secs_f64().max(f64::EPSILON)
+}
+
+fn print_status() {
+    let now = Instant::now();
+    let uptime = now.duration_since(*STARTED);
+    let requests = REQUESTS.load(Ordering::Relaxed);
+
+    let (since, previous) = LAST_STATUS.lock().unwrap()
+        .replace((now, requests))
+        .unwrap_or((*STARTED, 0));
+
+    println!("Uptime: {}", format_duration(uptime));
+    println!(
+        "Connections: {}{}",
+        active_connections(),
+        if is_draining() { " (draining)" } else { "" }
+    );
+    println!(
+        "Requests: {} total, {:.2}/sec since last status, {:.2}/sec average",
+        requests,
+        per_second(requests - previous, now.duration_since(since)),
+        per_second(requests, uptime)
+    );
+}
+
+fn print_threads() {
+    let total = thread_count();
+    let idle = idle_threads().min(total);
+
+    println!("Threads: {} total, {} busy, {} idle", total, total - idle, idle);
+}
+
+fn print_queue() {
+    println!("Tasks: {} open, {} waiting to be polled", task_count(), queued_tasks());
+    println!("Jobs: {} waiting for a thread", queued_jobs());
+}
+
+fn set_log_level(value:Option<&str>) {
+    match value.map(|value|value.parse::<Level>()) {
+        None => println!("Log level is {}", log::level()),
+        Some(Ok(level)) => {
+            log::set_level(level);
+            println!("Log level set to {}", level);
+        },
+        Some(Err(e)) => println!("{}", e)
+    }
+}
+
+/// Runs until stdin is closed, or the server is shut down.
+pub(crate) fn console<S: Server>() -> impl ThreadProcess {
+    move ||{
+        let stdin = std::io::stdin();
+        let mut input = String::new();
+
+        info!("Enter \"help\" to list console commands, or \"quit\" to shutdown server!");
+        //Started before the thread pool, so is_running can not be checked yet.
+        while !is_shutting_down() {
+            input.clear();
+            match stdin.read_line(&mut input) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            let mut args = input.split_whitespace();
+            let command = match args.next() {
+                Some(command) => command.to_lowercase(),
+                None => continue
+            };
+
+            match command.as_str() {
+                "quit" => {
+                    shut_down_gracefully(current::<S>().shutdown_timeout());
+                    break;
+                },
+                "status" => print_status(),
+                "connections" => println!("{} active connections", active_connections()),
+                "threads" => print_threads(),
+                "queue" => print_queue(),
+                "loglevel" => set_log_level(args.next()),
+                "reload" => if let Err(e) = reload_config::<S>() {
+                    error!("Unable to reload config: {}", e);
+                },
+                "drain" => if drain() {
+                    info!("Draining, no longer accepting new connections...");
+                    spawn_task(async {
+                        if drained().await {
+                            info!("Drained, all connections are closed, enter \"quit\" to shutdown server!");
+                        }
+                    });
+                } else {
+                    println!("Already draining");
+                },
+                "help" => println!("{}", HELP),
+                _ => println!("Unknown command \"{}\", enter \"help\" for a list of commands", input.trim())
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+/// Server Events
+///
+/// Emitted through an EventEmitter, with an EventPayload written as a JSON
+/// object for the listeners:
+///
+/// | Event            | Payload                                   |
+/// |------------------|-------------------------------------------|
+/// | start            | address                                   |
+/// | connection       | peer, address                             |
+/// | request          | peer, method, path                        |
+/// | routing          | peer, method, path, router                |
+/// | response         | peer, method, path, status, duration      |
+/// | error            | message, and the request if there was one |
+/// | connection_close | peer, duration                            |
+/// | close            | duration                                  |
+///
+/// Nothing is emitted until events has been called, so a server without
+/// listeners does not spawn a task for each event.
+///
+use std::{
+    fmt,
+    net::SocketAddr,
+    sync::{
+        LazyLock, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering}
+    },
+    time::{Duration, Instant}
+};
+use http_core::request::RequestBuilder;
+use async_lib::{EventEmitter, EventEmitterWrapper, io::AsyncRead};
+use crate::{
+    access_log::JsonString,
+    protocol::Transport
+};
+
+pub type ServerEvents = EventEmitterWrapper<()>;
+
+static EVENTS: LazyLock<Mutex<ServerEvents>> = LazyLock::new(||Mutex::new(EventEmitterWrapper::new(())));
+static LISTENING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ServerEvent {
+    Start,
+    Connection,
+    Request,
+    Routing,
+    Response,
+    Error,
+    ConnectionClose,
+    Close
+}
+
+impl ServerEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Connection => "connection",
+            Self::Request => "request",
+            Self::Routing => "routing",
+            Self::Response => "response",
+            Self::Error => "error",
+            Self::ConnectionClose => "connection_close",
+            Self::Close => "close"
+        }
+    }
+}
+
+impl fmt::Display for ServerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Values sent with an event, only the ones that are set are written.
+#[derive(Clone, Default, Debug)]
+pub struct EventPayload {
+    /// Address of the client, None for a Unix socket.
+    pub peer: Option<SocketAddr>,
+    /// Address the server is listening on, or the connection was accepted on.
+    pub address: Option<String>,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    /// Router that answered the request.
+    pub router: Option<String>,
+    pub status: Option<u16>,
+    /// Time taken by the request, or how long the connection or server was open.
+    pub duration: Option<Duration>,
+    pub message: Option<String>
+}
+
+impl EventPayload {
+    pub fn request<S: AsyncRead>(req:&RequestBuilder<S>) -> Self {
+        Self {
+            peer: req.peer_addr,
+            method: Some(req.method.to_str().to_string()),
+            path: Some(req.url.pathname()),
+            ..Self::default()
+        }
+    }
+
+    pub fn message<M:ToString>(message:M) -> Self {
+        Self {
+            message: Some(message.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+impl fmt::Display for EventPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peer = self.peer.map(|addr|addr.to_string());
+        let strings = [
+            ("peer", peer.as_deref()),
+            ("address", self.address.as_deref()),
+            ("method", self.method.as_deref()),
+            ("path", self.path.as_deref()),
+            ("router", self.router.as_deref())
+        ];
+        let mut first = true;
+
+        f.write_str("{")?;
+        for (key, value) in strings {
+            if value.is_some() {
+                write!(f, "{}\"{}\":{}", if first { "" } else { "," }, key, JsonString(value))?;
+                first = false;
+            }
+        }
+        if let Some(status) = self.status {
+            write!(f, "{}\"status\":{}", if first { "" } else { "," }, status)?;
+            first = false;
+        }
+        if let Some(duration) = self.duration {
+            write!(f, "{}\"duration\":{:.3}", if first { "" } else { "," }, duration.as_secs_f64())?;
+            first = false;
+        }
+        if let Some(message) = &self.message {
+            write!(f, "{}\"message\":{}", if first { "" } else { "," }, JsonString(Some(message)))?;
+        }
+        f.write_str("}")
+    }
+}
+
+/// Listeners can be added or removed at any time, including while the server is running.
+pub fn events() -> MutexGuard<'static, ServerEvents> {
+    LISTENING.store(true, Ordering::Relaxed);
+    EVENTS.lock().unwrap()
+}
+
+pub(crate) fn is_listening() -> bool {
+    LISTENING.load(Ordering::Relaxed)
+}
+
+/// Listeners are called in a new task.
+pub fn emit(event:ServerEvent, payload:EventPayload) {
+    if is_listening() {
+        EVENTS.lock().unwrap().emit(event, payload);
+    }
+}
+
+/// Waits for the listeners, for events sent while the thread pool is stopping.
+pub(crate) async fn emit_now(event:ServerEvent, payload:EventPayload) {
+    if is_listening() {
+        let future = EVENTS.lock().unwrap().emit_now(event, payload);
+        future.await;
+    }
+}
+
+/// Emits connection when opened, and connection_close when dropped.
+pub(crate) struct ConnectionEvents(Option<(Option<SocketAddr>, Instant)>);
+
+impl ConnectionEvents {
+    pub(crate) fn new<T: Transport>(stream:&T) -> Self {
+        if !is_listening() {
+            return Self(None);
+        }
+
+        let peer = stream.peer_addr();
+        emit(ServerEvent::Connection, EventPayload {
+            peer,
+            address: stream.local_addr().map(|addr|addr.to_string()),
+            ..EventPayload::default()
+        });
+
+        Self(Some((peer, Instant::now())))
+    }
+}
+
+impl Drop for ConnectionEvents {
+    fn drop(&mut self) {
+        if let Some((peer, start)) = self.0.take() {
+            emit(ServerEvent::ConnectionClose, EventPayload {
+                peer,
+                duration: Some(start.elapsed()),
+                ..EventPayload::default()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn payload_json() {
+        let payload = EventPayload {
+            peer: Some("127.0.0.1:8080".parse().unwrap()),
+            method: Some("GET".to_string()),
+            path: Some("/say \"hi\"".to_string()),
+            status: Some(200),
+            duration: Some(Duration::from_millis(1500)),
+            ..EventPayload::default()
+        };
+
+        assert_eq!(
+            payload.to_string(),
+            "{\"peer\":\"127.0.0.1:8080\",\"method\":\"GET\",\"path\":\"/say \\\"hi\\\"\",\"status\":200,\"duration\":1.500}"
+        );
+        assert_eq!(EventPayload::default().to_string(), "{}");
+        assert_eq!(EventPayload::message("oops").to_string(), "{\"message\":\"oops\"}");
+    }
+}
@@ -1,84 +1,271 @@
 #![feature(str_from_raw_parts)]
+#![feature(trait_alias)]
+use std::{pin::Pin, time::Duration};
 use http_core::{
-    request::RequestBuilder, response::Response, version::Version
+    request::RequestBuilder, response::Response, version::Version, status::HttpStatus, url::{Hostname, Protocol},
+    method::Method, upgrade::Upgraded, error::{HttpError, HttpErrorKind},
+    rate_limit::RateLimiter
 };
 use async_lib::{
     executor::*,
-    net::TcpStream
+    io::AsyncBufReader,
+    net::TcpStream,
+    time::timeout
+};
+use util::{
+    log::{error, warning, LogConfig},
+    toml::TomlError
 };
 use arguments::*;
 pub use http_macro::server;
 use protocol::{
+    BuildError,
     build_request,
-    write_response
+    frame_response,
+    write_response,
+    keep_alive,
+    persist_connection,
+    error_response,
+    TimedWriter,
+    http2,
+    Transport
 };
+pub use protocol::{Limits, websocket};
 use process::*;
+use tls::TlsStream;
+use access_log::AccessEntry;
+pub use access_log::{AccessLog, AccessLogFormat};
+use shutdown::{ConnectionGuard, listen_for_signals, wait_for_request};
+use reload::current;
+pub use reload::reload_config;
+pub use shutdown::{shut_down_gracefully, active_connections, drain, is_draining};
+pub use events::{events, emit, EventPayload, ServerEvent, ServerEvents};
+use events::ConnectionEvents;
+
+pub use options::*;
+pub use arguments::{ConfigError, ConfigFile, parse_config};
 
+mod access_log;
 mod arguments;
+mod console;
+mod events;
+mod options;
 mod protocol;
 mod process;
+mod reload;
+mod shutdown;
+mod tls;
 
-pub struct ServerOpts {
-    pub port:Option<u16>,
-    pub hostname:Option<String>,
-    pub threads:Option<usize>
-}
+/// Options from the command line, environment, and config file, in that order.
+///
+/// --help and --version are printed and exit the process, while invalid
+/// arguments are returned as an InvalidInput error.
+pub fn get_server_opts(config_filename:Option<&str>) -> std::io::Result<ServerOpts> {
+    let (opts, warnings) = read_server_opts(config_filename)?;
 
-impl ServerOpts {
-    pub fn new<H:ToString>(hostname:H, port:u16, threads:usize) -> Self {
-        Self {
-            hostname: Some(hostname.to_string()),
-            port: Some(port.into()),
-            threads: Some(threads.into())
+    if let Some(filename) = reload::CONFIG_FILE.lock().unwrap().as_deref() {
+        for w in warnings {
+            warning!("{}: {}", filename, w);
         }
     }
 
-    pub fn hostname<Hostname:ToString>(value:Hostname) -> Self {
-        Self {
-            hostname: Some(value.to_string()),
-            threads: None,
-            port: None
+    Ok(opts)
+}
+
+/// Options from the command line and environment, with the config file filling in the ones not given there.
+fn read_server_opts(config_filename:Option<&str>) -> std::io::Result<(ServerOpts, Vec<TomlError>)> {
+    let args = arguments::get_cmd_line_args()
+        .map_err(|e|std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    let CommandLineArguments{ opts, config } = match args {
+        CommandLine::Run(args) => *args,
+        CommandLine::Help => {
+            print!("{}", arguments::help());
+            std::process::exit(0);
+        },
+        CommandLine::Version => {
+            println!("{}", arguments::version());
+            std::process::exit(0);
         }
+    };
+
+    let config = config.or(config_filename.map(String::from));
+    reload::CONFIG_FILE.lock().unwrap().clone_from(&config);
+
+    if let Some(filename) = config.as_deref()
+        && let Some(file) = arguments::read_config_file(filename)? {
+        return Ok((opts.or(file.opts), file.warnings));
     }
 
-    pub fn port(value:u16) -> Self {
-        Self {
-            hostname: None,
-            threads: None,
-            port: Some(value.into())
-        }
+    Ok((opts, Vec::new()))
+}
+
+/// Handles every request sent over a single connection.
+/// 
+/// The connection is closed when the client or handler asks for it, the request
+/// limit is reached, no new request starts before the keep alive timeout, or the
+/// server is shutting down. `guard` keeps the connection counted until it is closed.
+async fn handle_connection<S: Server, T: Transport>(server:S, stream:T, guard:ConnectionGuard) {
+    let _events = ConnectionEvents::new(&stream);
+    let limits = server.limits();
+    let mut reader = AsyncBufReader::with_capacity(limits.buffer_size(), stream);
+    let mut count: usize = 0;
+
+    match timeout(server.keep_alive_timeout(), http2::is_preface(&mut reader)).await {
+        Ok(Ok(true)) => return handle_http2(&server, &mut reader, None, guard.is_overloaded()).await,
+        Ok(Ok(false)) => {},
+        _ => return
     }
 
-    pub fn threads(value:usize) -> Self {
-        Self {
-            hostname: None,
-            threads: Some(value.into()),
-            port: None
+    loop {
+        count += 1;
+
+        match timeout(server.keep_alive_timeout(), wait_for_request(&mut reader)).await {
+            Ok(true) => {},
+            _ => break
+        }
+
+        //Started once the first byte has arrived, so an idle connection is not sent a 408.
+        let result = timeout(
+            server.header_timeout(),
+            build_request(&mut reader, server.hostname(), server.port(), &limits, server.body_timeout())
+        ).await.unwrap_or(Err(BuildError::HeaderTimeout));
+
+        let mut req = match result {
+            Ok(req) => req,
+            Err(BuildError::EmptyRequest) => break,
+            Err(BuildError::IoError(e)) => {
+                error!("{}", e);
+                emit(ServerEvent::Error, EventPayload::message(&e));
+                break;
+            }
+            Err(err) => {
+                send_response(&server, reader.get_mut(), error_response(err), Version::new(), false, None).await;
+                break;
+            }
+        };
+
+        let mut access = AccessEntry::new(&req);
+        console::count_request();
+
+        //The request is read first, so the client is not reset before seeing the response.
+        if guard.is_overloaded() {
+            let mut resp = overloaded_response(&server);
+            resp.headers.set("Connection", "close");
+
+            send_response(&server, reader.get_mut(), resp, req.version, req.method == Method::HEAD, Some(&mut access)).await;
+            break;
+        }
+
+        //h2c is only for cleartext connections, TLS uses ALPN instead.
+        if let Protocol::Http = reader.get_ref().protocol() && let Some(settings) = http2::upgrade_settings(&req) {
+            let mut resp = Response::new(HttpStatus::SwitchingProtocols, None);
+            resp.headers.set("Connection", "Upgrade");
+            resp.headers.set("Upgrade", "h2c");
+
+            //The request is logged once it has been answered as the first stream.
+            if send_response(&server, reader.get_mut(), resp, req.version, false, None).await {
+                handle_http2(&server, &mut reader, Some((req, settings)), false).await;
+            }
+            break;
+        }
+
+        let version = req.version;
+        let head = req.method == Method::HEAD;
+        let mut resp = server.handle_request(&mut req).await;
+
+        //The handler takes over the connection, see websocket::accept.
+        if resp.status.code() == 101 && let Some(upgrade) = resp.upgrade.take() {
+            let sent = send_response(&server, reader.get_mut(), resp, version, false, Some(&mut access)).await;
+            drop(access);
+
+            if sent {
+                let buffered = reader.buffer().to_vec();
+                upgrade(Upgraded::new(buffered, reader.into_inner())).await;
+            }
+            return;
+        }
+
+        //Without a length the end of the response is marked by closing the connection.
+        let allowed = count < server.keep_alive_max() && !is_draining()
+            && (frame_response(&mut resp, version) || head);
+
+        //The body is only skipped when the connection is going to be reused.
+        let body_read = allowed && keep_alive(&req) && req.drain_body().await.is_ok();
+        let persist = persist_connection(&req, &mut resp, allowed, body_read);
+
+        if !send_response(&server, reader.get_mut(), resp, version, head, Some(&mut access)).await || !persist {
+            break;
         }
     }
+}
 
-    pub fn none() -> Self {
-        Self { port: None, hostname: None, threads: None }
+/// Sent instead of handling requests on a connection opened over max_connections.
+fn overloaded_response<S: Server>(server:&S) -> Response {
+    let mut resp = Response::from_error(HttpError::new(HttpErrorKind::ServiceUnavailable, "Too many open connections!"));
+    if let Some(seconds) = server.retry_after() {
+        resp.headers.set("Retry-After", seconds.to_string().as_str());
     }
+
+    resp
 }
 
-pub fn get_server_opts(config_filename:Option<&str>) -> std::io::Result<ServerOpts> {
-    let CommandLineArguments{
-        mut port,
-        mut hostname,
-        mut threads,
-        config
-    } = arguments::get_cmd_line_args();
-
-    if let Some(filename) = config.as_deref().or(config_filename) {
-        if let Some(opts) = arguments::read_config_file(filename)? {
-            port = port.or(opts.port);
-            hostname = hostname.or(opts.hostname);
-            threads = threads.or(opts.threads);
+/// Writes the response, returning false if it failed or the client stopped
+/// reading for longer then the write timeout.
+async fn send_response<S: Server, T: Transport>(server:&S, stream:&mut T, resp:Response, version:Version, head:bool, mut access:Option<&mut AccessEntry>) -> bool {
+    let mut writer = TimedWriter::new(stream, server.write_timeout());
+    if let Some(access) = access.as_deref_mut() {
+        access.status(resp.status.code());
+    }
+
+    match write_response(&mut writer, resp, version, head).await {
+        Ok(sent) => {
+            if let Some(access) = access {
+                access.sent(sent);
+            }
+            true
+        },
+        Err(e) => {
+            error!("{}", e);
+            emit(ServerEvent::Error, EventPayload::message(&e));
+            false
         }
     }
+}
+
+/// Handles every stream sent over an HTTP/2 connection.
+async fn handle_http2<S: Server, T: Transport>(server:&S, reader:&mut AsyncBufReader<T>, upgrade:Option<http2::Upgrade>, overloaded:bool) {
+    let clone = server.clone();
+    let handler = move |req:*mut RequestBuilder<TcpStream>| -> Pin<Box<dyn Future<Output = Response>>> {
+        let server = clone.clone();
+        console::count_request();
+
+        if overloaded {
+            return Box::pin(async move { overloaded_response(&server) });
+        }
+
+        //SAFETY: Connection keeps the request alive until the future is dropped.
+        Box::pin(async move {
+            server.handle_request(unsafe{ &mut *req }).await
+        })
+    };
 
-    Ok(ServerOpts { port, hostname, threads })
+    let mut conn = http2::Connection::new(
+        reader,
+        server.hostname(),
+        server.port(),
+        server.keep_alive_timeout(),
+        handler
+    );
+
+    if let Some((req, settings)) = upgrade {
+        conn.upgrade(req, settings);
+    }
+
+    if let Err(e) = conn.run().await {
+        error!("{}", e);
+        emit(ServerEvent::Error, EventPayload::message(&e));
+    }
 }
 
 pub trait Server: 'static + Sized + Sync + Send + Clone {
@@ -86,38 +273,97 @@ pub trait Server: 'static + Sized + Sync + Send + Clone {
     fn hostname(&self) -> &str;
     fn port(&self) -> u16;
     fn threads(&self) -> usize;
+    fn keep_alive_timeout(&self) -> Duration;
+    fn keep_alive_max(&self) -> usize;
+    fn tls_cert(&self) -> Option<&str>;
+    fn tls_key(&self) -> Option<&str>;
+    fn socket(&self) -> Option<&str>;
+    fn addresses(&self) -> &[String];
+    fn shutdown_timeout(&self) -> Duration;
+    fn header_timeout(&self) -> Duration;
+    fn body_timeout(&self) -> Duration;
+    fn write_timeout(&self) -> Duration;
+    fn limits(&self) -> Limits;
+    fn max_connections(&self) -> usize;
+    fn retry_after(&self) -> Option<u64>;
+    /// Limiter every request is counted against, before the routers.
+    fn rate_limiter(&self) -> Option<&'static RateLimiter>;
+    /// Limiters from the rate_limit of the routers, which keep the limits they were created with.
+    fn router_limiters(&self) -> Vec<&'static RateLimiter>;
+    fn rate_limit_max(&self) -> Option<u32>;
+    fn rate_limit_window(&self) -> Option<Duration>;
+    fn logging(&self) -> &LogConfig;
+    fn access_log(&self) -> Option<&AccessLog>;
+    fn console(&self) -> bool;
+    /// Adds the listeners from the server attribute, see events.
+    fn event_listeners(&self) -> Option<fn(&mut ServerEvents)>;
+    /// Directories on disk for the routers to serve files from.
+    fn static_dirs(&self) -> &[StaticDir];
 
     fn handle_request(&self, req:&mut RequestBuilder<TcpStream>) -> impl Future<Output = Response>;
 
+    /// Connections are handled with the settings in use when they were opened,
+    /// see reload_config.
     fn start(&self) -> std::io::Result<()> {
-        let server = self.clone();
-
-        queue_process(read_stdin);
-        queue_process(tcp_listener(server.address(), move|mut stream|{
-            let clone = server.clone();
-
-            spawn_task(async move {
-                if let Err(e) = match build_request(&mut stream, clone.hostname(), clone.port()).await {
-                    Ok(mut req) => {
-                        write_response(
-                            &mut stream,
-                            clone.handle_request(&mut req).await,
-                            req.version
-                        ).await
-                    },
-                    Err(err) => {
-                        write_response(
-                            &mut stream,
-                            Response::from_error(err.into()),
-                            Version::new()
-                        ).await
+        reload::apply(self.clone())?;
+        console::mark_started();
+
+        if let Some(register) = self.event_listeners() {
+            register(&mut events());
+        }
+
+        //Not part of the thread pool, so a blocked read does not keep it from closing.
+        if self.console() {
+            std::thread::Builder::new()
+                .name("console".to_string())
+                .spawn(console::console::<Self>())?;
+        }
+        listen_for_signals::<Self>()?;
+
+        match (self.socket(), self.tls_cert(), self.tls_key()) {
+            #[cfg(unix)]
+            (Some(path), None, None) => {
+                queue_process(unix_listener(path.to_string(), move|stream|{
+                    spawn_task(handle_connection(current::<Self>(), stream, ConnectionGuard::new()));
+                })? );
+            },
+            #[cfg(not(unix))]
+            (Some(_), None, None) => return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix sockets are not supported on this platform!"
+            )),
+            (Some(_), _, _) => return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "TLS can not be used with a Unix socket!"
+            )),
+            (None, Some(cert), Some(key)) => {
+                let config = tls::load_config(cert, key)?;
+
+                queue_process(tcp_listener(self.listen_addresses(), move|stream|{
+                    match TlsStream::new(stream, config.clone()) {
+                        Ok(stream) => spawn_task(handle_connection(current::<Self>(), stream, ConnectionGuard::new())),
+                        Err(e) => {
+                            error!("{}", e);
+                            emit(ServerEvent::Error, EventPayload::message(&e));
+                        }
                     }
-                } {
-                    println!("ERROR!: {}", e)
-                }
-            });
-        })? );
-        
+                })? );
+            },
+            (None, None, None) => {
+                queue_process(tcp_listener(self.listen_addresses(), move|stream|{
+                    spawn_task(handle_connection(current::<Self>(), stream, ConnectionGuard::new()));
+                })? );
+            },
+            _ => return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Both tls_cert and tls_key are needed to use TLS!"
+            ))
+        }
+
+        emit(ServerEvent::Start, EventPayload {
+            address: Some(self.socket().map(String::from).unwrap_or_else(||self.listen_addresses().join(", "))),
+            ..EventPayload::default()
+        });
 
         start_async_thread_pool(self.threads());
 
@@ -125,7 +371,15 @@ pub trait Server: 'static + Sized + Sync + Send + Clone {
     }
 
     fn address(&self) -> String {
-        format!("{}:{}", self.hostname(), self.port())
+        format!("{}:{}", Hostname::from(self.hostname()), self.port())
+    }
+
+    /// Configured addresses, or the hostname and port if there are none.
+    fn listen_addresses(&self) -> Vec<String> {
+        match self.addresses() {
+            [] => vec![self.address()],
+            list => list.to_vec()
+        }
     }
 }
 
@@ -0,0 +1,388 @@
+/// Server Options
+///
+/// Every option is optional, so options from the command line, config file and
+/// server attribute can be layered with `or`. The sections match the tables of
+/// the config file.
+///
+use util::log::{Level, Output};
+use crate::AccessLogFormat;
+
+#[derive(Clone, Default, Debug)]
+pub struct ServerOpts {
+    pub threads:Option<usize>,
+    /// Reads commands from stdin, turned off when running without a terminal.
+    pub console:Option<bool>,
+    pub listen:ListenOpts,
+    pub tls:TlsOpts,
+    pub limits:LimitOpts,
+    pub timeouts:TimeoutOpts,
+    pub log:LogOpts,
+    /// Directories on disk for the routers to serve files from.
+    pub static_dirs:Option<Vec<StaticDir>>
+}
+
+/// [listen]
+#[derive(Clone, Default, Debug)]
+pub struct ListenOpts {
+    pub hostname:Option<String>,
+    pub port:Option<u16>,
+    /// Every "host:port" to listen on, used instead of the hostname and port when set.
+    pub addresses:Option<Vec<String>>,
+    /// Path of a Unix domain socket to listen on instead of the hostname and port.
+    pub socket:Option<String>,
+    /// Number of connections served at once, zero for no limit.
+    pub max_connections:Option<usize>,
+    /// Seconds sent in Retry-After with a 503 to connections over the limit, new
+    /// connections are left waiting in the backlog instead when not set.
+    pub retry_after:Option<u64>
+}
+
+/// [tls], used when both are set.
+#[derive(Clone, Default, Debug)]
+pub struct TlsOpts {
+    /// Path to the PEM certificate chain.
+    pub cert:Option<String>,
+    /// Path to the PEM private key.
+    pub key:Option<String>
+}
+
+/// [limits]
+#[derive(Clone, Default, Debug)]
+pub struct LimitOpts {
+    /// Bytes allowed in the request line.
+    pub max_request_line:Option<usize>,
+    /// Bytes allowed in all of the request headers.
+    pub max_header_bytes:Option<usize>,
+    /// Number of headers allowed in a request.
+    pub max_headers:Option<usize>,
+    /// Bytes allowed in the request uri.
+    pub max_uri:Option<usize>,
+    /// Number of requests that can be sent on a single connection.
+    pub keep_alive_max:Option<usize>,
+    /// Requests each client can make per window, overriding the server's rate limiter.
+    pub rate_limit_max:Option<u32>,
+    /// Seconds the rate limit is counted over.
+    pub rate_limit_window:Option<u64>
+}
+
+/// [timeouts], all in seconds.
+#[derive(Clone, Default, Debug)]
+pub struct TimeoutOpts {
+    /// Time an idle connection is kept open waiting for the next request.
+    pub keep_alive:Option<u64>,
+    /// Time allowed to receive the request line and headers.
+    pub header:Option<u64>,
+    /// Time the request body can go without receiving any data.
+    pub body:Option<u64>,
+    /// Time allowed to send the response.
+    pub write:Option<u64>,
+    /// Time open connections are given to finish when shutting down.
+    pub shutdown:Option<u64>
+}
+
+/// [log]
+#[derive(Clone, Default, Debug)]
+pub struct LogOpts {
+    /// Most detailed messages that are logged.
+    pub level:Option<Level>,
+    /// Where messages are logged, stdout, stderr or a file path.
+    pub outputs:Option<Vec<Output>>,
+    /// Bytes a log file can grow to before it is rotated.
+    pub max_size:Option<u64>,
+    /// Seconds a log file is written to before it is rotated.
+    pub rotate_interval:Option<u64>,
+    /// Number of rotated log files kept.
+    pub keep:Option<usize>,
+    /// Where the access log is written, stdout, stderr or a file path.
+    pub access_log:Option<Output>,
+    /// Common, Combined or JSON lines.
+    pub access_log_format:Option<AccessLogFormat>
+}
+
+/// [[static]]
+#[derive(Clone, PartialEq, Debug)]
+pub struct StaticDir {
+    /// Path the files are under, such as "/assets".
+    pub path:String,
+    /// Directory on disk, such as "./public".
+    pub dir:String
+}
+
+impl ServerOpts {
+    pub fn new<H:ToString>(hostname:H, port:u16, threads:usize) -> Self {
+        Self {
+            threads: Some(threads),
+            listen: ListenOpts {
+                hostname: Some(hostname.to_string()),
+                port: Some(port),
+                ..ListenOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn hostname<Hostname:ToString>(value:Hostname) -> Self {
+        Self {
+            listen: ListenOpts {
+                hostname: Some(value.to_string()),
+                ..ListenOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn port(value:u16) -> Self {
+        Self {
+            listen: ListenOpts {
+                port: Some(value),
+                ..ListenOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn threads(value:usize) -> Self {
+        Self {
+            threads: Some(value),
+            ..Self::none()
+        }
+    }
+
+    pub fn keep_alive(timeout:u64, max:usize) -> Self {
+        Self {
+            timeouts: TimeoutOpts {
+                keep_alive: Some(timeout),
+                ..TimeoutOpts::default()
+            },
+            limits: LimitOpts {
+                keep_alive_max: Some(max),
+                ..LimitOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn tls<Path:ToString>(cert:Path, key:Path) -> Self {
+        Self {
+            tls: TlsOpts {
+                cert: Some(cert.to_string()),
+                key: Some(key.to_string())
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn socket<Path:ToString>(path:Path) -> Self {
+        Self {
+            listen: ListenOpts {
+                socket: Some(path.to_string()),
+                ..ListenOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn addresses<Address:ToString>(list:&[Address]) -> Self {
+        Self {
+            listen: ListenOpts {
+                addresses: Some(list.iter().map(|addr|addr.to_string()).collect()),
+                ..ListenOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn shutdown_timeout(value:u64) -> Self {
+        Self {
+            timeouts: TimeoutOpts {
+                shutdown: Some(value),
+                ..TimeoutOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn timeouts(header:u64, body:u64, write:u64) -> Self {
+        Self {
+            timeouts: TimeoutOpts {
+                header: Some(header),
+                body: Some(body),
+                write: Some(write),
+                ..TimeoutOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn limits(request_line:usize, header_bytes:usize, headers:usize, uri:usize) -> Self {
+        Self {
+            limits: LimitOpts {
+                max_request_line: Some(request_line),
+                max_header_bytes: Some(header_bytes),
+                max_headers: Some(headers),
+                max_uri: Some(uri),
+                ..LimitOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn max_connections(max:usize, retry_after:Option<u64>) -> Self {
+        Self {
+            listen: ListenOpts {
+                max_connections: Some(max),
+                retry_after,
+                ..ListenOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn rate_limit(max:u32, window:u64) -> Self {
+        Self {
+            limits: LimitOpts {
+                rate_limit_max: Some(max),
+                rate_limit_window: Some(window),
+                ..LimitOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn log(level:Level, outputs:&[Output]) -> Self {
+        Self {
+            log: LogOpts {
+                level: Some(level),
+                outputs: Some(outputs.to_vec()),
+                ..LogOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn log_rotation(max_size:Option<u64>, interval:Option<u64>, keep:usize) -> Self {
+        Self {
+            log: LogOpts {
+                max_size,
+                rotate_interval: interval,
+                keep: Some(keep),
+                ..LogOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn access_log(output:Output, format:AccessLogFormat) -> Self {
+        Self {
+            log: LogOpts {
+                access_log: Some(output),
+                access_log_format: Some(format),
+                ..LogOpts::default()
+            },
+            ..Self::none()
+        }
+    }
+
+    pub fn console(enabled:bool) -> Self {
+        Self {
+            console: Some(enabled),
+            ..Self::none()
+        }
+    }
+
+    pub fn static_dirs(list:&[StaticDir]) -> Self {
+        Self {
+            static_dirs: Some(list.to_vec()),
+            ..Self::none()
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Fills in the options not set with the ones from other.
+    pub fn or(self, other:Self) -> Self {
+        Self {
+            threads: self.threads.or(other.threads),
+            console: self.console.or(other.console),
+            listen: self.listen.or(other.listen),
+            tls: self.tls.or(other.tls),
+            limits: self.limits.or(other.limits),
+            timeouts: self.timeouts.or(other.timeouts),
+            log: self.log.or(other.log),
+            static_dirs: self.static_dirs.or(other.static_dirs)
+        }
+    }
+}
+
+impl ListenOpts {
+    pub fn or(self, other:Self) -> Self {
+        Self {
+            hostname: self.hostname.or(other.hostname),
+            port: self.port.or(other.port),
+            addresses: self.addresses.or(other.addresses),
+            socket: self.socket.or(other.socket),
+            max_connections: self.max_connections.or(other.max_connections),
+            retry_after: self.retry_after.or(other.retry_after)
+        }
+    }
+}
+
+impl TlsOpts {
+    pub fn or(self, other:Self) -> Self {
+        Self {
+            cert: self.cert.or(other.cert),
+            key: self.key.or(other.key)
+        }
+    }
+}
+
+impl LimitOpts {
+    pub fn or(self, other:Self) -> Self {
+        Self {
+            max_request_line: self.max_request_line.or(other.max_request_line),
+            max_header_bytes: self.max_header_bytes.or(other.max_header_bytes),
+            max_headers: self.max_headers.or(other.max_headers),
+            max_uri: self.max_uri.or(other.max_uri),
+            keep_alive_max: self.keep_alive_max.or(other.keep_alive_max),
+            rate_limit_max: self.rate_limit_max.or(other.rate_limit_max),
+            rate_limit_window: self.rate_limit_window.or(other.rate_limit_window)
+        }
+    }
+}
+
+impl TimeoutOpts {
+    pub fn or(self, other:Self) -> Self {
+        Self {
+            keep_alive: self.keep_alive.or(other.keep_alive),
+            header: self.header.or(other.header),
+            body: self.body.or(other.body),
+            write: self.write.or(other.write),
+            shutdown: self.shutdown.or(other.shutdown)
+        }
+    }
+}
+
+impl LogOpts {
+    pub fn or(self, other:Self) -> Self {
+        Self {
+            level: self.level.or(other.level),
+            outputs: self.outputs.or(other.outputs),
+            max_size: self.max_size.or(other.max_size),
+            rotate_interval: self.rotate_interval.or(other.rotate_interval),
+            keep: self.keep.or(other.keep),
+            access_log: self.access_log.or(other.access_log),
+            access_log_format: self.access_log_format.or(other.access_log_format)
+        }
+    }
+}
+
+impl StaticDir {
+    pub fn new<P:ToString, D:ToString>(path:P, dir:D) -> Self {
+        Self {
+            path: path.to_string(),
+            dir: dir.to_string()
+        }
+    }
+}
@@ -1,49 +1,168 @@
+use std::{
+    net::ToSocketAddrs,
+    time::Duration
+};
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
 use async_lib::{
-    io::{Result, ErrorKind},
-    net::{TcpListener, TcpStream},
+    io::{Error, Result, ErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream, wait_for_connection},
     executor::{
-        is_running, shut_down,
+        is_running,
         thread::ThreadProcess
     }
 };
+#[cfg(unix)]
+use async_lib::net::{UnixListener, UnixStream};
+use util::log::{error, info, warning};
+use crate::shutdown::{accept_max, active_connections, is_draining, wait_for_connection_slot};
+
+/// Longest the listener waits for a connection before checking if the server is draining.
+const ACCEPT_WAIT: Duration = Duration::from_millis(100);
+
+/// Binds the first address each one resolves to, like TcpListener::bind.
+/// 
+/// IPv6 sockets usually accept IPv4 connections as well, so they are made IPv6
+/// only when an IPv4 address uses the same port.
+fn bind_all(addresses:&[String]) -> Result<Vec<TcpListener>> {
+    let resolved = addresses.iter()
+        .map(|addr|addr.to_socket_addrs().map(|list|(addr, list.collect::<Vec<_>>())))
+        .collect::<Result<Vec<_>>>()?;
 
-pub fn read_stdin() {
-    let stdin = std::io::stdin();
-    let mut input = String::new();
+    let ipv4_ports: Vec<u16> = resolved.iter()
+        .flat_map(|(_, list)|list)
+        .filter(|addr|addr.is_ipv4())
+        .map(|addr|addr.port())
+        .collect();
 
-    println!("Enter \"quit\" to shutdown server!");
-    while is_running() {
-        stdin.read_line(&mut input).unwrap();
+    resolved.into_iter().map(|(name, list)|{
+        let mut error = Error::new(ErrorKind::InvalidInput, format!("Unable to resolve {}!", name));
 
-        match input.to_lowercase().trim() {
-            "quit" => {
-                shut_down();
-                break;
-            },
-            _ => println!("Unknown command \"{}\"", input)
+        for addr in list {
+            let result = match addr {
+                #[cfg(unix)]
+                SocketAddr::V6(v6) if ipv4_ports.contains(&v6.port()) => TcpListener::bind_v6_only(v6),
+                addr => TcpListener::bind(addr)
+            };
+
+            match result {
+                Ok(listener) => return Ok(listener),
+                Err(e) => error = e
+            }
         }
+
+        Err(error)
+    }).collect()
+}
+
+/// Stops accepting while max connections are open, leaving new ones in the
+/// backlog, zero for no limit.
+fn at_capacity() -> bool {
+    let max = accept_max();
+    if max > 0 && active_connections() >= max {
+        wait_for_connection_slot(max, ACCEPT_WAIT);
+        true
+    } else {
+        false
     }
 }
 
-pub fn tcp_listener(addr:String, callback:impl Fn(TcpStream) + Send + Sync + 'static) -> Result<impl ThreadProcess> {
-    let mut listener = TcpListener::bind(addr.clone())?;
+pub fn tcp_listener(addresses:Vec<String>, callback:impl Fn(TcpStream) + Send + Sync + 'static) -> Result<impl ThreadProcess> {
+    let mut listeners = bind_all(&addresses)?;
+
+    for listener in &mut listeners {
+        if listener.set_nonblocking(true).is_err() {
+            warning!("Failed to set nonblocking on TcpListener!");
+        }
+    }
+
+    Ok(move ||{
+        for (listener, addr) in listeners.iter().zip(&addresses) {
+            match listener.local_addr() {
+                Ok(local) => info!("Listening at {}", local),
+                Err(_) => info!("Listening at {}", addr)
+            }
+        }
+
+        #[cfg(unix)]
+        let fds: Vec<&dyn AsRawFd> = listeners.iter().map(|listener|listener as &dyn AsRawFd).collect();
+        #[cfg(not(unix))]
+        let fds: Vec<&TcpListener> = listeners.iter().collect();
+
+        while is_running() && !is_draining() {
+            if at_capacity() {
+                continue;
+            }
+
+            let mut accepted = false;
+
+            for listener in &listeners {
+                match listener.sync_accept() {
+                    Ok(conn) => {
+                        accepted = true;
+                        callback(conn.0);
+                    },
+                    Err(e) => if e.kind() != ErrorKind::WouldBlock {
+                        error!("Connection Error: {}", e);
+                    } 
+                }
+            }
+
+            if !accepted && let Err(e) = wait_for_connection(&fds, ACCEPT_WAIT) {
+                error!("Connection Error: {}", e);
+            }
+        }
+    })
+}
+
+/// Removes a socket file left behind by a server that is no longer running,
+/// a socket something is still listening on is left for bind to report.
+#[cfg(unix)]
+fn remove_stale_socket(path:&str) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => match std::os::unix::net::UnixStream::connect(path) {
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+            _ => Ok(())
+        },
+        _ => Ok(())
+    }
+}
+
+#[cfg(unix)]
+pub fn unix_listener(path:String, callback:impl Fn(UnixStream) + Send + Sync + 'static) -> Result<impl ThreadProcess> {
+    remove_stale_socket(&path)?;
+    let mut listener = UnixListener::bind(&path)?;
 
     if listener.set_nonblocking(true).is_err() {
-        println!("Failed to set nonblocking on TcpListener!");
+        warning!("Failed to set nonblocking on UnixListener!");
     }
 
     Ok(move ||{
-        println!("Listening at {}", addr);
+        info!("Listening at {}", path);
+
+        while is_running() && !is_draining() {
+            if at_capacity() {
+                continue;
+            }
 
-        while is_running() {
             match listener.sync_accept() {
                 Ok(conn) => {
                     callback(conn.0);
                 },
                 Err(e) => if e.kind() != ErrorKind::WouldBlock {
-                    println!("Connection Error: {}", e);
-                } 
+                    error!("Connection Error: {}", e);
+                } else if let Err(e) = wait_for_connection(&[&listener], ACCEPT_WAIT) {
+                    error!("Connection Error: {}", e);
+                }
             }
         }
+
+        //Unlike a port, the socket file stays behind after the listener is closed.
+        drop(listener);
+        if let Err(e) = std::fs::remove_file(&path) {
+            warning!("Unable to remove socket {}: {}", path, e);
+        }
     })
 }
\ No newline at end of file
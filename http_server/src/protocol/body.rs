@@ -0,0 +1,488 @@
+/// Request Message Body
+///
+/// RFC-7230 3.3.3
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.3
+///
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration
+};
+use async_lib::{
+    io::{AsyncRead, AsyncWrite, AsyncBufReader},
+    time::{Sleep, sleep}
+};
+use http_core::{
+    headers::Headers,
+    request::BodySource,
+    error::{HttpError, HttpErrorKind}
+};
+use super::{BuildError, Limits};
+
+const CRLF: &[u8] = b"\r\n";
+
+/// Body with a length set by the Content-Length header.
+pub struct FixedBody<S: AsyncRead> {
+    reader: *mut AsyncBufReader<S>,
+    remaining: usize
+}
+
+impl<S: AsyncRead> FixedBody<S> {
+    pub fn new(reader: *mut AsyncBufReader<S>, length:usize) -> Self {
+        Self {
+            reader,
+            remaining: length
+        }
+    }
+}
+
+impl<S: AsyncRead> BodySource for FixedBody<S> {
+    fn poll_next_chunk(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, HttpError>>> {
+        if self.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        //SAFETY: Reader is owned by the connection and outlives the request.
+        let reader = unsafe{ &mut *self.reader };
+
+        let buffer = match Pin::new(&mut *reader).poll_fill_buf(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(
+                BuildError::IoError(e).into()
+            ))),
+            Poll::Ready(Ok(buffer)) => buffer
+        };
+
+        if buffer.is_empty() {
+            return Poll::Ready(Some(Err(
+                BuildError::IoError(io::ErrorKind::UnexpectedEof.into()).into()
+            )));
+        }
+
+        let amt = buffer.len().min(self.remaining);
+        let chunk = Vec::from(&buffer[..amt]);
+        reader.consume(amt);
+        self.remaining -= amt;
+
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+/// Fails the body once no data has been received for the timeout, so a slow
+/// upload is fine as long as it keeps making progress.
+pub struct TimedBody {
+    inner: Pin<Box<dyn BodySource>>,
+    timeout: Duration,
+    deadline: Option<Sleep>
+}
+
+impl TimedBody {
+    pub fn new(inner:Pin<Box<dyn BodySource>>, timeout:Duration) -> Self {
+        Self {
+            inner, timeout,
+            deadline: None
+        }
+    }
+}
+
+impl BodySource for TimedBody {
+    fn poll_next_chunk(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, HttpError>>> {
+        let this = &mut *self;
+
+        match this.inner.as_mut().poll_next_chunk(cx) {
+            Poll::Pending => {
+                let timeout = this.timeout;
+                let deadline = this.deadline.get_or_insert_with(||sleep(timeout));
+
+                if Pin::new(deadline).poll(cx).is_ready() {
+                    Poll::Ready(Some(Err(HttpError::new(
+                        HttpErrorKind::RequestTimeout,
+                        "Timed out waiting for the request body!"
+                    ))))
+                } else {
+                    Poll::Pending
+                }
+            },
+            ready => {
+                this.deadline = None;
+                ready
+            }
+        }
+    }
+
+    fn take_trailers(mut self: Pin<&mut Self>) -> Option<Headers> {
+        self.inner.as_mut().take_trailers()
+    }
+
+    fn is_started(&self) -> bool {
+        self.inner.is_started()
+    }
+}
+
+/// Sends the interim 100 Continue response the first time the body is read,
+/// so the handler can reject the request before the client sends the body.
+/// 
+/// RFC-9110 10.1.1
+/// https://datatracker.ietf.org/doc/html/rfc9110#section-10.1.1
+pub struct ContinueBody<S: AsyncRead + AsyncWrite> {
+    inner: Pin<Box<dyn BodySource>>,
+    reader: *mut AsyncBufReader<S>,
+    written: usize,
+    started: bool
+}
+
+impl<S: AsyncRead + AsyncWrite> ContinueBody<S> {
+    const INTERIM: &'static [u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+    pub fn new(inner:Pin<Box<dyn BodySource>>, reader: *mut AsyncBufReader<S>) -> Self {
+        Self {
+            inner, reader,
+            written: 0,
+            started: false
+        }
+    }
+
+    fn poll_interim(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        //SAFETY: Reader is owned by the connection and outlives the request.
+        let reader = unsafe{ &mut *self.reader };
+
+        //The client stopped waiting and already started sending the body.
+        if self.written == 0 && !reader.buffer().is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+
+        let stream = reader.get_mut();
+        while self.written < Self::INTERIM.len() {
+            match Pin::new(&mut *stream).poll_write(cx, &Self::INTERIM[self.written..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => self.written += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        Pin::new(stream).poll_flush(cx)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> BodySource for ContinueBody<S> {
+    fn poll_next_chunk(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, HttpError>>> {
+        let this = &mut *self;
+
+        if !this.started {
+            match this.poll_interim(cx) {
+                Poll::Ready(Ok(())) => this.started = true,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(
+                    BuildError::IoError(e).into()
+                ))),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        this.inner.as_mut().poll_next_chunk(cx)
+    }
+
+    fn take_trailers(mut self: Pin<&mut Self>) -> Option<Headers> {
+        self.inner.as_mut().take_trailers()
+    }
+
+    fn is_started(&self) -> bool {
+        self.started
+    }
+}
+
+/// Reads the next line from the reader without the CRLF, a line that does not
+/// fit in the buffer is a malformed body so `too_long` gives the error to send.
+fn poll_line<S: AsyncRead>(reader:&mut AsyncBufReader<S>, cx: &mut Context<'_>, too_long:impl FnOnce(&[u8]) -> BuildError) -> Poll<Result<Vec<u8>, BuildError>> {
+    let mut index: usize = 0;
+
+    loop {
+        let buffer = reader.buffer();
+        if let Some(end) = buffer[index..].windows(CRLF.len()).position(|w|w == CRLF) {
+            let end = index + end;
+            let line = Vec::from(&buffer[..end]);
+            reader.consume(end + CRLF.len());
+            return Poll::Ready(Ok(line));
+        }
+
+        //Seperator may be split between reads.
+        index = buffer.len().saturating_sub(1);
+        if buffer.len() >= reader.capacity() {
+            return Poll::Ready(Err(too_long(buffer)));
+        }
+
+        match Pin::new(&mut *reader).poll_read_more(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(BuildError::IoError(e))),
+            Poll::Ready(Ok(0)) => return Poll::Ready(Err(BuildError::IoError(
+                io::ErrorKind::UnexpectedEof.into()
+            ))),
+            Poll::Ready(Ok(_)) => {}
+        }
+    }
+}
+
+/// Parses the size from a chunk size line, ignoring any chunk extensions.
+/// 
+/// chunk-size [ chunk-ext ] CRLF
+/// chunk-ext = *( ";" chunk-ext-name [ "=" chunk-ext-val ] )
+fn parse_chunk_size(line:&[u8]) -> Result<usize, BuildError> {
+    let line = String::from_utf8_lossy(line);
+    let size = match line.split_once(';') {
+        Some((size, _ext)) => size,
+        None => &line
+    }.trim();
+
+    if size.is_empty() || !size.bytes().all(|c|c.is_ascii_hexdigit()) {
+        return Err(BuildError::InvalidChunkSize(line.to_string()));
+    }
+
+    usize::from_str_radix(size, 16)
+        .map_err(|_|BuildError::InvalidChunkSize(line.to_string()))
+}
+
+enum ChunkState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done
+}
+
+/// Body sent using the chunked transfer coding.
+/// 
+/// RFC-7230 4.1
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-4.1
+pub struct ChunkedBody<S: AsyncRead> {
+    reader: *mut AsyncBufReader<S>,
+    state: ChunkState,
+    limits: Limits,
+    trailer_bytes: usize,
+    trailer_count: usize,
+    trailers: Option<Headers>
+}
+
+impl<S: AsyncRead> ChunkedBody<S> {
+    pub fn new(reader: *mut AsyncBufReader<S>, limits:Limits) -> Self {
+        Self {
+            reader, limits,
+            state: ChunkState::Size,
+            trailer_bytes: 0,
+            trailer_count: 0,
+            trailers: None
+        }
+    }
+
+    /// Trailers are held to the same limits as the request headers.
+    fn add_trailer(&mut self, line:&[u8]) -> Result<(), BuildError> {
+        self.trailer_bytes += line.len() + CRLF.len();
+        self.trailer_count += 1;
+        if self.trailer_bytes > self.limits.max_header_bytes {
+            return Err(BuildError::HeadersTooLarge(self.limits.max_header_bytes));
+        } else if self.trailer_count > self.limits.max_headers {
+            return Err(BuildError::TooManyHeaders(self.limits.max_headers));
+        }
+
+        //line = Header Name: Header Value
+        let line = String::from_utf8_lossy(line);
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) if !name.trim().is_empty() => (name, value),
+            _ => return Err(BuildError::InvalidTrailer(line.to_string()))
+        };
+
+        self.trailers.get_or_insert_with(Headers::new).set(
+            name.trim(),
+            value.trim()
+        );
+        Ok(())
+    }
+}
+
+impl<S: AsyncRead> BodySource for ChunkedBody<S> {
+    fn poll_next_chunk(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, HttpError>>> {
+        //SAFETY: Reader is owned by the connection and outlives the request.
+        let reader = unsafe{ &mut *self.reader };
+
+        loop {
+            match self.state {
+                ChunkState::Size => {
+                    let too_long = |line:&[u8]|BuildError::InvalidChunkSize(format!("{} byte line", line.len()));
+                    let line = match poll_line(reader, cx, too_long) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                        Poll::Ready(Ok(line)) => line
+                    };
+
+                    self.state = match parse_chunk_size(&line) {
+                        Ok(0) => ChunkState::Trailers,
+                        Ok(size) => ChunkState::Data(size),
+                        Err(e) => return Poll::Ready(Some(Err(e.into())))
+                    };
+                },
+                ChunkState::Data(remaining) => {
+                    let buffer = match Pin::new(&mut *reader).poll_fill_buf(cx) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(
+                            BuildError::IoError(e).into()
+                        ))),
+                        Poll::Ready(Ok(buffer)) => buffer
+                    };
+
+                    if buffer.is_empty() {
+                        return Poll::Ready(Some(Err(
+                            BuildError::IoError(io::ErrorKind::UnexpectedEof.into()).into()
+                        )));
+                    }
+
+                    let amt = buffer.len().min(remaining);
+                    let chunk = Vec::from(&buffer[..amt]);
+                    reader.consume(amt);
+
+                    self.state = if amt == remaining {
+                        ChunkState::DataEnd
+                    } else {
+                        ChunkState::Data(remaining - amt)
+                    };
+
+                    return Poll::Ready(Some(Ok(chunk)));
+                },
+                ChunkState::DataEnd => {
+                    match poll_line(reader, cx, |_|BuildError::MissingChunkEnd) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                        Poll::Ready(Ok(line)) => if !line.is_empty() {
+                            return Poll::Ready(Some(Err(BuildError::MissingChunkEnd.into())));
+                        }
+                    }
+
+                    self.state = ChunkState::Size;
+                },
+                ChunkState::Trailers => {
+                    let max = self.limits.max_header_bytes;
+                    let line = match poll_line(reader, cx, |_|BuildError::HeadersTooLarge(max)) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                        Poll::Ready(Ok(line)) => line
+                    };
+
+                    if line.is_empty() {
+                        self.state = ChunkState::Done;
+                        continue;
+                    }
+
+                    if let Err(e) = self.add_trailer(&line) {
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                },
+                ChunkState::Done => return Poll::Ready(None)
+            }
+        }
+    }
+
+    fn take_trailers(mut self: Pin<&mut Self>) -> Option<Headers> {
+        self.trailers.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_request_line: 64,
+        max_header_bytes: 64,
+        max_headers: 4,
+        max_uri: 64
+    };
+
+    /// Reads the whole body, returning the data or the first error.
+    fn read_chunked(input:&[u8]) -> (Result<Vec<u8>, HttpError>, Option<Headers>) {
+        let mut reader = AsyncBufReader::with_capacity(LIMITS.buffer_size(), input);
+        let mut body = Box::pin(ChunkedBody::new(&mut reader as *mut _, LIMITS));
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+        let mut data = Vec::new();
+
+        loop {
+            match body.as_mut().poll_next_chunk(&mut cx) {
+                Poll::Ready(Some(Ok(chunk))) => data.extend(chunk),
+                Poll::Ready(Some(Err(e))) => return (Err(e), None),
+                Poll::Ready(None) => break,
+                Poll::Pending => panic!("Slice reader should never be pending!")
+            }
+        }
+
+        (Ok(data), body.as_mut().take_trailers())
+    }
+
+    #[test]
+    fn chunk_size() {
+        assert_eq!(parse_chunk_size(b"0").ok(), Some(0));
+        assert_eq!(parse_chunk_size(b"1A").ok(), Some(26));
+        assert_eq!(parse_chunk_size(b"ff").ok(), Some(255));
+        assert_eq!(parse_chunk_size(b"1A;foo=bar").ok(), Some(26));
+        assert_eq!(parse_chunk_size(b"1A ; foo=\"bar\";baz").ok(), Some(26));
+    }
+
+    #[test]
+    fn invalid_chunk_size() {
+        assert!(parse_chunk_size(b"").is_err());
+        assert!(parse_chunk_size(b";foo=bar").is_err());
+        assert!(parse_chunk_size(b"xyz").is_err());
+        assert!(parse_chunk_size(b"-1").is_err());
+        assert!(parse_chunk_size(b"+1").is_err());
+        assert!(parse_chunk_size(b"1 2").is_err());
+        assert!(parse_chunk_size(b"10000000000000000").is_err());
+    }
+
+    #[test]
+    fn chunked_body() {
+        let (data, trailers) = read_chunked(b"5\r\nHello\r\n7;ext=1\r\n World!\r\n0\r\n\r\n");
+        assert_eq!(data.unwrap(), b"Hello World!");
+        assert!(trailers.is_none());
+    }
+
+    #[test]
+    fn missing_chunk_end() {
+        let (data, _) = read_chunked(b"5\r\nHelloXX\r\n0\r\n\r\n");
+        assert_eq!(data.unwrap_err().message, BuildError::MissingChunkEnd.to_string());
+    }
+
+    #[test]
+    fn chunked_trailers() {
+        let (data, trailers) = read_chunked(b"5\r\nHello\r\n0\r\nExpires: never\r\nX-Sum:  abc \r\n\r\n");
+        assert_eq!(data.unwrap(), b"Hello");
+
+        let trailers = trailers.unwrap();
+        assert_eq!(trailers.get("Expires").unwrap().ref_str().unwrap(), "never");
+        assert_eq!(trailers.get("X-Sum").unwrap().ref_str().unwrap(), "abc");
+    }
+
+    #[test]
+    fn invalid_trailers() {
+        let (data, _) = read_chunked(b"0\r\nno separator\r\n\r\n");
+        assert_eq!(data.unwrap_err().message, BuildError::InvalidTrailer("no separator".to_string()).to_string());
+
+        let (data, _) = read_chunked(b"0\r\nA: 1\r\nB: 2\r\nC: 3\r\nD: 4\r\nE: 5\r\n\r\n");
+        assert_eq!(data.unwrap_err().message, BuildError::TooManyHeaders(LIMITS.max_headers).to_string());
+
+        let (data, _) = read_chunked(b"0\r\nA: 0123456789012345678901234567890123456789\r\nB: 0123456789012345678901234567890123456789\r\n\r\n");
+        assert_eq!(data.unwrap_err().message, BuildError::HeadersTooLarge(LIMITS.max_header_bytes).to_string());
+    }
+
+    #[test]
+    fn chunk_line_too_long() {
+        let mut input = vec![b'0'; LIMITS.buffer_size() + 8];
+        input.extend(b"5\r\nHello\r\n0\r\n\r\n");
+        let (data, _) = read_chunked(&input);
+        let err = data.unwrap_err();
+        assert!(matches!(err.kind, HttpErrorKind::BadRequest), "{}", err.message);
+
+        let (data, _) = read_chunked(b"5\r\nHello0123456789012345678901234567890123456789012345678901234567890123456789\r\n0\r\n\r\n");
+        assert!(matches!(data.unwrap_err().kind, HttpErrorKind::BadRequest));
+
+        let (data, _) = read_chunked(b"0\r\nX-Long: 0123456789012345678901234567890123456789012345678901234567890123456789\r\n\r\n");
+        let err = data.unwrap_err();
+        assert!(matches!(err.kind, HttpErrorKind::RequestHeaderFieldsTooLarge), "{}", err.message);
+    }
+}
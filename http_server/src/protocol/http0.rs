@@ -1,6 +1,9 @@
 
 
-use async_lib::io::{ AsyncRead, AsyncWrite, Result };
+use async_lib::{
+    io::{ AsyncWrite, Result },
+    net::TcpStream
+};
 use http_core::{
     headers::Headers,
     method::Method,
@@ -11,6 +14,7 @@ use http_core::{
 };
 use super::{
     BuildError,
+    write_all,
     types::Uri
 };
 
@@ -19,8 +23,7 @@ use super::{
 /// 
 /// GET [PATH]
 /// 
-pub fn build_request<S>(port:u16, method: Method, path:Uri) -> std::result::Result<RequestBuilder<S>, BuildError> 
-    where S: AsyncRead {
+pub fn build_request(port:u16, method: Method, path:Uri) -> std::result::Result<RequestBuilder<TcpStream>, BuildError> {
     if method != Method::GET {
         return Err(BuildError::Http0GetMethodOnly)
     }
@@ -41,10 +44,21 @@ pub fn build_request<S>(port:u16, method: Method, path:Uri) -> std::result::Resu
 
 
 
-pub async fn write_response<S>(resp: Response, stream:&mut S) -> Result<()> where S: AsyncWrite {
+pub async fn write_response<S>(resp: Response, stream:&mut S) -> Result<u64> where S: AsyncWrite {
+    let mut sent = 0;
+
     for chunk in resp.body {
-        stream.write(chunk.value()).await?;
+        write_all(stream, chunk.value()).await?;
+        sent += chunk.value().len() as u64;
+    }
+
+    if let Some(mut body) = resp.stream {
+        while let Some(chunk) = std::future::poll_fn(|cx|body.as_mut().poll_next_chunk(cx)).await {
+            write_all(stream, chunk.value()).await?;
+            stream.flush().await?;
+            sent += chunk.value().len() as u64;
+        }
     }
     
-    Ok(())
+    Ok(sent)
 }
\ No newline at end of file
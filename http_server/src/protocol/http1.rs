@@ -1,22 +1,176 @@
 /// Following RFC-2616 Stanard:
 /// https://datatracker.ietf.org/doc/html/rfc2616
 /// 
-use async_lib::io::{AsyncRead, AsyncWrite, Result};
+use async_lib::{
+    io::{AsyncRead, AsyncWrite, AsyncBufReader, Result},
+    net::TcpStream
+};
 use http_core::{
     method::Method,
-    headers::Headers,
+    headers::{Headers, ExpectType},
     url::ToUrl,
     version::Version,
     response::Response,
-    request::RequestBuilder,
+    request::{RequestBuilder, BodySource},
 };
+use std::{pin::Pin, time::Duration};
 use super::{
     BuildError,
+    Limits,
+    write_all,
+    body::{FixedBody, ChunkedBody, TimedBody, ContinueBody},
     types::*
 };
 
+fn map_parse_error(e: ParseStreamError) -> BuildError {
+    match e {
+        ParseStreamError::ReadError(e) => BuildError::IoError(e),
+        parse_err => BuildError::ParseError(parse_err)
+    }
+}
+
+/// Finds where the body of the request ends.
+/// 
+/// RFC-7230 3.3.3
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.3
+fn build_body<S>(reader:*mut AsyncBufReader<S>, headers:&Headers, limits:&Limits) -> std::result::Result<Option<Pin<Box<dyn BodySource>>>, BuildError>
+    where S: AsyncRead + 'static {
+
+    if let Some(value) = headers.get("Transfer-Encoding") {
+        //RFC-9112 6.3, a request with both can be read two ways and is refused.
+        if headers.get("Content-Length").is_some() {
+            return Err(BuildError::ConflictingLength);
+        }
+
+        let value = value.ref_str().unwrap_or("").to_string();
+        let codings: Vec<String> = value.split(',')
+            .map(|str|str.trim().to_ascii_lowercase())
+            .filter(|str|!str.is_empty())
+            .collect();
+
+        return match codings.last().map(|str|str.as_str()) {
+            Some("chunked") => if codings.len() == 1 {
+                Ok(Some(Box::pin(ChunkedBody::new(reader, *limits))))
+            } else {
+                Err(BuildError::UnsupportedTransferEncoding(value))
+            },
+            _ => Err(BuildError::InvalidTransferEncoding(value))
+        };
+    }
+
+    match headers.get("Content-Length") {
+        Some(value) => {
+            let str = value.ref_str().unwrap_or("").trim();
+            //Content-Length = 1*DIGIT, parse would also take a sign.
+            if str.is_empty() || !str.bytes().all(|byte|byte.is_ascii_digit()) {
+                return Err(BuildError::InvalidContentLength(str.to_string()));
+            }
+
+            let length: usize = str.parse()
+                .map_err(|_|BuildError::InvalidContentLength(str.to_string()))?;
+
+            if length == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(Box::pin(FixedBody::new(reader, length))))
+            }
+        },
+        None => Ok(None)
+    }
+}
+
+/// Adds a request header, refusing a second Content-Length that does not match
+/// the first as there would be no telling which one marks the end of the body.
+/// 
+/// RFC-9112 6.3
+/// https://datatracker.ietf.org/doc/html/rfc9112#section-6.3
+fn add_header(headers:&mut Headers, name:&str, value:&str) -> std::result::Result<(), BuildError> {
+    if name.eq_ignore_ascii_case("Content-Length") && let Some(first) = headers.get(name)
+        && first.ref_str().unwrap_or("") != value {
+        return Err(BuildError::InvalidContentLength(format!("{}, {}", first.ref_str().unwrap_or(""), value)));
+    }
+
+    headers.set(name, value);
+    Ok(())
+}
+
+/// Checks if the client is waiting for a 100 Continue before sending the body.
+/// 
+/// RFC-9110 10.1.1
+/// https://datatracker.ietf.org/doc/html/rfc9110#section-10.1.1
+fn expect_continue(headers:&Headers, version:Version) -> std::result::Result<bool, BuildError> {
+    //Expect is ignored in http/1.0 requests.
+    if version.major != 1 || version.minor < 1 {
+        return Ok(false);
+    }
+
+    match headers.get("Expect") {
+        Some(value) => {
+            let str = value.ref_str().unwrap_or("");
+            match ExpectType::parse(str) {
+                ExpectType::Contune => Ok(true),
+                ExpectType::Extension(_, _) => Err(BuildError::ExpectationFailed(str.to_string()))
+            }
+        },
+        None => Ok(false)
+    }
+}
+
+/// Checks the Connection header to see if the connection should stay open.
+/// 
+/// RFC-7230 6.3
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-6.3
+pub fn keep_alive<S: AsyncRead>(req:&RequestBuilder<S>) -> bool {
+    let mut value = req.version.major == 1 && req.version.minor >= 1;
+
+    if let Some(header) = req.headers.get("Connection") && let Ok(str) = header.ref_str() {
+        for option in str.split(',') {
+            match option.trim().to_ascii_lowercase().as_str() {
+                "close" => return false,
+                "keep-alive" => value = true,
+                _ => {}
+            }
+        }
+    }
+
+    value
+}
 
+/// Decides if the connection stays open after the response, and sets the
+/// Connection header to tell the client.
+/// 
+/// `allowed` is false when the server is closing the connection itself, and
+/// `body_read` is false if the rest of the request body could not be skipped.
+/// 
+/// RFC-7230 6.3
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-6.3
+pub fn persist_connection<S: AsyncRead>(req:&RequestBuilder<S>, resp:&mut Response, allowed:bool, body_read:bool) -> bool {
+    let version = req.version;
+    let persist = allowed && body_read && keep_alive(req);
 
+    if let Some(value) = resp.headers.get("Connection") && let Ok(str) = value.ref_str()
+        && str.to_ascii_lowercase().contains("close") {
+        false
+    } else if !persist {
+        if version.major == 1 && version.minor >= 1 {
+            resp.headers.set("Connection", "close");
+        }
+        false
+    } else {
+        if version.minor == 0 {
+            resp.headers.set("Connection", "keep-alive");
+        }
+        true
+    }
+}
+
+/// Response to a request that could not be read, the connection is closed after
+/// it is sent as there is no telling where the next request starts.
+pub fn error_response(err:BuildError) -> Response {
+    let mut resp = Response::from_error(err.into());
+    resp.headers.set("Connection", "close");
+    resp
+}
 
 /// http/1.1 Request Format:
 /// 
@@ -25,21 +179,25 @@ use super::{
 /// %CRLF%
 /// [BODY]
 /// 
-pub async fn build_request<S>(stream:&mut S, hostname:&str, port:u16) -> std::result::Result<RequestBuilder<S>, BuildError>
-    where S: AsyncRead {
-
-    let mut parser = StreamParser::new(stream as *mut S);
-
-    let start_line = match parser.parse().await {
-        Ok(Some(line)) => line,
-        Ok(None) => return Err(BuildError::EmptyRequest),
-        Err(e) => match e {
-            ParseStreamError::ReadError(e) =>
-                return Err(BuildError::IoError(e)),
-            parse_err => {
-                return Err(BuildError::ParseError(parse_err));
-            }
-         }
+pub async fn build_request<S>(reader:&mut AsyncBufReader<S>, hostname:&str, port:u16, limits:&Limits, body_timeout:Duration) -> std::result::Result<RequestBuilder<TcpStream>, BuildError>
+    where S: AsyncRead + AsyncWrite + 'static {
+
+    let ptr = reader as *mut AsyncBufReader<S>;
+    let mut parser = StreamParser::new(reader);
+
+    //Ignore empty lines left before the request.
+    let start_line = loop {
+        let line = parser.parse(limits.max_request_line).await.map_err(|e|match e {
+            ParseStreamError::LineTooLong(_) => BuildError::RequestLineTooLong(limits.max_request_line),
+            e => map_parse_error(e)
+        })?;
+
+        match line {
+            Some(line) => if line.has_some() {
+                break line;
+            },
+            None => return Err(BuildError::EmptyRequest)
+        }
     };
 
     let mut it = start_line.split();
@@ -54,6 +212,9 @@ pub async fn build_request<S>(stream:&mut S, hostname:&str, port:u16) -> std::re
     };
 
     let uri = match it.next() {
+        Some(t) if t.len() > limits.max_uri => return Err(
+            BuildError::UriTooLong(limits.max_uri)
+        ),
         Some(t) => match Uri::parse(&t) {
             Ok(uri) => uri,
             Err(e) => return Err(
@@ -78,18 +239,45 @@ pub async fn build_request<S>(stream:&mut S, hostname:&str, port:u16) -> std::re
     };
 
     let mut headers = Headers::new();
-    while let Some(chunk) = parser.parse().await.map_err(|e|match e{
-        ParseStreamError::ReadError(e) => BuildError::IoError(e),
-        parse_err => BuildError::ParseError(parse_err)
-    })? && chunk.has_some() {
+    let mut header_bytes: usize = 0;
+    let mut header_count: usize = 0;
+
+    loop {
+        let remaining = limits.max_header_bytes.saturating_sub(header_bytes);
+        let chunk = match parser.parse(remaining).await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(ParseStreamError::LineTooLong(_)) => return Err(
+                BuildError::HeadersTooLarge(limits.max_header_bytes)
+            ),
+            Err(e) => return Err(map_parse_error(e))
+        };
+
+        if !chunk.has_some() {
+            break;
+        }
+
+        header_bytes += chunk.len() + 2;
+        header_count += 1;
+        if header_bytes > limits.max_header_bytes {
+            return Err(BuildError::HeadersTooLarge(limits.max_header_bytes));
+        } else if header_count > limits.max_headers {
+            return Err(BuildError::TooManyHeaders(limits.max_headers));
+        }
+
         //chunk = Header Name: Header Value
-        let mut line = chunk.as_str().split(':');
+        let (name, value) = chunk.as_str()
+            .split_once(':')
+            .unwrap_or((chunk.as_str(), ""));
+
+        add_header(&mut headers, name.trim(), value.trim())?;
+    }
 
-        headers.set(
-            line.next().unwrap().trim(),
-            line.next().unwrap_or("").trim()
-        );
+    let mut body = build_body(ptr, &headers, limits)?;
+    if expect_continue(&headers, version)? && let Some(inner) = body {
+        body = Some(Box::pin(ContinueBody::new(inner, ptr)));
     }
+    let body = body.map(|body|Box::pin(TimedBody::new(body, body_timeout)) as Pin<Box<dyn BodySource>>);
 
     Ok(
         RequestBuilder::new(
@@ -98,32 +286,350 @@ pub async fn build_request<S>(stream:&mut S, hostname:&str, port:u16) -> std::re
             method,
             headers,
             version,
-            Some(parser.take_reader() as *mut S)
+            body
         )
     )
 }
 
-pub async fn write_response<S>(resp:Response, ver:Version, stream:&mut S) -> Result<()> where S: AsyncWrite {
-    stream.write(format!(
+/// Status codes that never have a message body.
+/// 
+/// RFC-7230 3.3
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-3.3
+fn no_body(resp:&Response) -> bool {
+    let code = resp.status.code();
+    code < 200 || code == 204 || code == 304
+}
+
+/// Sets the headers that mark where the body of the response ends.
+/// 
+/// Buffered bodies get a Content-Length, while streamed bodies are sent
+/// chunked when the client supports it.  Returns false if the end of the
+/// body can only be marked by closing the connection.
+/// 
+/// RFC-7230 3.3.3
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.3
+pub fn frame_response(resp:&mut Response, ver:Version) -> bool {
+    if no_body(resp) {
+        if resp.status.code() != 304 {
+            resp.headers.remove("Content-Length");
+        }
+        resp.headers.remove("Transfer-Encoding");
+        return true;
+    }
+
+    if resp.stream.is_none() {
+        let length: usize = resp.body.iter().map(|chunk|chunk.value().len()).sum();
+        resp.headers.remove("Transfer-Encoding");
+        resp.headers.set("Content-Length", length.to_string().as_str());
+        true
+    } else if resp.headers.get("Content-Length").is_some() {
+        resp.headers.remove("Transfer-Encoding");
+        true
+    } else if ver.major == 1 && ver.minor >= 1 {
+        resp.headers.set("Transfer-Encoding", "chunked");
+        true
+    } else {
+        false
+    }
+}
+
+/// Writes the chunk using the chunked transfer coding.
+/// 
+/// RFC-7230 4.1
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-4.1
+async fn write_chunk<S>(stream:&mut S, chunk:&[u8]) -> Result<()> where S: AsyncWrite {
+    if chunk.is_empty() {
+        return Ok(());
+    }
+
+    write_all(stream, format!("{:X}\r\n", chunk.len()).as_bytes()).await?;
+    write_all(stream, chunk).await?;
+    write_all(stream, b"\r\n").await?;
+    Ok(())
+}
+
+/// Returns the number of bytes of the body that were sent.
+pub async fn write_response<S>(mut resp:Response, ver:Version, head:bool, stream:&mut S) -> Result<u64> where S: AsyncWrite {
+    frame_response(&mut resp, ver);
+    let chunked = resp.headers.get("Transfer-Encoding").is_some();
+
+    write_all(stream, format!(
         "{} {} {}\r\n",
         ver.to_string(),
         resp.status.code().to_string(),
         resp.status.as_str()
     ).as_bytes()).await?;
 
-    for (key, value) in resp.headers.into_iter() {
-         stream.write(&format!(
+    for (key, value) in &resp.headers {
+        write_all(stream, format!(
             "{}: {}\r\n",
             key.name(),
             value.ref_str().unwrap()
         ).as_bytes()).await?;
     }
 
-    stream.write(b"\r\n").await?;
+    write_all(stream, b"\r\n").await?;
+
+    //HEAD responses keep the headers the body would have had.
+    if head || no_body(&resp) {
+        return Ok(0);
+    }
 
+    let mut sent = 0;
     for chunk in resp.body {
-        stream.write(chunk.value()).await?;
+        if chunked {
+            write_chunk(stream, chunk.value()).await?;
+        } else {
+            write_all(stream, chunk.value()).await?;
+        }
+        sent += chunk.value().len() as u64;
     }
 
-    Ok(())
-}
\ No newline at end of file
+    if let Some(mut body) = resp.stream {
+        while let Some(chunk) = std::future::poll_fn(|cx|body.as_mut().poll_next_chunk(cx)).await {
+            if chunked {
+                write_chunk(stream, chunk.value()).await?;
+            } else {
+                write_all(stream, chunk.value()).await?;
+            }
+
+            //Streamed chunks are sent as soon as they are ready.
+            stream.flush().await?;
+            sent += chunk.value().len() as u64;
+        }
+    }
+
+    if chunked {
+        write_all(stream, b"0\r\n\r\n").await?;
+    }
+
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc, task::{Context, Poll, Waker}};
+    use http_core::{status::HttpStatus, url::Url};
+    use super::*;
+
+    /// Socket that returns one of the given reads at a time, keeping what is written to it.
+    struct MockStream {
+        reads: VecDeque<Vec<u8>>,
+        output: Output
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize>> {
+            let Some(read) = self.reads.front_mut() else {
+                return Poll::Ready(Ok(0));
+            };
+
+            let amt = buf.len().min(read.len());
+            buf[..amt].copy_from_slice(&read[..amt]);
+            read.drain(..amt);
+            if read.is_empty() {
+                self.reads.pop_front();
+            }
+            Poll::Ready(Ok(amt))
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+            self.output.borrow_mut().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Polls the future once, the mock stream never makes it wait.
+    fn ready<F: Future>(future:F) -> F::Output {
+        match std::pin::pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("Mock stream should never be pending!")
+        }
+    }
+
+    type Output = Rc<RefCell<Vec<u8>>>;
+    type Reader = Box<AsyncBufReader<MockStream>>;
+
+    /// Builds the request from the reads, returning the request, what was written back
+    /// and the reader the body reads from.
+    fn read_request(reads:&[&[u8]]) -> (std::result::Result<RequestBuilder<TcpStream>, BuildError>, Output, Reader) {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let stream = MockStream {
+            reads: reads.iter().map(|read|read.to_vec()).collect(),
+            output: output.clone()
+        };
+
+        let mut reader = Box::new(AsyncBufReader::with_capacity(LIMITS.buffer_size(), stream));
+        let result = ready(build_request(&mut reader, "localhost", 80, &LIMITS, Duration::from_secs(60)));
+        (result, output, reader)
+    }
+
+    fn request(version:Version, connection:Option<&str>) -> RequestBuilder<TcpStream> {
+        let mut headers = Headers::new();
+        if let Some(value) = connection {
+            headers.set("Connection", value);
+        }
+
+        RequestBuilder::new(Url::empty(80, "/"), Method::GET, headers, version, None)
+    }
+
+    fn connection(resp:&Response) -> Option<&str> {
+        resp.headers.get("Connection").map(|value|value.ref_str().unwrap())
+    }
+
+    const HTTP_1_0: Version = Version{major: 1, minor: 0};
+    const HTTP_1_1: Version = Version{major: 1, minor: 1};
+    const LIMITS: Limits = Limits{max_request_line: 64, max_header_bytes: 256, max_headers: 8, max_uri: 64};
+
+    /// Returns if the headers frame a body.
+    fn framing(list:&[(&str, &str)]) -> std::result::Result<bool, BuildError> {
+        let mut headers = Headers::new();
+        for (name, value) in list {
+            add_header(&mut headers, name, value)?;
+        }
+
+        let mut reader = AsyncBufReader::with_capacity(LIMITS.buffer_size(), &b""[..]);
+        build_body(&mut reader as *mut _, &headers, &LIMITS).map(|body|body.is_some())
+    }
+
+    #[test]
+    fn persist_http_1_1() {
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(persist_connection(&request(HTTP_1_1, None), &mut resp, true, true));
+        assert_eq!(connection(&resp), None);
+
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_1, Some("Close")), &mut resp, true, true));
+        assert_eq!(connection(&resp), Some("close"));
+
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_1, Some("keep-alive, close")), &mut resp, true, true));
+        assert_eq!(connection(&resp), Some("close"));
+    }
+
+    #[test]
+    fn persist_http_1_0() {
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_0, None), &mut resp, true, true));
+        assert_eq!(connection(&resp), None);
+
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(persist_connection(&request(HTTP_1_0, Some("Keep-Alive")), &mut resp, true, true));
+        assert_eq!(connection(&resp), Some("keep-alive"));
+
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_0, Some("keep-alive")), &mut resp, false, true));
+        assert_eq!(connection(&resp), None);
+    }
+
+    #[test]
+    fn close_from_server() {
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        resp.headers.set("Connection", "close");
+        assert!(!persist_connection(&request(HTTP_1_1, None), &mut resp, true, true));
+        assert_eq!(connection(&resp), Some("close"));
+
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_1, None), &mut resp, false, true));
+        assert_eq!(connection(&resp), Some("close"));
+    }
+
+    #[test]
+    fn body_not_read() {
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_1, None), &mut resp, true, false));
+        assert_eq!(connection(&resp), Some("close"));
+
+        let mut resp = Response::new(HttpStatus::Ok, None);
+        assert!(!persist_connection(&request(HTTP_1_0, Some("keep-alive")), &mut resp, true, false));
+        assert_eq!(connection(&resp), None);
+    }
+
+    #[test]
+    fn header_timeout() {
+        let resp = error_response(BuildError::HeaderTimeout);
+        assert_eq!(resp.status.code(), 408);
+        assert_eq!(connection(&resp), Some("close"));
+
+        let resp = error_response(BuildError::TooManyHeaders(100));
+        assert_eq!(resp.status.code(), 431);
+        assert_eq!(connection(&resp), Some("close"));
+    }
+
+    #[test]
+    fn content_length() {
+        assert!(matches!(framing(&[("Content-Length", "5")]), Ok(true)));
+        assert!(matches!(framing(&[("Content-Length", "0")]), Ok(false)));
+        assert!(matches!(framing(&[("Content-Length", "5"), ("content-length", "5")]), Ok(true)));
+        assert!(matches!(framing(&[]), Ok(false)));
+    }
+
+    #[test]
+    fn invalid_content_length() {
+        for value in ["+5", "-5", " ", "5, 5", "0x5"] {
+            assert!(matches!(framing(&[("Content-Length", value)]), Err(BuildError::InvalidContentLength(_))), "{}", value);
+        }
+
+        assert!(matches!(
+            framing(&[("Content-Length", "5"), ("Content-Length", "6")]),
+            Err(BuildError::InvalidContentLength(_))
+        ));
+    }
+
+    #[test]
+    fn transfer_encoding_with_content_length() {
+        assert!(matches!(framing(&[("Transfer-Encoding", "chunked")]), Ok(true)));
+        assert!(matches!(
+            framing(&[("Transfer-Encoding", "chunked"), ("Content-Length", "5")]),
+            Err(BuildError::ConflictingLength)
+        ));
+
+        let resp = error_response(BuildError::ConflictingLength);
+        assert_eq!(resp.status.code(), 400);
+        assert_eq!(connection(&resp), Some("close"));
+    }
+
+    #[test]
+    fn continue_sent_on_read() {
+        let head: &[u8] = b"POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-Continue\r\nContent-Length: 5\r\n\r\n";
+        let (req, output, _reader) = read_request(&[head, b"Hello"]);
+        let mut req = req.ok().unwrap();
+        assert!(output.borrow().is_empty());
+
+        let body = ready(req.body()).ok().unwrap().unwrap().to_vec();
+        assert_eq!(body, b"Hello");
+        assert_eq!(output.borrow().as_slice(), b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn continue_skipped_once_body_sent() {
+        let (req, output, _reader) = read_request(&[b"POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\nHello"]);
+        let mut req = req.ok().unwrap();
+
+        let body = ready(req.body()).ok().unwrap().unwrap().to_vec();
+        assert_eq!(body, b"Hello");
+        assert!(output.borrow().is_empty());
+    }
+
+    #[test]
+    fn expectation_failed() {
+        let (req, output, _reader) = read_request(&[b"POST / HTTP/1.1\r\nExpect: 200-ok\r\nContent-Length: 5\r\n\r\n"]);
+        let Err(err) = req else {
+            panic!("Unknown expectation should be refused!");
+        };
+
+        assert!(matches!(err, BuildError::ExpectationFailed(_)));
+        assert_eq!(error_response(err).status.code(), 417);
+        assert!(output.borrow().is_empty());
+    }
+}
@@ -1 +0,0 @@
-/// https://datatracker.ietf.org/doc/html/rfc9113#name-starting-http-2-for-https-u
\ No newline at end of file
@@ -0,0 +1,234 @@
+/// HTTP/2 Frames
+///
+/// RFC-9113 4
+/// https://datatracker.ietf.org/doc/html/rfc9113#name-http-frames
+///
+use std::fmt;
+
+pub const HEADER_SIZE: usize = 9;
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16_384;
+pub const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    Unknown(u8)
+}
+
+impl From<u8> for FrameType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x0 => Self::Data,
+            0x1 => Self::Headers,
+            0x2 => Self::Priority,
+            0x3 => Self::RstStream,
+            0x4 => Self::Settings,
+            0x5 => Self::PushPromise,
+            0x6 => Self::Ping,
+            0x7 => Self::GoAway,
+            0x8 => Self::WindowUpdate,
+            0x9 => Self::Continuation,
+            other => Self::Unknown(other)
+        }
+    }
+}
+
+impl From<FrameType> for u8 {
+    fn from(value:FrameType) -> u8 {
+        match value {
+            FrameType::Data => 0x0,
+            FrameType::Headers => 0x1,
+            FrameType::Priority => 0x2,
+            FrameType::RstStream => 0x3,
+            FrameType::Settings => 0x4,
+            FrameType::PushPromise => 0x5,
+            FrameType::Ping => 0x6,
+            FrameType::GoAway => 0x7,
+            FrameType::WindowUpdate => 0x8,
+            FrameType::Continuation => 0x9,
+            FrameType::Unknown(value) => value
+        }
+    }
+}
+
+pub mod flags {
+    pub const END_STREAM: u8 = 0x1;
+    pub const ACK: u8 = 0x1;
+    pub const END_HEADERS: u8 = 0x4;
+    pub const PADDED: u8 = 0x8;
+    pub const PRIORITY: u8 = 0x20;
+}
+
+/// RFC-9113 7
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoError,
+    ProtocolError,
+    InternalError,
+    FlowControlError,
+    SettingsTimeout,
+    StreamClosed,
+    FrameSizeError,
+    RefusedStream,
+    Cancel,
+    CompressionError,
+    ConnectError,
+    EnhanceYourCalm,
+    InadequateSecurity,
+    Http11Required
+}
+
+impl ErrorCode {
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::NoError => 0x0,
+            Self::ProtocolError => 0x1,
+            Self::InternalError => 0x2,
+            Self::FlowControlError => 0x3,
+            Self::SettingsTimeout => 0x4,
+            Self::StreamClosed => 0x5,
+            Self::FrameSizeError => 0x6,
+            Self::RefusedStream => 0x7,
+            Self::Cancel => 0x8,
+            Self::CompressionError => 0x9,
+            Self::ConnectError => 0xa,
+            Self::EnhanceYourCalm => 0xb,
+            Self::InadequateSecurity => 0xc,
+            Self::Http11Required => 0xd
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// RFC-9113 6.5.2
+#[allow(dead_code)]
+pub mod settings {
+    pub const HEADER_TABLE_SIZE: u16 = 0x1;
+    pub const ENABLE_PUSH: u16 = 0x2;
+    pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+    pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+    pub const MAX_FRAME_SIZE: u16 = 0x5;
+    pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+}
+
+/// Frame Format
+///
+/// +-----------------------------------------------+
+/// |                 Length (24)                   |
+/// +---------------+---------------+---------------+
+/// |   Type (8)    |   Flags (8)   |
+/// +-+-------------+---------------+-------------------------------+
+/// |R|                 Stream Identifier (31)                      |
+/// +=+=============================================================+
+/// |                   Frame Payload (0...)                      ...
+/// +---------------------------------------------------------------+
+pub struct Frame {
+    pub kind: FrameType,
+    pub flags: u8,
+    pub stream: u32,
+    pub payload: Vec<u8>
+}
+
+impl Frame {
+    pub fn new(kind:FrameType, flags:u8, stream:u32, payload:Vec<u8>) -> Self {
+        Self { kind, flags, stream, payload }
+    }
+
+    /// Parses the 9 byte frame header, returning the frame with an empty payload
+    /// and the length of the payload.
+    pub fn parse_header(buffer:&[u8; HEADER_SIZE]) -> (Self, usize) {
+        let length = u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]) as usize;
+        let stream = u32::from_be_bytes([buffer[5], buffer[6], buffer[7], buffer[8]]) & 0x7FFF_FFFF;
+
+        (
+            Self::new(buffer[3].into(), buffer[4], stream, Vec::with_capacity(length)),
+            length
+        )
+    }
+
+    pub fn has_flag(&self, flag:u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Removes the padding from DATA and HEADERS frames.
+    ///
+    /// RFC-9113 6.1
+    pub fn unpad(&mut self) -> Result<(), ErrorCode> {
+        if !self.has_flag(flags::PADDED) {
+            return Ok(());
+        }
+
+        let pad = *self.payload.first().ok_or(ErrorCode::FrameSizeError)? as usize;
+        if pad + 1 > self.payload.len() {
+            return Err(ErrorCode::ProtocolError);
+        }
+
+        let end = self.payload.len() - pad;
+        self.payload.truncate(end);
+        self.payload.remove(0);
+        Ok(())
+    }
+
+    pub fn write_to(&self, output:&mut Vec<u8>) {
+        let length = (self.payload.len() as u32).to_be_bytes();
+
+        output.extend_from_slice(&length[1..]);
+        output.push(self.kind.into());
+        output.push(self.flags);
+        output.extend_from_slice(&(self.stream & 0x7FFF_FFFF).to_be_bytes());
+        output.extend_from_slice(&self.payload);
+    }
+
+    pub fn settings(values:&[(u16, u32)]) -> Self {
+        let mut payload = Vec::with_capacity(values.len() * 6);
+        for (id, value) in values {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+
+        Self::new(FrameType::Settings, 0, 0, payload)
+    }
+
+    pub fn window_update(stream:u32, increment:u32) -> Self {
+        Self::new(FrameType::WindowUpdate, 0, stream, increment.to_be_bytes().to_vec())
+    }
+
+    pub fn rst_stream(stream:u32, error:ErrorCode) -> Self {
+        Self::new(FrameType::RstStream, 0, stream, error.code().to_be_bytes().to_vec())
+    }
+
+    pub fn go_away(last_stream:u32, error:ErrorCode) -> Self {
+        let mut payload = Vec::with_capacity(8);
+        payload.extend_from_slice(&(last_stream & 0x7FFF_FFFF).to_be_bytes());
+        payload.extend_from_slice(&error.code().to_be_bytes());
+
+        Self::new(FrameType::GoAway, 0, 0, payload)
+    }
+
+    /// Reads the settings from the payload of a SETTINGS frame.
+    pub fn parse_settings(payload:&[u8]) -> Result<Vec<(u16, u32)>, ErrorCode> {
+        if !payload.len().is_multiple_of(6) {
+            return Err(ErrorCode::FrameSizeError);
+        }
+
+        Ok(payload.chunks(6).map(|chunk|(
+            u16::from_be_bytes([chunk[0], chunk[1]]),
+            u32::from_be_bytes([chunk[2], chunk[3], chunk[4], chunk[5]])
+        )).collect())
+    }
+}
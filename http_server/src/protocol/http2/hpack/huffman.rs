@@ -0,0 +1,102 @@
+/// Huffman Code
+/// 
+/// RFC-7541 Appendix B
+/// https://datatracker.ietf.org/doc/html/rfc7541#appendix-B
+/// 
+/// The code is canonical, so only the length of each symbol's code is needed
+/// to rebuild it.  Symbol 256 is EOS.
+use std::sync::LazyLock;
+use super::HpackError;
+
+const EOS: usize = 256;
+const MAX_LENGTH: usize = 30;
+
+const CODE_LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28,
+    28, 28, 28, 28, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6,
+    5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10,
+    13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6,
+    15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6, 6, 5,
+    6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28,
+    20, 22, 20, 20, 22, 22, 22, 23, 22, 23, 23, 23, 23, 23, 24, 23,
+    24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24,
+    22, 21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23,
+    21, 21, 22, 21, 23, 22, 23, 23, 20, 22, 22, 22, 23, 22, 22, 23,
+    26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25,
+    19, 21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27,
+    20, 24, 20, 21, 22, 21, 21, 23, 22, 22, 25, 25, 24, 24, 26, 23,
+    26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+    30,
+];
+
+/// Symbols sorted by code length, along with the first code and the
+/// offset into the symbols for each length.
+struct CanonicalTable {
+    symbols: Vec<u16>,
+    first_code: [u32; MAX_LENGTH+1],
+    count: [u32; MAX_LENGTH+1],
+    offset: [usize; MAX_LENGTH+1]
+}
+
+static TABLE: LazyLock<CanonicalTable> = LazyLock::new(||{
+    let mut symbols: Vec<u16> = (0..=EOS as u16).collect();
+    symbols.sort_by_key(|s|(CODE_LENGTHS[*s as usize], *s));
+
+    let mut first_code = [0; MAX_LENGTH+1];
+    let mut count = [0; MAX_LENGTH+1];
+    let mut offset = [0; MAX_LENGTH+1];
+
+    for length in CODE_LENGTHS {
+        count[length as usize] += 1;
+    }
+
+    let mut code: u32 = 0;
+    let mut index: usize = 0;
+    for length in 1..=MAX_LENGTH {
+        first_code[length] = code;
+        offset[length] = index;
+        code = (code + count[length]) << 1;
+        index += count[length] as usize;
+    }
+
+    CanonicalTable { symbols, first_code, count, offset }
+});
+
+pub fn decode(input:&[u8]) -> Result<Vec<u8>, HpackError> {
+    let table = &*TABLE;
+    let mut output = Vec::with_capacity(input.len() * 8 / 5);
+    let mut code: u32 = 0;
+    let mut length: usize = 0;
+
+    for byte in input {
+        for shift in (0..8).rev() {
+            code = (code << 1) | ((*byte >> shift) & 1) as u32;
+            length += 1;
+
+            if length > MAX_LENGTH {
+                return Err(HpackError::InvalidHuffman);
+            }
+
+            let index = code.wrapping_sub(table.first_code[length]);
+            if code >= table.first_code[length] && index < table.count[length] {
+                let symbol = table.symbols[table.offset[length] + index as usize] as usize;
+                if symbol == EOS {
+                    return Err(HpackError::InvalidHuffman);
+                }
+
+                output.push(symbol as u8);
+                code = 0;
+                length = 0;
+            }
+        }
+    }
+
+    //Padding must be the most significant bits of EOS and shorter then a byte.
+    if length >= 8 || code != (1 << length) - 1 {
+        return Err(HpackError::InvalidHuffman);
+    }
+
+    Ok(output)
+}
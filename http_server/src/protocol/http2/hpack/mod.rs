@@ -0,0 +1,440 @@
+/// HPACK: Header Compression for HTTP/2
+///
+/// RFC-7541
+/// https://datatracker.ietf.org/doc/html/rfc7541
+///
+use std::{collections::VecDeque, fmt};
+
+mod huffman;
+
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// Size added to every entry in the dynamic table.
+///
+/// RFC-7541 4.1
+const ENTRY_OVERHEAD: usize = 32;
+
+/// RFC-7541 Appendix A
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum HpackError {
+    UnexpectedEnd,
+    IntegerOverflow,
+    InvalidIndex(usize),
+    InvalidHuffman,
+    InvalidTableSize(usize),
+    LateTableSizeUpdate,
+    HeaderListTooLarge(usize)
+}
+
+impl fmt::Display for HpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "Header block ended unexpectedly!"),
+            Self::IntegerOverflow => write!(f, "Integer is too large!"),
+            Self::InvalidIndex(index) => write!(f, "{index} is not a valid table index!"),
+            Self::InvalidHuffman => write!(f, "Invalid huffman encoded string!"),
+            Self::InvalidTableSize(size) => write!(f, "Table size {size} is larger then allowed!"),
+            Self::LateTableSizeUpdate => write!(f, "Table size update must be at the start of a header block!"),
+            Self::HeaderListTooLarge(max) => write!(f, "Header list is larger then {max} bytes!")
+        }
+    }
+}
+
+/// RFC-7541 5.1
+fn decode_integer(buffer:&[u8], index:&mut usize, prefix:u8) -> Result<usize, HpackError> {
+    let max = (1usize << prefix) - 1;
+    let mut value = (*buffer.get(*index).ok_or(HpackError::UnexpectedEnd)? as usize) & max;
+    *index += 1;
+
+    if value < max {
+        return Ok(value);
+    }
+
+    let mut shift = 0;
+    loop {
+        let byte = *buffer.get(*index).ok_or(HpackError::UnexpectedEnd)?;
+        *index += 1;
+
+        if shift > 28 {
+            return Err(HpackError::IntegerOverflow);
+        }
+
+        value += ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// RFC-7541 5.1
+fn encode_integer(output:&mut Vec<u8>, mut value:usize, prefix:u8, flags:u8) {
+    let max = (1usize << prefix) - 1;
+
+    if value < max {
+        output.push(flags | value as u8);
+        return;
+    }
+
+    output.push(flags | max as u8);
+    value -= max;
+    while value >= 128 {
+        output.push((value % 128 + 128) as u8);
+        value /= 128;
+    }
+    output.push(value as u8);
+}
+
+/// RFC-7541 5.2
+fn decode_string(buffer:&[u8], index:&mut usize) -> Result<Vec<u8>, HpackError> {
+    let huffman = *buffer.get(*index).ok_or(HpackError::UnexpectedEnd)? & 0x80 != 0;
+    let length = decode_integer(buffer, index, 7)?;
+
+    let end = index.checked_add(length)
+        .filter(|end|*end <= buffer.len())
+        .ok_or(HpackError::UnexpectedEnd)?;
+    let value = &buffer[*index..end];
+    *index = end;
+
+    if huffman {
+        huffman::decode(value)
+    } else {
+        Ok(value.to_vec())
+    }
+}
+
+/// RFC-7541 5.2
+fn encode_string(output:&mut Vec<u8>, value:&[u8]) {
+    encode_integer(output, value.len(), 7, 0);
+    output.extend_from_slice(value);
+}
+
+/// RFC-7541 2.3.2
+struct DynamicTable {
+    entries: VecDeque<(Vec<u8>, Vec<u8>)>,
+    size: usize,
+    max_size: usize
+}
+
+impl DynamicTable {
+    fn new(max_size:usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size && let Some((name, value)) = self.entries.pop_back() {
+            self.size -= name.len() + value.len() + ENTRY_OVERHEAD;
+        }
+    }
+
+    fn set_max_size(&mut self, size:usize) {
+        self.max_size = size;
+        self.evict();
+    }
+
+    fn insert(&mut self, name:Vec<u8>, value:Vec<u8>) {
+        self.size += name.len() + value.len() + ENTRY_OVERHEAD;
+        self.entries.push_front((name, value));
+        self.evict();
+    }
+}
+
+/// Decoded name and value pairs, in the order they were sent.
+pub type HeaderList = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Decodes header blocks sent by the client.
+///
+/// The dynamic table is shared by every header block on the connection.
+pub struct Decoder {
+    table: DynamicTable,
+    max_allowed: usize,
+    max_list_size: usize
+}
+
+impl Decoder {
+    /// `max_list_size` is the SETTINGS_MAX_HEADER_LIST_SIZE sent to the client.
+    pub fn new(max_list_size:usize) -> Self {
+        Self {
+            table: DynamicTable::new(DEFAULT_TABLE_SIZE),
+            max_allowed: DEFAULT_TABLE_SIZE,
+            max_list_size
+        }
+    }
+
+    /// Fields past the max list size are dropped, but the rest of the block is
+    /// still decoded so the dynamic table stays in sync.
+    ///
+    /// RFC-9113 6.5.2
+    fn push(&self, headers:&mut HeaderList, list_size:&mut usize, (name, value):(Vec<u8>, Vec<u8>)) {
+        *list_size += name.len() + value.len() + ENTRY_OVERHEAD;
+        if *list_size <= self.max_list_size {
+            headers.push((name, value));
+        }
+    }
+
+    fn get(&self, index:usize) -> Result<(Vec<u8>, Vec<u8>), HpackError> {
+        if index == 0 {
+            Err(HpackError::InvalidIndex(index))
+        } else if index <= STATIC_TABLE.len() {
+            let (name, value) = STATIC_TABLE[index-1];
+            Ok((name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+        } else {
+            self.table.entries.get(index - STATIC_TABLE.len() - 1)
+                .cloned()
+                .ok_or(HpackError::InvalidIndex(index))
+        }
+    }
+
+    /// Fails with HeaderListTooLarge once the decoded list passes the max list size,
+    /// even if the block itself is small.
+    ///
+    /// RFC-7541 6
+    pub fn decode(&mut self, buffer:&[u8]) -> Result<HeaderList, HpackError> {
+        let mut headers = Vec::new();
+        let mut list_size: usize = 0;
+        let mut index: usize = 0;
+
+        while index < buffer.len() {
+            let byte = buffer[index];
+
+            //Indexed Header Field
+            if byte & 0x80 != 0 {
+                let i = decode_integer(buffer, &mut index, 7)?;
+                let field = self.get(i)?;
+                self.push(&mut headers, &mut list_size, field);
+
+            //Literal Header Field with Incremental Indexing
+            } else if byte & 0x40 != 0 {
+                let (name, value) = self.decode_literal(buffer, &mut index, 6)?;
+                self.table.insert(name.clone(), value.clone());
+                self.push(&mut headers, &mut list_size, (name, value));
+
+            //Dynamic Table Size Update
+            } else if byte & 0x20 != 0 {
+                if list_size > 0 {
+                    return Err(HpackError::LateTableSizeUpdate);
+                }
+
+                let size = decode_integer(buffer, &mut index, 5)?;
+                if size > self.max_allowed {
+                    return Err(HpackError::InvalidTableSize(size));
+                }
+                self.table.set_max_size(size);
+
+            //Literal Header Field without Indexing / Never Indexed
+            } else {
+                let field = self.decode_literal(buffer, &mut index, 4)?;
+                self.push(&mut headers, &mut list_size, field);
+            }
+        }
+
+        if list_size > self.max_list_size {
+            return Err(HpackError::HeaderListTooLarge(self.max_list_size));
+        }
+
+        Ok(headers)
+    }
+
+    fn decode_literal(&self, buffer:&[u8], index:&mut usize, prefix:u8) -> Result<(Vec<u8>, Vec<u8>), HpackError> {
+        let name = match decode_integer(buffer, index, prefix)? {
+            0 => decode_string(buffer, index)?,
+            i => self.get(i)?.0
+        };
+
+        Ok((name, decode_string(buffer, index)?))
+    }
+}
+
+/// Encodes header blocks sent to the client.
+///
+/// Headers are never added to the dynamic table, so the client's table
+/// size does not need to be tracked.
+pub struct Encoder;
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn encode<'a>(&self, headers: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        for (name, value) in headers {
+            match STATIC_TABLE.iter().position(|entry|*entry == (name, value)) {
+                Some(i) => encode_integer(&mut output, i+1, 7, 0x80),
+                None => {
+                    //Literal Header Field without Indexing
+                    match STATIC_TABLE.iter().position(|entry|entry.0 == name) {
+                        Some(i) => encode_integer(&mut output, i+1, 4, 0),
+                        None => {
+                            output.push(0);
+                            encode_string(&mut output, name.as_bytes());
+                        }
+                    }
+                    encode_string(&mut output, value.as_bytes());
+                }
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT_LIST_SIZE: usize = 16_384;
+
+    fn hex(value:&str) -> Vec<u8> {
+        let value: String = value.split_whitespace().collect();
+        (0..value.len()).step_by(2)
+            .map(|i|u8::from_str_radix(&value[i..i+2], 16).unwrap())
+            .collect()
+    }
+
+    fn headers(list:&[(&str, &str)]) -> HeaderList {
+        list.iter().map(|(n, v)|(n.as_bytes().to_vec(), v.as_bytes().to_vec())).collect()
+    }
+
+    #[test]
+    fn integer() {
+        let mut output = Vec::new();
+        encode_integer(&mut output, 1337, 5, 0);
+        assert_eq!(output, vec![31, 154, 10]);
+
+        let mut index = 0;
+        assert_eq!(decode_integer(&output, &mut index, 5), Ok(1337));
+        assert_eq!(index, 3);
+    }
+
+    /// RFC-7541 C.4
+    #[test]
+    fn requests_with_huffman() {
+        let mut decoder = Decoder::new(DEFAULT_LIST_SIZE);
+
+        assert_eq!(decoder.decode(&hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff")), Ok(headers(&[
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com")
+        ])));
+
+        assert_eq!(decoder.decode(&hex("8286 84be 5886 a8eb 1064 9cbf")), Ok(headers(&[
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+            ("cache-control", "no-cache")
+        ])));
+
+        assert_eq!(decoder.decode(&hex("8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf")), Ok(headers(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/index.html"),
+            (":authority", "www.example.com"),
+            ("custom-key", "custom-value")
+        ])));
+        assert_eq!(decoder.table.size, 164);
+    }
+
+    #[test]
+    fn encode_decode() {
+        let list = [(":status", "200"), (":status", "418"), ("content-length", "12"), ("x-custom", "value")];
+        let encoded = Encoder::new().encode(list.iter().map(|(n, v)|(*n, *v)));
+
+        assert_eq!(Decoder::new(DEFAULT_LIST_SIZE).decode(&encoded), Ok(headers(&list)));
+    }
+
+    /// Indexed references to a large table entry make a small block expand into a large list.
+    #[test]
+    fn header_list_too_large() {
+        let value = "a".repeat(1000);
+        let mut block = Encoder::new().encode([("x-big", value.as_str())].into_iter());
+        //Literal with Incremental Indexing, so it can be referenced from the table.
+        block[0] = 0x40;
+
+        let mut decoder = Decoder::new(4096);
+        assert_eq!(decoder.decode(&block).map(|list|list.len()), Ok(1));
+
+        //Index 62 is the newest entry in the dynamic table.
+        assert_eq!(decoder.decode(&[0xBE; 3]).map(|list|list.len()), Ok(3));
+        assert_eq!(decoder.decode(&[0xBE; 4]), Err(HpackError::HeaderListTooLarge(4096)));
+
+        //The table is still in sync after the failed block.
+        let mut block = vec![0xBE; 5];
+        block.extend(Encoder::new().encode([("x-small", "1")].into_iter()));
+        block[5] = 0x40;
+        assert_eq!(decoder.decode(&block), Err(HpackError::HeaderListTooLarge(4096)));
+        assert_eq!(decoder.decode(&[0xBE]), Ok(headers(&[("x-small", "1")])));
+    }
+}
@@ -0,0 +1,1266 @@
+/// HTTP/2
+///
+/// RFC-9113
+/// https://datatracker.ietf.org/doc/html/rfc9113
+///
+/// Connections start either with the client preface (prior knowledge or "h2"
+/// negotiated with ALPN over TLS) or by upgrading an HTTP/1.1 request with
+/// `Upgrade: h2c`.
+/// https://datatracker.ietf.org/doc/html/rfc9113#name-starting-http-2-for-https-u
+///
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::{Duration, Instant}
+};
+use async_lib::{
+    io::{AsyncRead, AsyncBufReader},
+    net::TcpStream,
+    time::{Sleep, sleep_until}
+};
+use http_core::{
+    headers::Headers,
+    method::Method,
+    request::{RequestBuilder, BodySource},
+    response::{Response, BodyStream},
+    url::ToUrl,
+    version::Version,
+    error::HttpError
+};
+use frame::*;
+use hpack::{Decoder, Encoder, HeaderList, HpackError};
+use crate::{
+    access_log::AccessEntry,
+    shutdown::{ShutdownSignal, is_draining}
+};
+use super::{
+    BuildError,
+    Transport,
+    http1::frame_response,
+    types::*
+};
+
+mod frame;
+mod hpack;
+
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+pub const VERSION: Version = Version { major: 2, minor: 0 };
+
+const DEFAULT_WINDOW_SIZE: i64 = 65_535;
+/// Request body buffered for every stream on a connection, each stream is
+/// also held to DEFAULT_WINDOW_SIZE.
+const CONNECTION_WINDOW_SIZE: i64 = 1 << 20;
+const MAX_CONCURRENT_STREAMS: usize = 100;
+const MAX_HEADER_LIST_SIZE: usize = 16_384;
+
+/// Stop creating DATA frames while this much is waiting to be written.
+const OUTPUT_HIGH_WATER: usize = 65_536;
+
+/// Header fields that only apply to HTTP/1 connections.
+///
+/// RFC-9113 8.2.2
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade"
+];
+
+/// Creates the future that handles a request.
+///
+/// The request lives until the future is dropped.
+pub trait Handler = Fn(*mut RequestBuilder<TcpStream>) -> Pin<Box<dyn Future<Output = Response>>>;
+
+/// Checks if the connection starts with the HTTP/2 client preface without
+/// consuming any of it.
+pub async fn is_preface<S: AsyncRead + Unpin>(reader:&mut AsyncBufReader<S>) -> io::Result<bool> {
+    loop {
+        let buffer = reader.buffer();
+        let length = buffer.len().min(PREFACE.len());
+
+        if buffer[..length] != PREFACE[..length] {
+            return Ok(false);
+        } else if length == PREFACE.len() {
+            return Ok(true);
+        } else if reader.read_more().await? == 0 {
+            return Ok(false);
+        }
+    }
+}
+
+/// base64url decoding used by the HTTP2-Settings header.
+fn decode_base64url(value:&str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(value.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut count = 0;
+
+    for c in value.trim().trim_end_matches('=').bytes() {
+        let n = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None
+        };
+
+        bits = (bits << 6) | n as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            output.push((bits >> count) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+/// Request that asked to upgrade to h2c, with the settings it was sent with.
+pub type Upgrade = (RequestBuilder<TcpStream>, Vec<(u16, u32)>);
+
+/// Returns the client's settings if the request asks to upgrade to HTTP/2.
+///
+/// Requests with a body are not upgraded.
+///
+/// RFC-7540 3.2
+/// https://datatracker.ietf.org/doc/html/rfc7540#section-3.2
+pub fn upgrade_settings<S: AsyncRead>(req:&RequestBuilder<S>) -> Option<Vec<(u16, u32)>> {
+    if req.version != Version::new() || req.has_body() {
+        return None;
+    }
+
+    let upgrade = req.headers.get("Upgrade")?.ref_str().ok()?;
+    if !upgrade.split(',').any(|p|p.trim().eq_ignore_ascii_case("h2c")) {
+        return None;
+    }
+
+    let settings = req.headers.get("HTTP2-Settings")?.ref_str().ok()?;
+    Frame::parse_settings(&decode_base64url(settings)?).ok()
+}
+
+/// Request body received in DATA frames.
+struct StreamBody {
+    chunks: VecDeque<Vec<u8>>,
+    trailers: Option<Headers>,
+    ended: bool,
+    reset: bool,
+    consumed: usize
+}
+
+impl StreamBody {
+    fn new() -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            trailers: None,
+            ended: false,
+            reset: false,
+            consumed: 0
+        }
+    }
+}
+
+struct H2Body(Rc<RefCell<StreamBody>>);
+
+impl BodySource for H2Body {
+    fn poll_next_chunk(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, HttpError>>> {
+        let mut body = self.0.borrow_mut();
+
+        if let Some(chunk) = body.chunks.pop_front() {
+            body.consumed += chunk.len();
+            Poll::Ready(Some(Ok(chunk)))
+        } else if body.ended {
+            Poll::Ready(None)
+        } else if body.reset {
+            Poll::Ready(Some(Err(
+                BuildError::IoError(io::ErrorKind::ConnectionReset.into()).into()
+            )))
+        } else {
+            //Connection wakes the handler when polling it again.
+            Poll::Pending
+        }
+    }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<Headers> {
+        self.0.borrow_mut().trailers.take()
+    }
+}
+
+/// Response body waiting on flow control.
+struct Outgoing {
+    data: VecDeque<u8>,
+    stream: Option<Pin<Box<dyn BodyStream>>>
+}
+
+/// RFC-9113 5.1
+struct Stream {
+    //Handler must be dropped before the request it points to.
+    handler: Option<Pin<Box<dyn Future<Output = Response>>>>,
+    request: Option<Box<RequestBuilder<TcpStream>>>,
+    body: Rc<RefCell<StreamBody>>,
+    response: Option<Outgoing>,
+    send_window: i64,
+    recv_window: i64,
+    remote_closed: bool,
+    //Written once the stream is closed.
+    access: AccessEntry
+}
+
+pub struct Connection<'c, S, H> where S: Transport, H: Handler {
+    reader: &'c mut AsyncBufReader<S>,
+    hostname: &'c str,
+    port: u16,
+    handler: H,
+    decoder: Decoder,
+    encoder: Encoder,
+    streams: BTreeMap<u32, Stream>,
+    output: Vec<u8>,
+    preface: bool,
+    incoming: Option<(Frame, usize)>,
+    header_block: Option<(u32, Vec<u8>, bool)>,
+    last_stream: u32,
+    send_window: i64,
+    initial_window: i64,
+    recv_window: i64,
+    //Connection window freed since the last WINDOW_UPDATE.
+    released: usize,
+    max_frame_size: usize,
+    read_closed: bool,
+    going_away: bool,
+    idle_timeout: Duration,
+    last_active: Instant,
+    idle: Sleep,
+    shutdown: ShutdownSignal
+}
+
+impl<'c, S, H> Connection<'c, S, H> where S: Transport, H: Handler {
+    pub fn new(reader:&'c mut AsyncBufReader<S>, hostname:&'c str, port:u16, idle_timeout:Duration, handler:H) -> Self {
+        let mut conn = Self {
+            reader, hostname, port, handler,
+            decoder: Decoder::new(MAX_HEADER_LIST_SIZE),
+            encoder: Encoder::new(),
+            streams: BTreeMap::new(),
+            output: Vec::new(),
+            preface: false,
+            incoming: None,
+            header_block: None,
+            last_stream: 0,
+            send_window: DEFAULT_WINDOW_SIZE,
+            initial_window: DEFAULT_WINDOW_SIZE,
+            recv_window: CONNECTION_WINDOW_SIZE,
+            released: 0,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            read_closed: false,
+            going_away: false,
+            idle_timeout,
+            last_active: Instant::now(),
+            idle: sleep_until(Instant::now() + idle_timeout),
+            shutdown: ShutdownSignal::new()
+        };
+
+        //RFC-9113 3.4
+        conn.send(Frame::settings(&[
+            (settings::MAX_CONCURRENT_STREAMS, MAX_CONCURRENT_STREAMS as u32),
+            (settings::MAX_HEADER_LIST_SIZE, MAX_HEADER_LIST_SIZE as u32),
+            (settings::ENABLE_PUSH, 0)
+        ]));
+        conn.send(Frame::window_update(0, (CONNECTION_WINDOW_SIZE - DEFAULT_WINDOW_SIZE) as u32));
+
+        conn
+    }
+
+    /// Continues the request that asked to upgrade as stream 1.
+    ///
+    /// RFC-7540 3.2
+    pub fn upgrade(&mut self, mut req:RequestBuilder<TcpStream>, settings:Vec<(u16, u32)>) {
+        if let Err(e) = self.apply_settings(settings) {
+            self.fatal(e);
+            return;
+        }
+
+        req.version = VERSION;
+        self.last_stream = 1;
+        self.open_stream(1, req, None);
+    }
+
+    pub async fn run(&mut self) -> io::Result<()> {
+        std::future::poll_fn(|cx|self.poll_run(cx)).await
+    }
+
+    fn send(&mut self, frame:Frame) {
+        frame.write_to(&mut self.output);
+    }
+
+    /// Connection Error
+    ///
+    /// RFC-9113 5.4.1
+    fn fatal(&mut self, error:ErrorCode) {
+        self.send(Frame::go_away(self.last_stream, error));
+        self.streams.clear();
+        self.header_block = None;
+        self.read_closed = true;
+        self.going_away = true;
+    }
+
+    /// Stream Error
+    ///
+    /// RFC-9113 5.4.2
+    fn reset(&mut self, id:u32, error:ErrorCode) {
+        self.send(Frame::rst_stream(id, error));
+        self.remove_stream(id);
+    }
+
+    /// Removes the stream, freeing the connection window held by request body
+    /// the handler will now never read.
+    fn remove_stream(&mut self, id:u32) -> Option<Stream> {
+        let stream = self.streams.remove(&id)?;
+        let unread = {
+            let body = stream.body.borrow();
+            body.consumed + body.chunks.iter().map(Vec::len).sum::<usize>()
+        };
+
+        self.released += unread;
+        Some(stream)
+    }
+
+    fn poll_run(&mut self, cx:&mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            let mut progress = false;
+
+            if !self.read_closed {
+                match self.poll_frame(cx) {
+                    Poll::Ready(Ok(Some(frame))) => {
+                        progress = true;
+                        self.last_active = Instant::now();
+                        if let Err(e) = self.handle_frame(frame) {
+                            self.fatal(e);
+                        }
+                    },
+                    Poll::Ready(Ok(None)) => {
+                        progress = true;
+                        self.read_closed = true;
+                    },
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => {}
+                }
+            }
+
+            //RFC-9113 6.8
+            if !self.going_away && is_draining() {
+                self.send(Frame::go_away(self.last_stream, ErrorCode::NoError));
+                self.going_away = true;
+            }
+
+            progress |= self.poll_handlers(cx);
+            progress |= self.poll_responses(cx);
+            self.update_windows();
+
+            match self.poll_flush(cx) {
+                Poll::Ready(Ok(())) => {},
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => {}
+            }
+
+            if self.streams.is_empty() && self.output.is_empty() {
+                if self.read_closed || self.going_away {
+                    return Poll::Ready(Ok(()));
+                } else if self.last_active.elapsed() >= self.idle_timeout {
+                    self.send(Frame::go_away(self.last_stream, ErrorCode::NoError));
+                    self.going_away = true;
+                    self.read_closed = true;
+                    continue;
+                }
+            }
+
+            if !progress {
+                if !self.going_away && self.shutdown.poll(cx).is_ready() {
+                    continue;
+                }
+
+                //Otherwise the socket and the handlers wake the connection.
+                if self.streams.is_empty() && self.output.is_empty() && !self.going_away {
+                    self.idle.reset(self.last_active + self.idle_timeout);
+                    if Pin::new(&mut self.idle).poll(cx).is_ready() {
+                        continue;
+                    }
+                }
+
+                return Poll::Pending;
+            }
+        }
+    }
+
+    /// Reads the client preface and then the next frame.
+    ///
+    /// Returns None once nothing more can be read from the connection.
+    fn poll_frame(&mut self, cx:&mut Context<'_>) -> Poll<io::Result<Option<Frame>>> {
+        loop {
+            let buffer = self.reader.buffer();
+
+            if !self.preface {
+                let length = buffer.len().min(PREFACE.len());
+                if buffer[..length] != PREFACE[..length] {
+                    self.fatal(ErrorCode::ProtocolError);
+                    return Poll::Ready(Ok(None));
+                } else if length == PREFACE.len() {
+                    self.reader.consume(length);
+                    self.preface = true;
+                    continue;
+                }
+            } else if let Some((frame, length)) = self.incoming.as_mut() {
+                let amt = buffer.len().min(*length - frame.payload.len());
+                frame.payload.extend_from_slice(&buffer[..amt]);
+                self.reader.consume(amt);
+
+                if frame.payload.len() == *length {
+                    return Poll::Ready(Ok(self.incoming.take().map(|(frame, _)|frame)));
+                }
+            } else if buffer.len() >= HEADER_SIZE {
+                let (frame, length) = Frame::parse_header(buffer[..HEADER_SIZE].try_into().unwrap());
+                self.reader.consume(HEADER_SIZE);
+
+                //RFC-9113 4.2
+                if length > DEFAULT_MAX_FRAME_SIZE {
+                    self.fatal(ErrorCode::FrameSizeError);
+                    return Poll::Ready(Ok(None));
+                }
+
+                self.incoming = Some((frame, length));
+                continue;
+            }
+
+            let result = if self.reader.buffer().is_empty() {
+                Pin::new(&mut *self.reader).poll_fill_buf(cx).map(|r|r.map(|b|b.len()))
+            } else {
+                Pin::new(&mut *self.reader).poll_read_more(cx)
+            };
+
+            match result {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(None)),
+                Poll::Ready(Ok(_)) => {}
+            }
+        }
+    }
+
+    fn handle_frame(&mut self, mut frame:Frame) -> Result<(), ErrorCode> {
+        //RFC-9113 6.10
+        if let Some((id, _, _)) = self.header_block
+            && (frame.kind != FrameType::Continuation || frame.stream != id) {
+            return Err(ErrorCode::ProtocolError);
+        }
+
+        match frame.kind {
+            FrameType::Data => {
+                if frame.stream == 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+
+                //RFC-9113 6.9, the entire frame counts against flow control.
+                let length = frame.payload.len();
+                self.recv_window -= length as i64;
+                if self.recv_window < 0 {
+                    return Err(ErrorCode::FlowControlError);
+                }
+                frame.unpad()?;
+
+                let end_stream = frame.has_flag(flags::END_STREAM);
+                match self.streams.get_mut(&frame.stream) {
+                    Some(stream) if !stream.remote_closed => {
+                        stream.recv_window -= length as i64;
+                        if stream.recv_window < 0 {
+                            self.released += length;
+                            self.reset(frame.stream, ErrorCode::FlowControlError);
+                            return Ok(());
+                        }
+
+                        let mut body = stream.body.borrow_mut();
+                        body.consumed += length - frame.payload.len();
+                        if !frame.payload.is_empty() {
+                            body.chunks.push_back(frame.payload);
+                        }
+                        if end_stream {
+                            body.ended = true;
+                            stream.remote_closed = true;
+                        }
+                    },
+                    Some(_) => {
+                        self.released += length;
+                        self.reset(frame.stream, ErrorCode::StreamClosed);
+                    },
+                    None => if frame.stream > self.last_stream {
+                        return Err(ErrorCode::ProtocolError);
+                    } else {
+                        self.released += length;
+                    }
+                }
+            },
+            FrameType::Headers => {
+                if frame.stream == 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+
+                frame.unpad()?;
+                if frame.has_flag(flags::PRIORITY) {
+                    if frame.payload.len() < 5 {
+                        return Err(ErrorCode::FrameSizeError);
+                    }
+                    frame.payload.drain(..5);
+                }
+
+                let end_stream = frame.has_flag(flags::END_STREAM);
+                if frame.has_flag(flags::END_HEADERS) {
+                    self.handle_headers(frame.stream, frame.payload, end_stream)?;
+                } else {
+                    self.header_block = Some((frame.stream, frame.payload, end_stream));
+                }
+            },
+            FrameType::Continuation => {
+                let (id, mut block, end_stream) = self.header_block.take()
+                    .ok_or(ErrorCode::ProtocolError)?;
+                block.extend_from_slice(&frame.payload);
+
+                if block.len() > MAX_HEADER_LIST_SIZE * 2 {
+                    return Err(ErrorCode::EnhanceYourCalm);
+                } else if frame.has_flag(flags::END_HEADERS) {
+                    self.handle_headers(id, block, end_stream)?;
+                } else {
+                    self.header_block = Some((id, block, end_stream));
+                }
+            },
+            FrameType::Priority => {},
+            FrameType::RstStream => {
+                if frame.stream == 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+
+                if let Some(stream) = self.remove_stream(frame.stream) {
+                    stream.body.borrow_mut().reset = true;
+                }
+            },
+            FrameType::Settings => {
+                if frame.stream != 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+
+                if !frame.has_flag(flags::ACK) {
+                    self.apply_settings(Frame::parse_settings(&frame.payload)?)?;
+                    self.send(Frame::new(FrameType::Settings, flags::ACK, 0, Vec::new()));
+                }
+            },
+            FrameType::PushPromise => return Err(ErrorCode::ProtocolError),
+            FrameType::Ping => {
+                if frame.stream != 0 {
+                    return Err(ErrorCode::ProtocolError);
+                } else if frame.payload.len() != 8 {
+                    return Err(ErrorCode::FrameSizeError);
+                }
+
+                if !frame.has_flag(flags::ACK) {
+                    self.send(Frame::new(FrameType::Ping, flags::ACK, 0, frame.payload));
+                }
+            },
+            FrameType::GoAway => {
+                self.going_away = true;
+            },
+            FrameType::WindowUpdate => {
+                if frame.payload.len() != 4 {
+                    return Err(ErrorCode::FrameSizeError);
+                }
+
+                let increment = (u32::from_be_bytes(frame.payload[..4].try_into().unwrap()) & 0x7FFF_FFFF) as i64;
+                if frame.stream == 0 {
+                    if increment == 0 {
+                        return Err(ErrorCode::ProtocolError);
+                    }
+
+                    self.send_window += increment;
+                    if self.send_window > MAX_WINDOW_SIZE {
+                        return Err(ErrorCode::FlowControlError);
+                    }
+                } else if let Some(stream) = self.streams.get_mut(&frame.stream) {
+                    stream.send_window += increment;
+
+                    if increment == 0 {
+                        self.reset(frame.stream, ErrorCode::ProtocolError);
+                    } else if stream.send_window > MAX_WINDOW_SIZE {
+                        self.reset(frame.stream, ErrorCode::FlowControlError);
+                    }
+                }
+            },
+            FrameType::Unknown(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// RFC-9113 6.5.2
+    fn apply_settings(&mut self, list:Vec<(u16, u32)>) -> Result<(), ErrorCode> {
+        for (id, value) in list {
+            match id {
+                settings::INITIAL_WINDOW_SIZE => {
+                    let value = value as i64;
+                    if value > MAX_WINDOW_SIZE {
+                        return Err(ErrorCode::FlowControlError);
+                    }
+
+                    let delta = value - self.initial_window;
+                    self.initial_window = value;
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                    }
+                },
+                settings::MAX_FRAME_SIZE => {
+                    if !(DEFAULT_MAX_FRAME_SIZE as u32..=0xFF_FFFF).contains(&value) {
+                        return Err(ErrorCode::ProtocolError);
+                    }
+
+                    self.max_frame_size = value as usize;
+                },
+                settings::ENABLE_PUSH if value > 1 => {
+                    return Err(ErrorCode::ProtocolError);
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_headers(&mut self, id:u32, block:Vec<u8>, end_stream:bool) -> Result<(), ErrorCode> {
+        //Block is always decoded to keep the table in sync.
+        let list = match self.decoder.decode(&block) {
+            Ok(list) => Some(list),
+            Err(HpackError::HeaderListTooLarge(_)) => None,
+            Err(_) => return Err(ErrorCode::CompressionError)
+        };
+
+        //Trailers
+        if let Some(stream) = self.streams.get_mut(&id) {
+            if stream.remote_closed || !end_stream {
+                self.reset(id, ErrorCode::StreamClosed);
+                return Ok(());
+            }
+
+            let Some(list) = list else {
+                self.reset(id, ErrorCode::EnhanceYourCalm);
+                return Ok(());
+            };
+
+            let mut headers = Headers::new();
+            for (name, value) in list {
+                headers.set(&String::from_utf8_lossy(&name), String::from_utf8_lossy(&value).as_ref());
+            }
+
+            let mut body = stream.body.borrow_mut();
+            body.trailers = Some(headers);
+            body.ended = true;
+            stream.remote_closed = true;
+            return Ok(());
+        }
+
+        //RFC-9113 5.1.1
+        if id.is_multiple_of(2) || id <= self.last_stream {
+            return Err(ErrorCode::ProtocolError);
+        }
+        self.last_stream = id;
+
+        if self.going_away {
+            return Ok(());
+        } else if self.streams.len() >= MAX_CONCURRENT_STREAMS {
+            self.reset(id, ErrorCode::RefusedStream);
+            return Ok(());
+        }
+
+        //RFC-9113 10.5.1
+        let Some(list) = list else {
+            self.reset(id, ErrorCode::EnhanceYourCalm);
+            return Ok(());
+        };
+
+        let body = if end_stream {
+            None
+        } else {
+            Some(Rc::new(RefCell::new(StreamBody::new())))
+        };
+
+        let source = body.clone().map(|b|Box::pin(H2Body(b)) as Pin<Box<dyn BodySource>>);
+        match self.build_request(list, source) {
+            Ok(req) => self.open_stream(id, req, body),
+            Err(_) => self.reset(id, ErrorCode::ProtocolError)
+        }
+
+        Ok(())
+    }
+
+    /// RFC-9113 8.3.1
+    fn build_request(&self, list:HeaderList, body:Option<Pin<Box<dyn BodySource>>>) -> Result<RequestBuilder<TcpStream>, BuildError> {
+        let mut method: Option<Method> = None;
+        let mut path: Option<Vec<u8>> = None;
+        let mut authority: Option<String> = None;
+        let mut cookies: Vec<String> = Vec::new();
+        let mut headers = Headers::new();
+
+        for (name, value) in list {
+            let name = String::from_utf8_lossy(&name).to_string();
+            let value = String::from_utf8_lossy(&value).to_string();
+
+            match name.as_str() {
+                ":method" => method = Some(
+                    Method::from(&value).ok_or(BuildError::InvalidMethod(value))?
+                ),
+                ":path" => path = Some(value.into_bytes()),
+                ":authority" => authority = Some(value),
+                ":scheme" => {},
+                "cookie" => cookies.push(value),
+                other => {
+                    if other.starts_with(':') || CONNECTION_HEADERS.contains(&other)
+                        || (other == "te" && value != "trailers") {
+                        return Err(BuildError::InvalidHeader(other.to_string()));
+                    }
+
+                    let value = match headers.get(other).and_then(|v|v.ref_str().ok()) {
+                        Some(existing) => format!("{existing}, {value}"),
+                        None => value
+                    };
+                    headers.set(other, value.as_str());
+                }
+            }
+        }
+
+        //RFC-9113 8.2.3
+        if !cookies.is_empty() {
+            headers.set("Cookie", cookies.join("; ").as_str());
+        }
+
+        if let Some(authority) = authority && headers.get("Host").is_none() {
+            headers.set("Host", authority.as_str());
+        }
+
+        let method = method.ok_or(BuildError::MissingMethod)?;
+        let path = Chunk::from(path.ok_or(BuildError::MissingUri)?);
+        let uri = match path.split().next() {
+            Some(text) => Uri::parse(&text).map_err(BuildError::InvalidUri)?,
+            None => return Err(BuildError::MissingUri)
+        };
+
+        let mut url = uri.to_url(self.hostname.into(), self.port)
+            .map_err(BuildError::InvalidUrl)?;
+        url.protocol = self.reader.get_ref().protocol();
+
+        let mut builder = RequestBuilder::new(url, method, headers, VERSION, body);
+        builder.local_addr = self.reader.get_ref().local_addr();
+        builder.peer_addr = self.reader.get_ref().peer_addr();
+        Ok(builder)
+    }
+
+    fn open_stream(&mut self, id:u32, req:RequestBuilder<TcpStream>, body:Option<Rc<RefCell<StreamBody>>>) {
+        let remote_closed = body.is_none();
+        let body = body.unwrap_or_else(||Rc::new(RefCell::new(StreamBody::new())));
+
+        let access = AccessEntry::new(&req);
+        let mut request = Box::new(req);
+        let handler = (self.handler)(&mut *request as *mut RequestBuilder<TcpStream>);
+
+        self.streams.insert(id, Stream {
+            handler: Some(handler),
+            request: Some(request),
+            body,
+            response: None,
+            send_window: self.initial_window,
+            recv_window: DEFAULT_WINDOW_SIZE,
+            remote_closed,
+            access
+        });
+    }
+
+    /// Polls each handler that is not done.
+    fn poll_handlers(&mut self, cx:&mut Context<'_>) -> bool {
+        let mut ready = Vec::new();
+
+        for (id, stream) in self.streams.iter_mut() {
+            if let Some(handler) = stream.handler.as_mut()
+                && let Poll::Ready(resp) = handler.as_mut().poll(cx) {
+                stream.handler = None;
+                ready.push((*id, resp));
+            }
+        }
+
+        let progress = !ready.is_empty();
+        for (id, resp) in ready {
+            self.start_response(id, resp);
+        }
+
+        progress
+    }
+
+    /// Sends the HEADERS of the response and queues the body.
+    fn start_response(&mut self, id:u32, mut resp:Response) {
+        let method = self.streams.get(&id)
+            .and_then(|s|s.request.as_ref())
+            .map(|r|r.method);
+
+        frame_response(&mut resp, VERSION);
+        let code = resp.status.code();
+        if let Some(s) = self.streams.get_mut(&id) {
+            s.access.status(code);
+        }
+        let no_body = code < 200 || code == 204 || code == 304 || method == Some(Method::HEAD);
+
+        let status = code.to_string();
+        let mut list: Vec<(String, String)> = vec![(String::from(":status"), status)];
+        for (name, value) in &resp.headers {
+            let name = name.name().to_ascii_lowercase();
+            if !CONNECTION_HEADERS.contains(&name.as_str()) && let Ok(value) = value.ref_str() {
+                list.push((name, value.to_string()));
+            }
+        }
+
+        let data: VecDeque<u8> = if no_body {
+            VecDeque::new()
+        } else {
+            resp.body.iter().flat_map(|chunk|chunk.value().iter().copied()).collect()
+        };
+        let stream = if no_body { None } else { resp.stream.take() };
+        let end_stream = data.is_empty() && stream.is_none();
+
+        let block = self.encoder.encode(list.iter().map(|(n, v)|(n.as_str(), v.as_str())));
+        let mut chunks = block.chunks(self.max_frame_size).peekable();
+        let mut kind = FrameType::Headers;
+        let mut flags = if end_stream { flags::END_STREAM } else { 0 };
+        loop {
+            let chunk = chunks.next().unwrap_or(&[]);
+            let last = chunks.peek().is_none();
+            if last {
+                flags |= flags::END_HEADERS;
+            }
+
+            self.send(Frame::new(kind, flags, id, chunk.to_vec()));
+            if last {
+                break;
+            }
+
+            kind = FrameType::Continuation;
+            flags = 0;
+        }
+
+        if end_stream {
+            self.finish_stream(id);
+        } else if let Some(s) = self.streams.get_mut(&id) {
+            s.request = None;
+            s.response = Some(Outgoing { data, stream });
+        }
+    }
+
+    /// Closes our side of the stream, and stops the client from sending
+    /// any more of the request.
+    ///
+    /// RFC-9113 8.1
+    fn finish_stream(&mut self, id:u32) {
+        if let Some(stream) = self.remove_stream(id) && !stream.remote_closed {
+            self.send(Frame::rst_stream(id, ErrorCode::NoError));
+        }
+    }
+
+    /// Writes DATA frames for as much of each response as flow control allows.
+    fn poll_responses(&mut self, cx:&mut Context<'_>) -> bool {
+        let mut progress = false;
+        let mut finished = Vec::new();
+        let ids: Vec<u32> = self.streams.keys().copied().collect();
+
+        for id in ids {
+            loop {
+                if self.output.len() >= OUTPUT_HIGH_WATER {
+                    break;
+                }
+
+                let max_frame_size = self.max_frame_size as i64;
+                let window = self.send_window;
+                let stream = self.streams.get_mut(&id).unwrap();
+                let outgoing = match stream.response.as_mut() {
+                    Some(outgoing) => outgoing,
+                    None => break
+                };
+
+                if outgoing.data.is_empty() {
+                    match outgoing.stream.as_mut() {
+                        Some(body) => match body.as_mut().poll_next_chunk(cx) {
+                            Poll::Ready(Some(chunk)) => {
+                                outgoing.data.extend(chunk.value());
+                                continue;
+                            },
+                            Poll::Ready(None) => {
+                                outgoing.stream = None;
+                                continue;
+                            },
+                            Poll::Pending => break
+                        },
+                        None => {
+                            Frame::new(FrameType::Data, flags::END_STREAM, id, Vec::new())
+                                .write_to(&mut self.output);
+                            finished.push(id);
+                            progress = true;
+                            break;
+                        }
+                    }
+                }
+
+                let amt = (outgoing.data.len() as i64)
+                    .min(window)
+                    .min(stream.send_window)
+                    .min(max_frame_size);
+                if amt <= 0 {
+                    break;
+                }
+
+                let payload: Vec<u8> = outgoing.data.drain(..amt as usize).collect();
+                let end = outgoing.data.is_empty() && outgoing.stream.is_none();
+                stream.send_window -= amt;
+                stream.access.sent(amt as u64);
+                self.send_window -= amt;
+
+                Frame::new(FrameType::Data, if end { flags::END_STREAM } else { 0 }, id, payload)
+                    .write_to(&mut self.output);
+                progress = true;
+
+                if end {
+                    finished.push(id);
+                    break;
+                }
+            }
+        }
+
+        for id in finished {
+            self.finish_stream(id);
+        }
+
+        progress
+    }
+
+    /// Gives the client back the window used by request body the handler has read,
+    /// so no more is buffered than the windows allow.
+    fn update_windows(&mut self) {
+        let mut updates = Vec::new();
+
+        for (id, stream) in self.streams.iter_mut() {
+            let mut body = stream.body.borrow_mut();
+            if body.consumed > 0 {
+                self.released += body.consumed;
+                stream.recv_window += body.consumed as i64;
+                if !stream.remote_closed {
+                    updates.push((*id, body.consumed as u32));
+                }
+                body.consumed = 0;
+            }
+        }
+
+        if self.released > 0 && !self.read_closed {
+            self.recv_window += self.released as i64;
+            self.send(Frame::window_update(0, self.released as u32));
+        }
+        self.released = 0;
+
+        for (id, increment) in updates {
+            self.send(Frame::window_update(id, increment));
+        }
+    }
+
+    fn poll_flush(&mut self, cx:&mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.output.is_empty() {
+            match Pin::new(self.reader.get_mut()).poll_write(cx, &self.output) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(amt)) => {
+                    self.output.drain(..amt);
+                }
+            }
+        }
+
+        Pin::new(self.reader.get_mut()).poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+    use async_lib::io::AsyncWrite;
+    use http_core::{status::HttpStatus, url::Protocol};
+    use super::*;
+
+    /// Socket that reads the given bytes and then waits, keeping what is written to it.
+    struct MockStream {
+        input: VecDeque<u8>,
+        output: Rc<RefCell<Vec<u8>>>
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            if self.input.is_empty() {
+                return Poll::Pending;
+            }
+
+            let amt = buf.len().min(self.input.len());
+            for (byte, value) in buf.iter_mut().zip(self.input.drain(..amt)) {
+                *byte = value;
+            }
+            Poll::Ready(Ok(amt))
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.output.borrow_mut().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl Transport for MockStream {
+        fn protocol(&self) -> Protocol {
+            Protocol::Http
+        }
+    }
+
+    type TestHandler = fn(*mut RequestBuilder<TcpStream>) -> Pin<Box<dyn Future<Output = Response>>>;
+
+    /// Never reads the body or responds.
+    const WAIT: TestHandler = |_|Box::pin(std::future::pending());
+
+    /// Reads the body as it comes in.
+    const READ: TestHandler = |req|Box::pin(async move {
+        //SAFETY: Request outlives the handler.
+        let _ = unsafe{ &mut *req }.drain_body().await;
+        std::future::pending().await
+    });
+
+    const RESPOND: TestHandler = |_|Box::pin(async{ Response::new(HttpStatus::Ok, None) });
+
+    /// Sends the client preface and frames, returning the frames written back
+    /// once the connection is waiting on the client.
+    fn run(input:&[Frame], handler:TestHandler) -> Vec<Frame> {
+        let mut bytes = PREFACE.to_vec();
+        for frame in input {
+            frame.write_to(&mut bytes);
+        }
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let stream = MockStream { input: bytes.into(), output: output.clone() };
+        let mut reader = AsyncBufReader::with_capacity(DEFAULT_MAX_FRAME_SIZE, stream);
+        let mut conn = Connection::new(&mut reader, "localhost", 80, Duration::from_secs(60), handler);
+        let _ = conn.poll_run(&mut Context::from_waker(Waker::noop()));
+        drop(conn);
+
+        let output = output.borrow();
+        let mut frames = Vec::new();
+        let mut index = 0;
+        while index < output.len() {
+            let (mut frame, length) = Frame::parse_header(output[index..index + HEADER_SIZE].try_into().unwrap());
+            index += HEADER_SIZE;
+            frame.payload.extend_from_slice(&output[index..index + length]);
+            index += length;
+            frames.push(frame);
+        }
+
+        frames
+    }
+
+    fn headers(id:u32, end_stream:bool) -> Frame {
+        let block = Encoder::new().encode([
+            (":method", "POST"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "localhost")
+        ].into_iter());
+
+        let flags = if end_stream { flags::END_HEADERS | flags::END_STREAM } else { flags::END_HEADERS };
+        Frame::new(FrameType::Headers, flags, id, block)
+    }
+
+    fn data(id:u32, length:usize) -> Frame {
+        Frame::new(FrameType::Data, 0, id, vec![b'a'; length])
+    }
+
+    /// Sends a full stream window of DATA.
+    fn fill_window(frames:&mut Vec<Frame>, id:u32) {
+        let mut remaining = DEFAULT_WINDOW_SIZE as usize;
+        while remaining > 0 {
+            let length = remaining.min(DEFAULT_MAX_FRAME_SIZE);
+            frames.push(data(id, length));
+            remaining -= length;
+        }
+    }
+
+    fn error_code(frame:&Frame) -> u32 {
+        let start = if frame.kind == FrameType::GoAway { 4 } else { 0 };
+        u32::from_be_bytes(frame.payload[start..start + 4].try_into().unwrap())
+    }
+
+    fn go_away(frames:&[Frame]) -> Option<u32> {
+        frames.iter().find(|f|f.kind == FrameType::GoAway).map(error_code)
+    }
+
+    fn rst_stream(frames:&[Frame], id:u32) -> Option<u32> {
+        frames.iter().find(|f|f.kind == FrameType::RstStream && f.stream == id).map(error_code)
+    }
+
+    /// Increments sent for the stream, leaving out the one sent with the settings.
+    fn window_updates(frames:&[Frame], id:u32) -> Vec<u32> {
+        frames.iter()
+            .filter(|f|f.kind == FrameType::WindowUpdate && f.stream == id)
+            .map(|f|u32::from_be_bytes(f.payload[..4].try_into().unwrap()))
+            .skip(if id == 0 { 1 } else { 0 })
+            .collect()
+    }
+
+    #[test]
+    fn connection_start() {
+        let ping = Frame::new(FrameType::Ping, 0, 0, b"12345678".to_vec());
+        let frames = run(&[Frame::settings(&[]), ping], WAIT);
+
+        assert!(frames[0].kind == FrameType::Settings && !frames[0].has_flag(flags::ACK));
+        assert!(frames[1].kind == FrameType::WindowUpdate && frames[1].stream == 0);
+        assert_eq!(u32::from_be_bytes(frames[1].payload[..4].try_into().unwrap()) as i64, CONNECTION_WINDOW_SIZE - DEFAULT_WINDOW_SIZE);
+        assert!(frames.iter().any(|f|f.kind == FrameType::Settings && f.has_flag(flags::ACK)));
+        assert!(frames.iter().any(|f|f.kind == FrameType::Ping && f.has_flag(flags::ACK) && f.payload == b"12345678"));
+        assert_eq!(go_away(&frames), None);
+    }
+
+    #[test]
+    fn window_update_only_when_read() {
+        let frames = run(&[headers(1, false), data(1, 1000)], WAIT);
+        assert!(window_updates(&frames, 0).is_empty());
+        assert!(window_updates(&frames, 1).is_empty());
+
+        let frames = run(&[headers(1, false), data(1, 1000)], READ);
+        assert_eq!(window_updates(&frames, 0), [1000]);
+        assert_eq!(window_updates(&frames, 1), [1000]);
+    }
+
+    #[test]
+    fn padding_given_back() {
+        let mut payload = vec![10];
+        payload.extend([b'a'; 5]);
+        payload.extend([0; 10]);
+        let padded = Frame::new(FrameType::Data, flags::PADDED, 1, payload);
+
+        let frames = run(&[headers(1, false), padded], WAIT);
+        assert_eq!(window_updates(&frames, 0), [11]);
+        assert_eq!(window_updates(&frames, 1), [11]);
+    }
+
+    #[test]
+    fn stream_window_overflow() {
+        let mut input = vec![headers(1, false)];
+        fill_window(&mut input, 1);
+        let frames = run(&input, WAIT);
+        assert_eq!(rst_stream(&frames, 1), None);
+
+        input.push(data(1, 1));
+        let frames = run(&input, WAIT);
+        assert_eq!(rst_stream(&frames, 1), Some(ErrorCode::FlowControlError.code()));
+        assert_eq!(go_away(&frames), None);
+
+        //Discarded body is given back to the connection.
+        assert_eq!(window_updates(&frames, 0), [DEFAULT_WINDOW_SIZE as u32 + 1]);
+    }
+
+    #[test]
+    fn connection_window_overflow() {
+        let streams = (CONNECTION_WINDOW_SIZE / DEFAULT_WINDOW_SIZE) as u32;
+        let mut input = Vec::new();
+        for id in (1..streams * 2).step_by(2) {
+            input.push(headers(id, false));
+            fill_window(&mut input, id);
+        }
+
+        let used = streams as i64 * DEFAULT_WINDOW_SIZE;
+        input.push(headers(streams * 2 + 1, false));
+        input.push(data(streams * 2 + 1, (CONNECTION_WINDOW_SIZE - used) as usize));
+        let frames = run(&input, WAIT);
+        assert_eq!(go_away(&frames), None);
+
+        input.push(data(streams * 2 + 1, 1));
+        let frames = run(&input, WAIT);
+        assert_eq!(go_away(&frames), Some(ErrorCode::FlowControlError.code()));
+    }
+
+    #[test]
+    fn reset_gives_back_window() {
+        let reset = Frame::rst_stream(1, ErrorCode::Cancel);
+        let frames = run(&[headers(1, false), data(1, 1000), reset], WAIT);
+        assert_eq!(window_updates(&frames, 0), [1000]);
+        assert!(window_updates(&frames, 1).is_empty());
+    }
+
+    #[test]
+    fn response_ends_stream() {
+        let frames = run(&[headers(1, true)], RESPOND);
+        let resp = frames.iter().find(|f|f.kind == FrameType::Headers && f.stream == 1).unwrap();
+        assert!(resp.has_flag(flags::END_STREAM) && resp.has_flag(flags::END_HEADERS));
+        assert_eq!(rst_stream(&frames, 1), None);
+
+        //Client is told to stop sending a body the handler did not wait for.
+        let frames = run(&[headers(1, false)], RESPOND);
+        assert_eq!(rst_stream(&frames, 1), Some(ErrorCode::NoError.code()));
+    }
+
+    #[test]
+    fn data_after_end_stream() {
+        let frames = run(&[headers(1, true), data(1, 10)], WAIT);
+        assert_eq!(rst_stream(&frames, 1), Some(ErrorCode::StreamClosed.code()));
+        assert_eq!(window_updates(&frames, 0), [10]);
+        assert_eq!(go_away(&frames), None);
+    }
+
+    #[test]
+    fn invalid_stream_ids() {
+        let frames = run(&[headers(2, true)], WAIT);
+        assert_eq!(go_away(&frames), Some(ErrorCode::ProtocolError.code()));
+
+        let frames = run(&[headers(3, true), headers(1, true)], WAIT);
+        assert_eq!(go_away(&frames), Some(ErrorCode::ProtocolError.code()));
+
+        let frames = run(&[data(0, 10)], WAIT);
+        assert_eq!(go_away(&frames), Some(ErrorCode::ProtocolError.code()));
+
+        let frames = run(&[data(5, 10)], WAIT);
+        assert_eq!(go_away(&frames), Some(ErrorCode::ProtocolError.code()));
+    }
+
+    #[test]
+    fn interrupted_header_block() {
+        let mut start = headers(1, true);
+        start.flags = flags::END_STREAM;
+        let ping = Frame::new(FrameType::Ping, 0, 0, vec![0; 8]);
+
+        let frames = run(&[start, ping], WAIT);
+        assert_eq!(go_away(&frames), Some(ErrorCode::ProtocolError.code()));
+    }
+
+    #[test]
+    fn too_many_streams() {
+        let mut input = Vec::new();
+        for id in (1..=MAX_CONCURRENT_STREAMS as u32 * 2 + 1).step_by(2) {
+            input.push(headers(id, true));
+        }
+
+        let frames = run(&input, WAIT);
+        assert_eq!(rst_stream(&frames, MAX_CONCURRENT_STREAMS as u32 * 2 - 1), None);
+        assert_eq!(rst_stream(&frames, MAX_CONCURRENT_STREAMS as u32 * 2 + 1), Some(ErrorCode::RefusedStream.code()));
+        assert_eq!(go_away(&frames), None);
+    }
+}
@@ -1,4 +1,9 @@
-use std::fmt;
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration
+};
 use http_core::{
     method::Method,
     request::RequestBuilder,
@@ -6,15 +11,79 @@ use http_core::{
     version::Version,
     error::{HttpError, HttpErrorKind, ValidHttpError}
 };
+use http_core::url::Protocol;
 use async_lib::{
-    io::{AsyncRead, Result},
-    net::TcpStream
+    io::{AsyncRead, AsyncWrite, AsyncBufReader, Result},
+    net::{SocketAddr, TcpStream},
+    time::{Sleep, sleep}
 };
 use types::*;
 
 pub mod types;
+mod body;
 mod http0;
 mod http1;
+pub mod http2;
+pub mod websocket;
+
+pub use http1::{keep_alive, persist_connection, error_response};
+
+/// Largest request head the server will read.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Bytes in the request line, not counting the CRLF.
+    pub max_request_line: usize,
+    /// Bytes in every header line together, counting each CRLF.
+    pub max_header_bytes: usize,
+    /// Number of header lines.
+    pub max_headers: usize,
+    /// Bytes in the request target.
+    pub max_uri: usize
+}
+
+impl Limits {
+    /// Size of the read buffer needed to hold the longest allowed line.
+    pub fn buffer_size(&self) -> usize {
+        self.max_request_line.max(self.max_header_bytes) + 2
+    }
+}
+
+/// Stream a connection is served over.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + 'static {
+    fn protocol(&self) -> Protocol;
+
+    /// Address the connection was accepted on.
+    fn local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Address of the client on the other end of the connection.
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+}
+
+impl Transport for TcpStream {
+    fn protocol(&self) -> Protocol {
+        Protocol::Http
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        TcpStream::local_addr(self).ok()
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+}
+
+/// TLS is left to the proxy in front of the socket.
+#[cfg(unix)]
+impl Transport for async_lib::net::UnixStream {
+    fn protocol(&self) -> Protocol {
+        Protocol::Http
+    }
+}
 
 pub enum BuildError {
     Http0GetMethodOnly,
@@ -28,7 +97,21 @@ pub enum BuildError {
     MissingVersion(Method, Uri),
     MissingUri,
     InvalidUri(UriError),
-    InvalidUrl(String)
+    InvalidUrl(String),
+    InvalidContentLength(String),
+    ConflictingLength,
+    InvalidHeader(String),
+    InvalidTransferEncoding(String),
+    UnsupportedTransferEncoding(String),
+    InvalidChunkSize(String),
+    MissingChunkEnd,
+    InvalidTrailer(String),
+    RequestLineTooLong(usize),
+    UriTooLong(usize),
+    HeadersTooLarge(usize),
+    TooManyHeaders(usize),
+    HeaderTimeout,
+    ExpectationFailed(String)
 }
 
 impl fmt::Display for BuildError {
@@ -45,7 +128,21 @@ impl fmt::Display for BuildError {
             Self::MissingVersion(_, _) => write!(f, "Unable to find the http version!"),
             Self::InvalidUri(e) => write!(f, "{}", e),
             Self::MissingUri => write!(f, "Uri missing from request!"),
-            Self::InvalidUrl(str) => write!(f, "{}", str)
+            Self::InvalidUrl(str) => write!(f, "{}", str),
+            Self::InvalidContentLength(str) => write!(f, "{} is not a valid content length!", str),
+            Self::ConflictingLength => write!(f, "Both Transfer-Encoding and Content-Length were sent!"),
+            Self::InvalidHeader(str) => write!(f, "Header {} is not allowed!", str),
+            Self::InvalidTransferEncoding(str) => write!(f, "Transfer encoding {} does not end with chunked!", str),
+            Self::UnsupportedTransferEncoding(str) => write!(f, "Transfer encoding {} is not supported!", str),
+            Self::InvalidChunkSize(str) => write!(f, "{} is not a valid chunk size!", str),
+            Self::MissingChunkEnd => write!(f, "Chunk data was not followed by CRLF!"),
+            Self::InvalidTrailer(str) => write!(f, "{} is not a valid trailer!", str),
+            Self::RequestLineTooLong(max) => write!(f, "Request line is longer then {} bytes!", max),
+            Self::UriTooLong(max) => write!(f, "Uri is longer then {} bytes!", max),
+            Self::HeadersTooLarge(max) => write!(f, "Headers are larger then {} bytes!", max),
+            Self::TooManyHeaders(max) => write!(f, "More then {} headers were sent!", max),
+            Self::HeaderTimeout => write!(f, "Timed out waiting for the request headers!"),
+            Self::ExpectationFailed(str) => write!(f, "Expectation {} is not supported!", str)
         }
     }
 }
@@ -55,6 +152,26 @@ impl Into<HttpError> for BuildError {
         match self {
             Self::IoError(_) => HttpErrorKind::InternalServerError.err(),
             Self::ParseError(_) => HttpErrorKind::InternalServerError.err(),
+            Self::UnsupportedTransferEncoding(str) => HttpError::new(
+                HttpErrorKind::NotImplemented,
+                &format!("Transfer encoding {} is not supported!", str)
+            ),
+            Self::RequestLineTooLong(_) | Self::UriTooLong(_) => HttpError::new(
+                HttpErrorKind::UriTooLong,
+                &self.to_string()
+            ),
+            Self::HeadersTooLarge(_) | Self::TooManyHeaders(_) => HttpError::new(
+                HttpErrorKind::RequestHeaderFieldsTooLarge,
+                &self.to_string()
+            ),
+            Self::HeaderTimeout => HttpError::new(
+                HttpErrorKind::RequestTimeout,
+                &self.to_string()
+            ),
+            Self::ExpectationFailed(_) => HttpError::new(
+                HttpErrorKind::ExpectationFailed,
+                &self.to_string()
+            ),
             bad_req => HttpError::new(
                 HttpErrorKind::BadRequest,
                 &bad_req.to_string()
@@ -63,19 +180,112 @@ impl Into<HttpError> for BuildError {
     }
 }
 
-pub async fn build_request<S: AsyncRead>(stream:&mut S, hostname:&str, port:u16) -> std::result::Result<RequestBuilder<S>, BuildError> {
-    match http1::build_request(stream, hostname, port).await {
-        Ok(builder) => Ok(builder),
+/// Requests are always built as `RequestBuilder<TcpStream>`, which is what the server's
+/// handlers expect, even when the connection is wrapped in TLS.
+pub async fn build_request<S: Transport>(reader:&mut AsyncBufReader<S>, hostname:&str, port:u16, limits:&Limits, body_timeout:Duration) -> std::result::Result<RequestBuilder<TcpStream>, BuildError> {
+    let mut builder = match http1::build_request(reader, hostname, port, limits, body_timeout).await {
+        Ok(builder) => builder,
         Err(e) => match e {
-            BuildError::MissingVersion(method, uri) =>  http0::build_request(port, method, uri),
-            err => Err(err)
+            BuildError::MissingVersion(method, uri) =>  http0::build_request(port, method, uri)?,
+            err => return Err(err)
         }
-    }
+    };
+
+    builder.url.protocol = reader.get_ref().protocol();
+    builder.local_addr = reader.get_ref().local_addr();
+    builder.peer_addr = reader.get_ref().peer_addr();
+    Ok(builder)
 }
 
-pub async fn write_response(stream:&mut TcpStream, response:Response, version:Version) -> Result<()> {
+/// Sets the headers that mark the end of the response body.
+/// 
+/// Returns false if the connection has to be closed to end the body.
+pub fn frame_response(response:&mut Response, version:Version) -> bool {
     match version.major {
-        0 => http0::write_response(response, stream).await,
-        _ => http1::write_response(response, version, stream).await
+        0 => false,
+        _ => http1::frame_response(response, version)
     }
-}
\ No newline at end of file
+}
+
+/// Keeps writing until all of the buffer has been written, a single write
+/// can stop part way through when the socket or TLS buffer is full.
+pub(crate) async fn write_all<S: AsyncWrite>(stream:&mut S, buffer:&[u8]) -> Result<()> {
+    let mut written = 0;
+
+    while written < buffer.len() {
+        written += match std::future::poll_fn(|cx|Pin::new(&mut *stream).poll_write(cx, &buffer[written..])).await? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            n => n
+        };
+    }
+
+    Ok(())
+}
+
+/// Writes the response, leaving off the body when answering a HEAD request.
+/// Returns the number of bytes of the body that were sent.
+pub async fn write_response<S: AsyncWrite>(stream:&mut S, response:Response, version:Version, head:bool) -> Result<u64> {
+    let sent = match version.major {
+        0 => http0::write_response(response, stream).await?,
+        _ => http1::write_response(response, version, head, stream).await?
+    };
+
+    stream.flush().await?;
+    Ok(sent)
+}
+
+/// Fails a write or flush that has not made progress within the timeout, so
+/// long running streamed responses are only stopped when the client stops reading.
+pub struct TimedWriter<'a, S: AsyncWrite> {
+    inner: &'a mut S,
+    timeout: Duration,
+    deadline: Option<Sleep>
+}
+
+impl<'a, S: AsyncWrite> TimedWriter<'a, S> {
+    pub fn new(inner:&'a mut S, timeout:Duration) -> Self {
+        Self {
+            inner, timeout,
+            deadline: None
+        }
+    }
+
+    fn check<T>(&mut self, cx:&mut Context<'_>, result:Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        match result {
+            Poll::Pending => {
+                let timeout = self.timeout;
+                let deadline = self.deadline.get_or_insert_with(||sleep(timeout));
+
+                if Pin::new(deadline).poll(cx).is_ready() {
+                    Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "Timed out writing the response!")))
+                } else {
+                    Poll::Pending
+                }
+            },
+            ready => {
+                self.deadline = None;
+                ready
+            }
+        }
+    }
+}
+
+impl<'a, S: AsyncWrite> AsyncWrite for TimedWriter<'a, S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_write(cx, buf);
+        this.check(cx, result)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_flush(cx);
+        this.check(cx, result)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_close(cx);
+        this.check(cx, result)
+    }
+}
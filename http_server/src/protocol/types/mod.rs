@@ -1,4 +1,4 @@
-use std::{collections::LinkedList, fmt };
+use std::{fmt, io};
 use async_lib::io::{AsyncRead, AsyncBufReader};
 pub use tokens::*;
 pub use version::parse_version;
@@ -8,21 +8,21 @@ mod tokens;
 mod version;
 mod uri;
 
-const CHUNK_SEPERATOR: &'static [u8] = b"\r\n";
+const CHUNK_SEPERATOR: &[u8] = b"\r\n";
 
 #[derive(Debug)]
 pub enum ParseStreamError {
     ReadError(std::io::Error),
-    TryOffset,
-    ParseError(usize)
+    ParseError(usize),
+    LineTooLong(usize)
 }
 
 impl ParseStreamError {
     fn code(&self) -> u8 {
         match self {
             Self::ReadError(_) => 0,
-            Self::TryOffset => 1,
-            Self::ParseError(_) => 2
+            Self::ParseError(_) => 1,
+            Self::LineTooLong(_) => 2
         }
     }
 }
@@ -37,102 +37,93 @@ impl fmt::Display for ParseStreamError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::ReadError(e) => write!(f, "{}", e),
-            Self::TryOffset => write!(f, "Invalid character at start!"),
-            Self::ParseError(index) => write!(f, "Invalid character found at: {index}!")
+            Self::ParseError(index) => write!(f, "Invalid character found at: {index}!"),
+            Self::LineTooLong(size) => write!(f, "Line is longer then {size} bytes!")
         }
     }
 }
 
+/// Searches for the end of the line starting at index.
+/// 
+/// Returns the index of the seperator if it is found.
 fn next_chunk<'a>(buffer:&'a [u8], mut index:usize) -> Result<Option<usize>, ParseStreamError> {
-    let mut length = buffer.len();
+    let length = buffer.len();
 
-    if index >= length {
-        return Ok(None);
-    } else if buffer[index] > 127 {
-        return Err(ParseStreamError::TryOffset)
-    }
-
-    length -= 1;
-    
     while index < length {
-        let next = index+1;
-        if buffer[next] > 127 {
-            return Err(ParseStreamError::ParseError(next));
+        if buffer[index] > 127 {
+            return Err(ParseStreamError::ParseError(index));
 
-        } else if buffer[index..=next] == *CHUNK_SEPERATOR {
+        } else if buffer[index..].starts_with(CHUNK_SEPERATOR) {
             return Ok(Some(index));
         }
 
-        index = next;
+        index += 1;
     }
 
-    Ok(Some(index))
+    Ok(None)
 }
 
-fn next_chunk_wrapper(buffer: &[u8], index: usize) -> Result<Option<(usize, usize)>, ParseStreamError> {
-    match next_chunk(buffer, index) {
-        Ok(Some(next)) => Ok(Some((index, next))),
-        Ok(None) => Ok(None),
-        Err(e) => if e == ParseStreamError::TryOffset {
-            next_chunk_wrapper(buffer, index+1)
-        } else {
-            Err(e)
-        }
-    }
+/// Reads lines from the connection's buffered reader.
+/// 
+/// Only the bytes of each line are consumed, so anything following
+/// the head of the request is left in the reader.
+pub struct StreamParser<'r, S> where S: AsyncRead{
+    reader: &'r mut AsyncBufReader<S>
 }
 
-pub struct StreamParser<S> where S: AsyncRead{
-    reader: S,
-    buffer: LinkedList<Chunk>,
-    end: bool
-}
-
-impl<S> StreamParser<S>  where S: AsyncRead{
-    pub fn new(stream:S) -> Self {
-        Self {
-            reader: stream,
-            buffer: LinkedList::new(),
-            end: false
-        }
-    }
-
-    pub fn take_reader(self) -> S {
-        self.reader
+impl<'r, S> StreamParser<'r, S>  where S: AsyncRead{
+    pub fn new(reader:&'r mut AsyncBufReader<S>) -> Self {
+        Self { reader }
     }
 
-    pub async fn parse(&mut self) -> Result<Option<Chunk>, ParseStreamError> {
-        let next = self.buffer.pop_front();
-        if next.is_some() || self.end{
-            return Ok(next);
-        }
-        let mut reader = AsyncBufReader::new(&mut self.reader);
+    /// Reads the next line, which can not be longer then limit bytes.
+    pub async fn parse(&mut self, limit:usize) -> Result<Option<Chunk>, ParseStreamError> {
         let mut index: usize = 0;
-        let sep_len = CHUNK_SEPERATOR.len();
-        let peek_buffer = reader.fill_buf().await
-            .map_err(|e|ParseStreamError::ReadError(e))?;
 
-        if peek_buffer.len() == 0 {
-            return Ok(None);
-        }
+        loop {
+            let buffer = self.reader.buffer();
+            if let Some(end) = next_chunk(buffer, index)? {
+                if end > limit {
+                    return Err(ParseStreamError::LineTooLong(limit));
+                }
 
-        while let Some((start, end)) = next_chunk_wrapper(peek_buffer, index)? {
-            let slice = &peek_buffer[start..end];
-            if slice.len() == 0 {
-                self.end = true;
-                break;
+                let chunk = Chunk(Vec::from(&buffer[..end]));
+                self.reader.consume(end + CHUNK_SEPERATOR.len());
+                return Ok(Some(chunk));
             }
-            self.buffer.push_back(Chunk(Vec::from(slice)));
 
-            index = end + sep_len;
-        }
-        reader.consume(index);
+            //Seperator may be split between reads.
+            index = buffer.len().saturating_sub(1);
+            if index > limit {
+                return Err(ParseStreamError::LineTooLong(limit));
+            } else if buffer.len() >= self.reader.capacity() {
+                return Err(ParseStreamError::LineTooLong(buffer.len()));
+            }
 
-        Ok(self.buffer.pop_front())
+            let amt = self.reader.read_more().await
+                .map_err(|e|ParseStreamError::ReadError(e))?;
+
+            if amt == 0 {
+                return if self.reader.buffer().is_empty() {
+                    Ok(None)
+                } else {
+                    Err(ParseStreamError::ReadError(
+                        io::ErrorKind::UnexpectedEof.into()
+                    ))
+                };
+            }
+        }
     }
 }
 
 pub struct Chunk(Vec<u8>);
 
+impl From<Vec<u8>> for Chunk {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
 impl Tokenizer for Chunk {
     fn as_str<'a>(&'a self) -> &'a str {
         unsafe {
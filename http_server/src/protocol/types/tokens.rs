@@ -310,6 +310,17 @@ pub struct Text {
     size: usize
 }
 
+impl Text {
+    /// Text between the start and end byte index.
+    pub fn slice(&self, start:usize, end:usize) -> Text {
+        assert!(start <= end && end <= self.size, "Slice is outside of the text!");
+        Text {
+            ptr: self.ptr(start),
+            size: end - start
+        }
+    }
+}
+
 impl Tokenizer for Text {
     fn as_str<'a>(&'a self) -> &'a str {
         unsafe { std::str::from_raw_parts(self.ptr, self.size) }
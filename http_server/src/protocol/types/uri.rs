@@ -33,11 +33,12 @@
 ///             ";" | ":" | "&" | "=" | "+" | "$" | "," )
 /// 
 /// hostport    = host [: port ]
-/// host        = hostname | IPv4address
+/// host        = hostname | IPv4address | IP-literal
 /// hostname    = *( domainlabel "." ) toplabel [ "." ]
 /// domainlabel = alphanum | alphanum *( alphanum | "-" ) alphanum
 /// toplabel    = lpha | alpha *( alphanum | "-" ) alphanum
 /// IPv4address = 1*digit "." 1*digit "." 1*digit "." 1*digit
+/// IP-literal  = "[" IPv6address "]"   (RFC-3986 3.2.2)
 /// port        = *digit
 /// 
 /// path          = [ abs_path | opaque_part ]
@@ -83,7 +84,44 @@ impl fmt::Display for UriError {
 
 impl Authority {
     pub fn parse(value: &Text) -> Result<Authority, UriError> {
-        Self::parse_vec(value.tokenize().collect())
+        if value.as_str().contains('[') {
+            Self::parse_ip_literal(value)
+        } else {
+            Self::parse_vec(value.tokenize().collect())
+        }
+    }
+
+    /// The colons in an IPv6 address would be read as seperators, so the host
+    /// is found between the brackets instead.
+    fn parse_ip_literal(value: &Text) -> Result<Authority, UriError> {
+        let str = value.as_str();
+        let open = str.find('[').unwrap();
+        let close = match str.find(']') {
+            Some(index) if index > open => index,
+            _ => return Err(UriError::SyntaxError("Unclosed IP literal!"))
+        };
+
+        let user = match open {
+            0 => None,
+            _ if str[..open].ends_with('@') => Some(value.slice(0, open - 1)),
+            _ => return Err(UriError::SyntaxError("Invalid Authority Syntax!"))
+        };
+
+        let port = match &str[close+1..] {
+            "" => None,
+            rest => match rest.strip_prefix(':') {
+                Some(port) => match port.parse() {
+                    Ok(number) => Some(number),
+                    Err(_) => return Err(UriError::InvalidPort(value.slice(close + 2, str.len())))
+                },
+                None => return Err(UriError::SyntaxError("Invalid Authority Syntax!"))
+            }
+        };
+
+        Ok(Authority{
+            host: value.slice(open, close + 1),
+            port, user
+        })
     }
 
     fn parse_vec<'a>(value:Vec<Tokens>) -> Result<Authority, UriError> {
@@ -333,6 +371,7 @@ pub struct AbsUri {
 
 impl AbsUri {
     fn parse(value: &Text) -> Result<Self, UriError> {
+        let rest: Text;
         let mut it = value.tokenize();
 
         // [scheme] ":", "/" , ([authority],)? "/" ([abs_path],)? ("?", [query])
@@ -363,8 +402,23 @@ impl AbsUri {
             None => return Err(UriError::SyntaxError("Unexpected end when parsing authority or path!"))
         };
 
+        //IP literals are split off before the rest is tokenized, see Authority::parse_ip_literal.
+        let ip_literal = value.as_str().find("//")
+            .map(|start|(start + 2, value.as_str()[start+2..].find('/').map(|end|start + 2 + end)))
+            .filter(|(start, end)|value.as_str()[*start..end.unwrap_or(value.len())].contains('['));
+
+        let authority = if let Some((start, end)) = ip_literal {
+            let end = end.ok_or(UriError::SyntaxError("Unexpected end when parsing Authority!"))?;
+            let authority = Authority::parse(&value.slice(start, end))?;
+
+            rest = value.slice(end, value.len());
+            it = rest.tokenize();
+            next = it.next().unwrap();
+
+            Some(authority)
+
         //if authority
-        let authority = if next.is_text() {
+        } else if next.is_text() {
             let mut vec:Vec<Tokens> = Vec::with_capacity(5);
             vec.push(next.clone());
             
@@ -57,7 +57,12 @@ pub fn parse_version(text:&Text) -> Result<Version, ()> {
             None => return Err(())
         };
 
-        let minor = match version.at(0) {
+        match version.at(1) {
+            Some(b'.') => {},
+            _ => return Err(())
+        }
+
+        let minor = match version.at(2) {
             Some(c) => match (*c as char).to_digit(10) {
                 Some(d) => d as u8,
                 None => return Err(())
@@ -0,0 +1,229 @@
+/// WebSocket Frames
+/// 
+/// RFC-6455 5.2
+/// https://datatracker.ietf.org/doc/html/rfc6455#section-5.2
+/// 
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-------+-+-------------+-------------------------------+
+/// |F|R|R|R| opcode|M| Payload len |    Extended payload length    |
+/// |I|S|S|S|  (4)  |A|     (7)     |             (16/64)           |
+/// |N|V|V|V|       |S|             |   (if payload len==126/127)   |
+/// | |1|2|3|       |K|             |                               |
+/// +-+-+-+-+-------+-+-------------+ - - - - - - - - - - - - - - - +
+/// |     Extended payload length continued, if payload len == 127  |
+/// + - - - - - - - - - - - - - - - +-------------------------------+
+/// |                               |Masking-key, if MASK set to 1  |
+/// +-------------------------------+-------------------------------+
+/// | Masking-key (continued)       |          Payload Data         |
+/// +-------------------------------- - - - - - - - - - - - - - - - +
+/// ```
+/// 
+use std::fmt;
+
+/// Largest payload allowed in a control frame.
+pub const MAX_CONTROL_PAYLOAD: usize = 125;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong
+}
+
+impl OpCode {
+    pub fn from(value:u8) -> Option<Self> {
+        match value {
+            0x0 => Some(Self::Continuation),
+            0x1 => Some(Self::Text),
+            0x2 => Some(Self::Binary),
+            0x8 => Some(Self::Close),
+            0x9 => Some(Self::Ping),
+            0xA => Some(Self::Pong),
+            _ => None
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA
+        }
+    }
+
+    pub fn is_control(&self) -> bool {
+        self.code() & 0x8 != 0
+    }
+}
+
+/// RFC-6455 7.4.1
+#[allow(dead_code)]
+pub mod close_code {
+    pub const NORMAL: u16 = 1000;
+    pub const GOING_AWAY: u16 = 1001;
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    pub const UNSUPPORTED_DATA: u16 = 1003;
+    pub const NO_STATUS: u16 = 1005;
+    pub const INVALID_DATA: u16 = 1007;
+    pub const POLICY_VIOLATION: u16 = 1008;
+    pub const MESSAGE_TOO_BIG: u16 = 1009;
+    pub const INTERNAL_ERROR: u16 = 1011;
+
+    /// Codes that are allowed to be sent in a close frame.
+    /// 
+    /// RFC-6455 7.4.2
+    pub fn is_valid(code:u16) -> bool {
+        matches!(code, 1000..=1003 | 1007..=1011 | 3000..=4999)
+    }
+}
+
+/// Reason the connection has to be failed.
+/// 
+/// RFC-6455 7.1.7
+#[derive(Debug)]
+pub struct FrameError {
+    pub code: u16,
+    pub reason: &'static str
+}
+
+impl FrameError {
+    pub fn new(code:u16, reason:&'static str) -> Self {
+        Self { code, reason }
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.reason, self.code)
+    }
+}
+
+/// First two bytes of a frame.
+pub struct FrameHeader {
+    pub fin: bool,
+    pub opcode: OpCode,
+    pub masked: bool,
+    /// Payload length, or 126/127 if an extended length follows.
+    pub length: u8
+}
+
+impl FrameHeader {
+    pub fn parse(buffer:[u8; 2]) -> Result<Self, FrameError> {
+        //No extensions are negotiated, so the reserved bits must be 0.
+        if buffer[0] & 0x70 != 0 {
+            return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Reserved bits are set!"));
+        }
+
+        let opcode = OpCode::from(buffer[0] & 0x0F)
+            .ok_or(FrameError::new(close_code::PROTOCOL_ERROR, "Unknown opcode!"))?;
+        let fin = buffer[0] & 0x80 != 0;
+        let length = buffer[1] & 0x7F;
+
+        //RFC-6455 5.5
+        if opcode.is_control() && (!fin || length as usize > MAX_CONTROL_PAYLOAD) {
+            return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Invalid control frame!"));
+        }
+
+        Ok(Self {
+            fin, opcode, length,
+            masked: buffer[1] & 0x80 != 0
+        })
+    }
+
+    /// Bytes of extended payload length that follow the header.
+    pub fn extended_length(&self) -> usize {
+        match self.length {
+            126 => 2,
+            127 => 8,
+            _ => 0
+        }
+    }
+}
+
+/// Frames sent by the server are never masked.
+pub fn write_frame(output:&mut Vec<u8>, fin:bool, opcode:OpCode, payload:&[u8]) {
+    output.push(if fin { 0x80 } else { 0 } | opcode.code());
+
+    let length = payload.len();
+    if length < 126 {
+        output.push(length as u8);
+    } else if length <= u16::MAX as usize {
+        output.push(126);
+        output.extend_from_slice(&(length as u16).to_be_bytes());
+    } else {
+        output.push(127);
+        output.extend_from_slice(&(length as u64).to_be_bytes());
+    }
+
+    output.extend_from_slice(payload);
+}
+
+/// RFC-6455 5.3
+pub fn unmask(payload:&mut [u8], key:[u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= key[i % 4];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_header() {
+        let header = FrameHeader::parse([0x81, 0x85]).unwrap();
+        assert!(header.fin && header.masked);
+        assert_eq!(header.opcode, OpCode::Text);
+        assert_eq!(header.length, 5);
+        assert_eq!(header.extended_length(), 0);
+
+        let header = FrameHeader::parse([0x02, 0xFE]).unwrap();
+        assert!(!header.fin);
+        assert_eq!(header.opcode, OpCode::Binary);
+        assert_eq!(header.extended_length(), 2);
+        assert_eq!(FrameHeader::parse([0x80, 0x7F]).unwrap().extended_length(), 8);
+    }
+
+    #[test]
+    fn invalid_header() {
+        //Reserved bit, unknown opcode, fragmented and oversized control frames.
+        for buffer in [[0xC1, 0x80], [0x83, 0x80], [0x09, 0x80], [0x89, 0xFE]] {
+            assert_eq!(FrameHeader::parse(buffer).err().unwrap().code, close_code::PROTOCOL_ERROR);
+        }
+
+        assert!(FrameHeader::parse([0x89, 0x80 | MAX_CONTROL_PAYLOAD as u8]).is_ok());
+    }
+
+    #[test]
+    fn write_lengths() {
+        let mut output = Vec::new();
+        write_frame(&mut output, true, OpCode::Text, b"Hello");
+        assert_eq!(output, b"\x81\x05Hello");
+
+        for (length, header) in [(125, vec![0x82, 125]), (126, vec![0x82, 126, 0, 126]), (65_536, vec![0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0])] {
+            let mut output = Vec::new();
+            write_frame(&mut output, true, OpCode::Binary, &vec![0; length]);
+            assert_eq!(output[..header.len()], header);
+            assert_eq!(output.len(), header.len() + length);
+        }
+    }
+
+    /// RFC-6455 5.7
+    #[test]
+    fn mask() {
+        let mut payload = vec![0x7f, 0x9f, 0x4d, 0x51, 0x58];
+        unmask(&mut payload, [0x37, 0xfa, 0x21, 0x3d]);
+        assert_eq!(payload, b"Hello");
+
+        unmask(&mut payload, [0x37, 0xfa, 0x21, 0x3d]);
+        assert_eq!(payload, [0x7f, 0x9f, 0x4d, 0x51, 0x58]);
+    }
+}
@@ -0,0 +1,506 @@
+/// The WebSocket Protocol
+///
+/// RFC-6455
+/// https://datatracker.ietf.org/doc/html/rfc6455
+///
+/// A handler accepts the handshake with `accept`, which returns the 101 response
+/// and runs the given function with the socket once the response has been sent.
+/// Only http/1.1 connections can be upgraded.
+///
+use std::{io, pin::Pin};
+use async_lib::io::{AsyncBufReader, AsyncWrite};
+use http_core::{
+    error::{HttpError, HttpErrorKind},
+    request::Request,
+    response::Response,
+    result::Result,
+    status::HttpStatus,
+    method::Method,
+    upgrade::Upgraded
+};
+use crate::shutdown::wait_for_request;
+use frame::{FrameHeader, FrameError, OpCode, write_frame, unmask};
+
+mod frame;
+mod handshake;
+
+pub use frame::close_code;
+
+/// Default largest message that will be received, including all of its fragments.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Messages longer then this are sent in fragments.
+const MAX_FRAME_SIZE: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(u16, String)
+}
+
+/// Checks if any of the comma separated tokens in the header match the value.
+fn has_token<P>(req:&Request<P>, name:&str, value:&str) -> bool {
+    req.headers().get(name)
+        .and_then(|header|header.ref_str().ok())
+        .map(|str|str.split(',').any(|token|token.trim().eq_ignore_ascii_case(value)))
+        .unwrap_or(false)
+}
+
+/// Accepts the opening handshake, `handler` is run with the socket after the
+/// 101 Switching Protocols response is sent.
+///
+/// RFC-6455 4.2
+pub fn accept<P, F, Fut>(req:&Request<P>, handler:F) -> Result<Response>
+    where F: FnOnce(WebSocket) -> Fut + 'static, Fut: Future<Output = ()> + 'static {
+
+    let version = req.version();
+    if *req.method() != Method::GET || version.major != 1 || version.minor < 1
+        || !has_token(req, "Upgrade", "websocket") || !has_token(req, "Connection", "Upgrade") {
+        return Err(HttpError::new(HttpErrorKind::BadRequest, "Expected a WebSocket handshake!"));
+    }
+
+    let key = match req.headers().get("Sec-WebSocket-Key").and_then(|value|value.ref_str().ok()) {
+        Some(key) if !key.trim().is_empty() => key,
+        _ => return Err(HttpError::new(HttpErrorKind::BadRequest, "Missing Sec-WebSocket-Key!"))
+    };
+
+    //RFC-6455 4.4
+    if !has_token(req, "Sec-WebSocket-Version", "13") {
+        let mut resp = Response::new(HttpStatus::UpgradeRequired, None);
+        resp.headers.set("Sec-WebSocket-Version", "13");
+        return Ok(resp);
+    }
+
+    let mut resp = Response::new(HttpStatus::SwitchingProtocols, None);
+    resp.headers.set("Upgrade", "websocket");
+    resp.headers.set("Connection", "Upgrade");
+    resp.headers.set("Sec-WebSocket-Accept", handshake::accept_key(key).as_str());
+    resp.upgrade = Some(Box::new(move|io|Box::pin(handler(WebSocket::new(io)))));
+
+    Ok(resp)
+}
+
+enum RecvError {
+    Io(io::Error),
+    Fail(FrameError)
+}
+
+impl From<io::Error> for RecvError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<FrameError> for RecvError {
+    fn from(value: FrameError) -> Self {
+        Self::Fail(value)
+    }
+}
+
+pub struct WebSocket {
+    io: AsyncBufReader<Upgraded>,
+    max_message_size: usize,
+    /// Fragments of a message that is still being received.
+    partial: Option<(OpCode, Vec<u8>)>,
+    close_sent: bool,
+    closed: bool
+}
+
+impl WebSocket {
+    fn new(io:Upgraded) -> Self {
+        Self {
+            io: AsyncBufReader::new(io),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            partial: None,
+            close_sent: false,
+            closed: false
+        }
+    }
+
+    /// Messages larger then this close the connection with 1009 Message Too Big.
+    pub fn set_max_message_size(&mut self, size:usize) {
+        self.max_message_size = size;
+    }
+
+    async fn read_exact(&mut self, length:usize) -> io::Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(length);
+
+        while output.len() < length {
+            let buffer = self.io.fill_buf().await?;
+            if buffer.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            let amt = buffer.len().min(length - output.len());
+            output.extend_from_slice(&buffer[..amt]);
+            self.io.consume(amt);
+        }
+
+        Ok(output)
+    }
+
+    async fn write_all(&mut self, buffer:&[u8]) -> io::Result<()> {
+        let stream = self.io.get_mut();
+        let mut written = 0;
+
+        while written < buffer.len() {
+            written += match std::future::poll_fn(|cx|Pin::new(&mut *stream).poll_write(cx, &buffer[written..])).await? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                n => n
+            };
+        }
+
+        std::future::poll_fn(|cx|Pin::new(&mut *stream).poll_flush(cx)).await
+    }
+
+    /// Reads the next frame, where `received` is the size of the fragmented message so far.
+    async fn read_frame(&mut self, received:usize) -> std::result::Result<(FrameHeader, Vec<u8>), RecvError> {
+        let buffer = self.read_exact(2).await?;
+        let header = FrameHeader::parse([buffer[0], buffer[1]])?;
+
+        //RFC-6455 5.1
+        if !header.masked {
+            return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Client frames must be masked!").into());
+        }
+
+        let length = match header.extended_length() {
+            0 => header.length as u64,
+            size => self.read_exact(size).await?
+                .iter().fold(0u64, |length, byte|(length << 8) | *byte as u64)
+        };
+
+        //Control frames are held to their own 125 bytes and are not part of the message.
+        if !header.opcode.is_control() && length as u128 + received as u128 > self.max_message_size as u128 {
+            return Err(FrameError::new(close_code::MESSAGE_TOO_BIG, "Message is too big!").into());
+        }
+
+        let key = self.read_exact(4).await?;
+        let mut payload = self.read_exact(length as usize).await?;
+        unmask(&mut payload, [key[0], key[1], key[2], key[3]]);
+
+        Ok((header, payload))
+    }
+
+    async fn recv_inner(&mut self) -> std::result::Result<Option<Message>, RecvError> {
+        loop {
+            if !self.close_sent && !wait_for_request(&mut self.io).await {
+                self.send_close(close_code::GOING_AWAY, "Server is shutting down").await?;
+            }
+
+            let received = self.partial.as_ref().map(|(_, data)|data.len()).unwrap_or(0);
+            let (header, payload) = self.read_frame(received).await?;
+
+            match header.opcode {
+                OpCode::Ping => {
+                    if !self.close_sent {
+                        self.send_frame(true, OpCode::Pong, &payload).await?;
+                    }
+                    return Ok(Some(Message::Ping(payload)));
+                },
+                OpCode::Pong => return Ok(Some(Message::Pong(payload))),
+                OpCode::Close => {
+                    //RFC-6455 5.5.1
+                    let (code, reason) = match payload.len() {
+                        0 => (close_code::NO_STATUS, String::new()),
+                        1 => return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Invalid close frame!").into()),
+                        _ => {
+                            let code = u16::from_be_bytes([payload[0], payload[1]]);
+                            if !close_code::is_valid(code) {
+                                return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Invalid close code!").into());
+                            }
+
+                            match String::from_utf8(payload[2..].to_vec()) {
+                                Ok(reason) => (code, reason),
+                                Err(_) => return Err(FrameError::new(close_code::INVALID_DATA, "Close reason is not valid UTF-8!").into())
+                            }
+                        }
+                    };
+
+                    if !self.close_sent {
+                        let echo = if code == close_code::NO_STATUS { close_code::NORMAL } else { code };
+                        self.send_close(echo, "").await?;
+                    }
+
+                    self.shut_down().await;
+                    return Ok(Some(Message::Close(code, reason)));
+                },
+                //Control frames can arrive between the fragments of a message.
+                OpCode::Continuation => match self.partial.as_mut() {
+                    Some((_, data)) => data.extend(payload),
+                    None => return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Unexpected continuation frame!").into())
+                },
+                opcode => match self.partial {
+                    Some(_) => return Err(FrameError::new(close_code::PROTOCOL_ERROR, "Expected a continuation frame!").into()),
+                    None => self.partial = Some((opcode, payload))
+                }
+            }
+
+            if header.fin && let Some((opcode, data)) = self.partial.take() {
+                //Messages still arriving after a close frame was sent are ignored.
+                if self.close_sent {
+                    continue;
+                }
+
+                return match opcode {
+                    OpCode::Text => match String::from_utf8(data) {
+                        Ok(text) => Ok(Some(Message::Text(text))),
+                        Err(_) => Err(FrameError::new(close_code::INVALID_DATA, "Text message is not valid UTF-8!").into())
+                    },
+                    _ => Ok(Some(Message::Binary(data)))
+                };
+            }
+        }
+    }
+
+    /// Waits for the next message from the client.
+    ///
+    /// Pings are answered automatically, and a close frame from the client is
+    /// answered before being returned. Returns None once the connection is closed.
+    pub async fn recv(&mut self) -> io::Result<Option<Message>> {
+        if self.closed {
+            return Ok(None);
+        }
+
+        match self.recv_inner().await {
+            Ok(message) => Ok(message),
+            Err(RecvError::Io(e)) => {
+                self.closed = true;
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            },
+            Err(RecvError::Fail(e)) => {
+                //RFC-6455 7.1.7
+                if !self.close_sent {
+                    let _ = self.send_close(e.code, e.reason).await;
+                }
+                self.shut_down().await;
+
+                Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            }
+        }
+    }
+
+    async fn send_frame(&mut self, fin:bool, opcode:OpCode, payload:&[u8]) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(payload.len() + 10);
+        write_frame(&mut buffer, fin, opcode, payload);
+        self.write_all(&buffer).await
+    }
+
+    async fn send_close(&mut self, code:u16, reason:&str) -> io::Result<()> {
+        let mut payload = code.to_be_bytes().to_vec();
+
+        let mut end = reason.len().min(frame::MAX_CONTROL_PAYLOAD - 2);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        payload.extend_from_slice(&reason.as_bytes()[..end]);
+
+        self.close_sent = true;
+        self.send_frame(true, OpCode::Close, &payload).await
+    }
+
+    async fn shut_down(&mut self) {
+        self.closed = true;
+        let stream = self.io.get_mut();
+        let _ = std::future::poll_fn(|cx|Pin::new(&mut *stream).poll_close(cx)).await;
+    }
+
+    /// Sends the message, text and binary messages larger then 64KiB are sent in fragments.
+    pub async fn send(&mut self, message:Message) -> io::Result<()> {
+        if self.close_sent || self.closed {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "WebSocket is closing!"));
+        }
+
+        let (opcode, data) = match message {
+            Message::Text(text) => (OpCode::Text, text.into_bytes()),
+            Message::Binary(data) => (OpCode::Binary, data),
+            Message::Ping(data) | Message::Pong(data) if data.len() > frame::MAX_CONTROL_PAYLOAD => return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Control frame payload is larger then 125 bytes!"
+            )),
+            Message::Ping(data) => return self.send_frame(true, OpCode::Ping, &data).await,
+            Message::Pong(data) => return self.send_frame(true, OpCode::Pong, &data).await,
+            Message::Close(code, reason) => return self.close(code, &reason).await
+        };
+
+        let mut chunks = data.chunks(MAX_FRAME_SIZE).peekable();
+        let mut opcode = opcode;
+        loop {
+            let chunk = chunks.next().unwrap_or(&[]);
+            let fin = chunks.peek().is_none();
+            self.send_frame(fin, opcode, chunk).await?;
+
+            if fin {
+                return Ok(());
+            }
+            opcode = OpCode::Continuation;
+        }
+    }
+
+    /// Starts the closing handshake and waits for the client to answer it,
+    /// discarding any messages sent before then.
+    ///
+    /// RFC-6455 7.1.2
+    pub async fn close(&mut self, code:u16, reason:&str) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+
+        if !self.close_sent {
+            self.send_close(code, reason).await?;
+        }
+
+        loop {
+            match self.recv().await {
+                Ok(Some(Message::Close(_, _))) | Ok(None) => return Ok(()),
+                Ok(Some(_)) => {},
+                Err(e) => return Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, task::{Context, Poll, Waker}};
+    use async_lib::io::AsyncRead;
+    use super::*;
+
+    /// Connection that has nothing more to read, keeping what is written to it.
+    struct Client(Rc<RefCell<Vec<u8>>>);
+
+    impl AsyncRead for Client {
+        fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, _buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(0))
+        }
+    }
+
+    impl AsyncWrite for Client {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    const KEY: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+    /// Masked frame as a client would send it.
+    fn client_frame(fin:bool, opcode:OpCode, payload:&[u8]) -> Vec<u8> {
+        let mut masked = payload.to_vec();
+        unmask(&mut masked, KEY);
+
+        let mut frame = Vec::new();
+        write_frame(&mut frame, fin, opcode, &masked);
+
+        //Mask bit is set and the key goes between the length and the payload.
+        let start = frame.len() - masked.len();
+        frame[1] |= 0x80;
+        frame.splice(start..start, KEY);
+        frame
+    }
+
+    fn open(frames:&[Vec<u8>], max_message_size:usize) -> (WebSocket, Rc<RefCell<Vec<u8>>>) {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mut socket = WebSocket::new(Upgraded::new(frames.concat(), Client(output.clone())));
+        socket.set_max_message_size(max_message_size);
+        (socket, output)
+    }
+
+    fn recv(socket:&mut WebSocket) -> io::Result<Option<Message>> {
+        match std::pin::pin!(socket.recv()).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("Mock client should never be pending!")
+        }
+    }
+
+    /// Checks the server started with a close frame with the code.
+    fn sent_close(output:&[u8], code:u16) -> bool {
+        output.len() >= 4 && output[0] == 0x88 && output[2..4] == code.to_be_bytes()
+    }
+
+    #[test]
+    fn fragmented_message() {
+        let (mut socket, _) = open(&[
+            client_frame(false, OpCode::Text, b"Hel"),
+            client_frame(false, OpCode::Continuation, b"l"),
+            client_frame(true, OpCode::Continuation, b"o")
+        ], DEFAULT_MAX_MESSAGE_SIZE);
+
+        assert_eq!(recv(&mut socket).unwrap(), Some(Message::Text(String::from("Hello"))));
+        assert_eq!(recv(&mut socket).unwrap(), None);
+    }
+
+    #[test]
+    fn ping_between_fragments() {
+        let (mut socket, output) = open(&[
+            client_frame(false, OpCode::Binary, &[1; 8]),
+            client_frame(true, OpCode::Ping, b"ping!"),
+            client_frame(true, OpCode::Continuation, &[2; 2])
+        ], 10);
+
+        assert_eq!(recv(&mut socket).unwrap(), Some(Message::Ping(b"ping!".to_vec())));
+        assert_eq!(output.borrow().as_slice(), b"\x8A\x05ping!");
+
+        let mut data = vec![1; 8];
+        data.extend([2; 2]);
+        assert_eq!(recv(&mut socket).unwrap(), Some(Message::Binary(data)));
+    }
+
+    #[test]
+    fn message_too_big() {
+        let (mut socket, output) = open(&[
+            client_frame(false, OpCode::Binary, &[1; 8]),
+            client_frame(true, OpCode::Continuation, &[2; 3])
+        ], 10);
+
+        assert!(recv(&mut socket).is_err());
+        assert!(sent_close(&output.borrow(), close_code::MESSAGE_TOO_BIG));
+        assert_eq!(recv(&mut socket).unwrap(), None);
+
+        let (mut socket, output) = open(&[client_frame(true, OpCode::Text, &[b'a'; 11])], 10);
+        assert!(recv(&mut socket).is_err());
+        assert!(sent_close(&output.borrow(), close_code::MESSAGE_TOO_BIG));
+    }
+
+    #[test]
+    fn protocol_errors() {
+        let mut unmasked = Vec::new();
+        write_frame(&mut unmasked, true, OpCode::Text, b"hi");
+
+        for frames in [
+            vec![unmasked],
+            vec![client_frame(true, OpCode::Continuation, b"hi")],
+            vec![client_frame(false, OpCode::Text, b"a"), client_frame(true, OpCode::Text, b"b")],
+            vec![client_frame(false, OpCode::Ping, b"")]
+        ] {
+            let (mut socket, output) = open(&frames, DEFAULT_MAX_MESSAGE_SIZE);
+            assert!(recv(&mut socket).is_err());
+            assert!(sent_close(&output.borrow(), close_code::PROTOCOL_ERROR));
+        }
+    }
+
+    #[test]
+    fn close_handshake() {
+        let mut payload = close_code::GOING_AWAY.to_be_bytes().to_vec();
+        payload.extend(b"bye");
+        let (mut socket, output) = open(&[client_frame(true, OpCode::Close, &payload)], DEFAULT_MAX_MESSAGE_SIZE);
+
+        assert_eq!(recv(&mut socket).unwrap(), Some(Message::Close(close_code::GOING_AWAY, String::from("bye"))));
+        assert!(sent_close(&output.borrow(), close_code::GOING_AWAY));
+        assert_eq!(recv(&mut socket).unwrap(), None);
+    }
+}
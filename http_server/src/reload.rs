@@ -0,0 +1,114 @@
+/// Config Reload
+///
+/// The config file is read again on SIGHUP or the console reload command.
+/// Connections opened afterwards use the new settings, while those already open
+/// finish with the ones they started with. Settings that need the listeners or
+/// thread pool set up again are reported, and keep their value until restarted.
+///
+use std::{
+    any::Any,
+    io::{Error, ErrorKind, Result},
+    sync::{Arc, Mutex, RwLock}
+};
+use util::log::{self, LogWriter, info, warning};
+use crate::{
+    Server, ServerOpts, ListenOpts, TlsOpts, access_log, read_server_opts,
+    shutdown::set_max_connections
+};
+
+/// Config file the options were last read from.
+pub(crate) static CONFIG_FILE: Mutex<Option<String>> = Mutex::new(None);
+static CURRENT: RwLock<Option<Arc<dyn Any + Send + Sync>>> = RwLock::new(None);
+
+/// Settings new connections are handled with.
+pub(crate) fn current<S: Server>() -> S {
+    CURRENT.read().unwrap().as_ref()
+        .and_then(|server|server.downcast_ref::<S>())
+        .expect("Server has not been started!")
+        .clone()
+}
+
+/// Opens the logs before anything is changed, so the old settings stay in force if one fails.
+pub(crate) fn apply<S: Server>(server:S) -> Result<()> {
+    let logging = server.logging();
+    let log_writer = LogWriter::open(&logging.outputs, logging)?;
+    let access_writer = access_log::open(server.access_log(), logging)?;
+
+    log::set_writer(log_writer);
+    log::set_level(logging.level);
+    access_log::set_writer(access_writer);
+
+    set_max_connections(server.max_connections(), server.retry_after().is_some());
+    if let Some(limiter) = server.rate_limiter() {
+        limiter.set_limit(server.rate_limit_max(), server.rate_limit_window());
+    }
+
+    *CURRENT.write().unwrap() = Some(Arc::new(server));
+    Ok(())
+}
+
+/// Names of the settings that changed, but are only used when the server starts.
+fn needs_restart<S: Server>(old:&S, new:&S) -> Vec<&'static str> {
+    let checks = [
+        ("hostname", old.hostname() != new.hostname()),
+        ("port", old.port() != new.port()),
+        ("addresses", old.addresses() != new.addresses()),
+        ("socket", old.socket() != new.socket()),
+        ("tls_cert", old.tls_cert() != new.tls_cert()),
+        ("tls_key", old.tls_key() != new.tls_key()),
+        ("threads", old.threads() != new.threads()),
+        ("console", old.console() != new.console())
+    ];
+
+    checks.into_iter()
+        .filter(|(_, changed)|*changed)
+        .map(|(name, _)|name)
+        .collect()
+}
+
+/// Reads the command line and config file again, and applies the new settings.
+///
+/// Nothing is changed if the file is missing or has an invalid value in it, unknown
+/// keys are logged as warnings.
+pub fn reload_config<S: Server>() -> Result<()> {
+    let filename = CONFIG_FILE.lock().unwrap().clone()
+        .ok_or(Error::new(ErrorKind::NotFound, "No config file to reload!"))?;
+
+    if !std::fs::exists(&filename)? {
+        return Err(Error::new(ErrorKind::NotFound, format!("Config file {} not found!", filename)));
+    }
+
+    let (opts, warnings) = read_server_opts(Some(&filename))?;
+    for w in warnings {
+        warning!("{}: {}", filename, w);
+    }
+
+    let old = current::<S>();
+    for name in needs_restart(&old, &S::new(opts.clone())) {
+        warning!("{} changed, restart the server for it to take effect!", name);
+    }
+    if !old.router_limiters().is_empty() {
+        warning!("Router rate limits are not reloaded, restart the server for changes to them to take effect!");
+    }
+
+    //Kept as they are, so the server still describes what it is listening on.
+    apply(S::new(ServerOpts {
+        threads: Some(old.threads()),
+        console: Some(old.console()),
+        listen: ListenOpts {
+            hostname: Some(old.hostname().to_string()),
+            port: Some(old.port()),
+            addresses: Some(old.addresses().to_vec()),
+            socket: old.socket().map(String::from),
+            ..opts.listen
+        },
+        tls: TlsOpts {
+            cert: old.tls_cert().map(String::from),
+            key: old.tls_key().map(String::from)
+        },
+        ..opts
+    }))?;
+
+    info!("Reloaded config from {}", filename);
+    Ok(())
+}
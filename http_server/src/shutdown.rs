@@ -0,0 +1,305 @@
+/// Graceful Shutdown
+///
+/// Stops accepting new connections and closes idle ones, then lets the requests
+/// already being handled finish before the thread pool is stopped. Anything still
+/// open once the deadline has passed is dropped. Draining does the same without
+/// stopping the thread pool.
+///
+use std::{
+    collections::BTreeMap,
+    io,
+    pin::Pin,
+    sync::{
+        Condvar, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering}
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant}
+};
+use async_lib::{
+    executor::{self, spawn_task},
+    io::{AsyncRead, AsyncBufReader},
+    time::sleep
+};
+use util::log::{error, info, warning};
+use crate::{
+    Server, console,
+    events::{self, EventPayload, ServerEvent},
+    reload::{current, reload_config}
+};
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static DRAINING: AtomicBool = AtomicBool::new(false);
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+static HANGUPS: AtomicUsize = AtomicUsize::new(0);
+static ACCEPT_MAX: AtomicUsize = AtomicUsize::new(0);
+static REJECT_OVER: AtomicUsize = AtomicUsize::new(0);
+static WAITING: Mutex<BTreeMap<usize, Waker>> = Mutex::new(BTreeMap::new());
+static CLOSED: (Mutex<()>, Condvar) = (Mutex::new(()), Condvar::new());
+
+/// Write end of the pipe the signal handlers wake the signal thread with.
+#[cfg(unix)]
+static SIGNAL_PIPE: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(-1);
+
+/// How often open connections are checked while shutting down.
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[inline]
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Relaxed)
+}
+
+/// True once new connections are no longer accepted, either to drain the server or shut it down.
+#[inline]
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::Relaxed)
+}
+
+/// Number of connections currently open.
+#[inline]
+pub fn active_connections() -> usize {
+    CONNECTIONS.load(Ordering::Relaxed)
+}
+
+/// Connections over max are either sent a 503, or left waiting in the backlog
+/// when they are not rejected. Zero for no limit.
+pub(crate) fn set_max_connections(max:usize, reject:bool) {
+    let (accept_max, reject_over) = if reject { (0, max) } else { (max, 0) };
+
+    ACCEPT_MAX.store(accept_max, Ordering::Relaxed);
+    REJECT_OVER.store(reject_over, Ordering::Relaxed);
+}
+
+/// Connections the listener accepts before waiting, zero for no limit.
+#[inline]
+pub(crate) fn accept_max() -> usize {
+    ACCEPT_MAX.load(Ordering::Relaxed)
+}
+
+/// Blocks the listener until fewer than max connections are open, or the timeout has passed.
+pub(crate) fn wait_for_connection_slot(max:usize, timeout:Duration) {
+    let (lock, closed) = &CLOSED;
+    let guard = lock.lock().unwrap();
+
+    drop(closed.wait_timeout_while(guard, timeout, |_|active_connections() >= max).unwrap());
+}
+
+/// Stops accepting new connections and closes idle ones, the server keeps
+/// running so the requests already being handled can finish.
+///
+/// Returns false if the server was already draining.
+pub fn drain() -> bool {
+    if DRAINING.swap(true, Ordering::Relaxed) {
+        return false;
+    }
+
+    let waiting = std::mem::take(&mut *WAITING.lock().unwrap());
+    for waker in waiting.into_values() {
+        waker.wake();
+    }
+
+    true
+}
+
+/// Waits for every connection to close, false if the server started shutting down first.
+pub(crate) async fn drained() -> bool {
+    while CONNECTIONS.load(Ordering::Relaxed) > 0 && !is_shutting_down() {
+        sleep(CHECK_INTERVAL).await;
+    }
+
+    !is_shutting_down()
+}
+
+/// Starts a graceful shutdown, the thread pool is stopped once every connection
+/// is closed or the deadline has passed.
+pub fn shut_down_gracefully(deadline:Duration) {
+    if SHUTTING_DOWN.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    info!("Shutting down, waiting for open connections to close...");
+    let deadline = Instant::now() + deadline;
+    drain();
+
+    spawn_task(async move {
+        //A second signal skips waiting for connections to close.
+        while CONNECTIONS.load(Ordering::Relaxed) > 0 && Instant::now() < deadline
+                && SIGNALS.load(Ordering::Relaxed) < 2 {
+            sleep(CHECK_INTERVAL.min(deadline - Instant::now())).await;
+        }
+
+        let remaining = CONNECTIONS.load(Ordering::Relaxed);
+        if remaining > 0 {
+            warning!("Dropping {} connections after shutdown deadline!", remaining);
+        }
+
+        events::emit_now(ServerEvent::Close, EventPayload {
+            duration: Some(console::uptime()),
+            ..EventPayload::default()
+        }).await;
+
+        executor::shut_down();
+    });
+}
+
+/// Wakes the task waiting on it once the server starts draining or shutting down.
+pub(crate) struct ShutdownSignal(usize);
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn poll(&mut self, cx:&Context<'_>) -> Poll<()> {
+        WAITING.lock().unwrap().insert(self.0, cx.waker().clone());
+
+        //Checked after the waker is stored, so a drain starting in between is not missed.
+        if is_draining() {
+            WAITING.lock().unwrap().remove(&self.0);
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for ShutdownSignal {
+    fn drop(&mut self) {
+        WAITING.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Counts a connection as open until it is dropped.
+pub(crate) struct ConnectionGuard {
+    overloaded: bool
+}
+
+impl ConnectionGuard {
+    /// The connection is overloaded if opening it went over the limit set to be rejected.
+    pub fn new() -> Self {
+        let limit = REJECT_OVER.load(Ordering::Relaxed);
+        let count = CONNECTIONS.fetch_add(1, Ordering::Relaxed) + 1;
+        Self {
+            overloaded: limit > 0 && count > limit
+        }
+    }
+
+    pub fn is_overloaded(&self) -> bool {
+        self.overloaded
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+
+        //Taking the lock keeps the listener from missing it between checking and waiting.
+        let _lock = CLOSED.0.lock().unwrap();
+        CLOSED.1.notify_all();
+    }
+}
+
+/// Waits for the start of the next request on a kept alive connection.
+///
+/// Returns false if the server started draining before the client sent anything.
+pub(crate) async fn wait_for_request<S: AsyncRead>(reader:&mut AsyncBufReader<S>) -> bool {
+    let mut shutdown = ShutdownSignal::new();
+
+    std::future::poll_fn(|cx|{
+        if !reader.buffer().is_empty() {
+            Poll::Ready(true)
+        } else if shutdown.poll(cx).is_ready() {
+            Poll::Ready(false)
+        } else {
+            //Errors and closed connections are left for the request parser to report.
+            Pin::new(&mut *reader).poll_fill_buf(cx).map(|_|true)
+        }
+    }).await
+}
+
+/// Only async-signal-safe calls are allowed here, so the thread is woken with a
+/// write to the pipe. A full pipe already has a wake up waiting.
+#[cfg(unix)]
+fn wake_signal_thread() {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        unsafe{ libc::write(fd, [1u8].as_ptr() as *const libc::c_void, 1) };
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_:libc::c_int) {
+    SIGNALS.fetch_add(1, Ordering::Relaxed);
+    wake_signal_thread();
+}
+
+#[cfg(unix)]
+extern "C" fn on_hangup(_:libc::c_int) {
+    HANGUPS.fetch_add(1, Ordering::Relaxed);
+    wake_signal_thread();
+}
+
+/// Starts a graceful shutdown when SIGINT or SIGTERM is received, and reloads
+/// the config file on SIGHUP.
+///
+/// Signals are handled on their own thread, like the console, so reading the
+/// config file does not block the thread pool.
+pub(crate) fn listen_for_signals<S: Server>() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::{fd::IntoRawFd, unix::net::UnixStream};
+
+        let (reader, writer) = UnixStream::pair()?;
+        writer.set_nonblocking(true)?;
+        SIGNAL_PIPE.store(writer.into_raw_fd(), Ordering::Relaxed);
+
+        std::thread::Builder::new()
+            .name("signals".to_string())
+            .spawn(move||handle_signals::<S>(reader))?;
+
+        unsafe {
+            let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
+
+            let handler = on_hangup as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::signal(libc::SIGHUP, handler);
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits on the pipe until a signal handler writes to it, then checks the counts.
+#[cfg(unix)]
+fn handle_signals<S: Server>(mut reader:std::os::unix::net::UnixStream) {
+    use std::io::Read;
+
+    let mut reloads = 0;
+    let mut buffer = [0u8; 64];
+
+    while SIGNALS.load(Ordering::Relaxed) == 0 {
+        //Hangups received while reloading are handled by a single reload.
+        let hangups = HANGUPS.load(Ordering::Relaxed);
+        if hangups != reloads {
+            reloads = hangups;
+            if let Err(e) = reload_config::<S>() {
+                error!("Unable to reload config: {}", e);
+            }
+            continue;
+        }
+
+        match reader.read(&mut buffer) {
+            Ok(_) => {},
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => {
+                error!("Unable to wait for signals: {}", e);
+                return;
+            }
+        }
+    }
+
+    shut_down_gracefully(current::<S>().shutdown_timeout());
+}
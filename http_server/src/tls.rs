@@ -0,0 +1,211 @@
+/// TLS Connections
+///
+/// Wraps an accepted TcpStream in a rustls server connection so it can be read
+/// and written like any other stream.
+///
+use std::{
+    io::{self, Read, Write},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll}
+};
+use async_lib::{
+    io::{AsyncRead, AsyncWrite},
+    net::{SocketAddr, TcpStream}
+};
+use http_core::url::Protocol;
+use rustls::{
+    ServerConfig, ServerConnection,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject}
+};
+use crate::protocol::Transport;
+
+/// Loads the certificate chain and private key from PEM files.
+///
+/// Both HTTP/2 and HTTP/1.1 are offered with ALPN.
+pub fn load_config(cert:&str, key:&str) -> io::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|it|it.collect::<Result<Vec<_>, _>>())
+        .map_err(|e|io::Error::new(io::ErrorKind::InvalidInput, format!("Unable to read certificate {}: {}", cert, e)))?;
+
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e|io::Error::new(io::ErrorKind::InvalidInput, format!("Unable to read private key {}: {}", key, e)))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder|builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e|io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Lets rustls read and write the TcpStream from inside a poll function.
+struct SyncStream<'a, 'b> {
+    io: &'a mut TcpStream,
+    cx: &'a mut Context<'b>
+}
+
+impl<'a, 'b> Read for SyncStream<'a, 'b> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.io).poll_read(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+}
+
+impl<'a, 'b> Write for SyncStream<'a, 'b> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.io).poll_write(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match Pin::new(&mut *self.io).poll_flush(self.cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+}
+
+pub struct TlsStream {
+    io: TcpStream,
+    conn: ServerConnection
+}
+
+impl TlsStream {
+    pub fn new(io:TcpStream, config:Arc<ServerConfig>) -> io::Result<Self> {
+        let mut conn = ServerConnection::new(config)
+            .map_err(io::Error::other)?;
+
+        //Backpressure comes from waiting on the TcpStream in poll_write instead.
+        conn.set_buffer_limit(None);
+
+        Ok(Self { io, conn })
+    }
+
+    /// Sends any pending TLS records to the client.
+    fn poll_write_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.conn.wants_write() {
+            let mut io = SyncStream{ io: &mut self.io, cx };
+
+            match self.conn.write_tls(&mut io) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(_) => {},
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) => return Poll::Ready(Err(e))
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Reads TLS records from the client, returning false once the client has closed the connection.
+    fn poll_read_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        let mut io = SyncStream{ io: &mut self.io, cx };
+
+        match self.conn.read_tls(&mut io) {
+            Ok(0) => Poll::Ready(Ok(false)),
+            Ok(_) => match self.conn.process_new_packets() {
+                Ok(_) => Poll::Ready(Ok(true)),
+                Err(e) => {
+                    //Try to let the client know why the connection is closing.
+                    let _ = self.poll_write_tls(cx);
+                    Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e)))
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e))
+        }
+    }
+}
+
+impl AsyncRead for TlsStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            match this.conn.reader().read(buf) {
+                Ok(n) => return Poll::Ready(Ok(n)),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+                //Client closed the connection without a close_notify.
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Poll::Ready(Ok(0)),
+                Err(e) => return Poll::Ready(Err(e))
+            }
+
+            //Handshake messages have to be sent before more can be read.
+            match this.poll_write_tls(cx) {
+                Poll::Ready(Ok(())) => {},
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+
+            match this.poll_read_tls(cx) {
+                Poll::Ready(Ok(true)) => {},
+                Poll::Ready(Ok(false)) => return Poll::Ready(Ok(0)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+    }
+}
+
+impl AsyncWrite for TlsStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        //Wait for the last write to be sent before taking more.
+        match this.poll_write_tls(cx) {
+            Poll::Ready(Ok(())) => {},
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending
+        }
+
+        let written = match this.conn.writer().write(buf) {
+            Ok(n) => n,
+            Err(e) => return Poll::Ready(Err(e))
+        };
+
+        match this.poll_write_tls(cx) {
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            //Anything left is sent on the next write or flush.
+            _ => Poll::Ready(Ok(written))
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_write_tls(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_flush(cx),
+            other => other
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.conn.send_close_notify();
+
+        match this.poll_write_tls(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_close(cx),
+            other => other
+        }
+    }
+}
+
+impl Transport for TlsStream {
+    fn protocol(&self) -> Protocol {
+        Protocol::Https
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.io.local_addr().ok()
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.io.peer_addr().ok()
+    }
+}
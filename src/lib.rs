@@ -32,6 +32,14 @@ pub mod types {
     pub mod response {
         pub use http_core::response::Chunk;
     }
+
+    pub mod sse {
+        pub use http_core::sse::*;
+    }
+
+    pub mod rate_limit {
+        pub use http_core::rate_limit::*;
+    }
 }
 
 pub mod server {
@@ -41,6 +49,10 @@ pub mod server {
     pub use async_lib::executor;
 }
 
+pub mod log {
+    pub use util::log::*;
+}
+
 pub mod async_net {
     pub use async_lib::net::*;
 }
@@ -68,7 +80,3 @@ pub mod promise {
 pub mod html {
     pub use html::*;
 }
-
-/*pub(crate) fn log(req: &RequestBuilder<impl AsyncRead>, resp: &types::Response) {
-    println!("{:?} {:?}", req, resp);
-}*/
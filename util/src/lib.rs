@@ -1,3 +1,6 @@
 #![feature(str_from_raw_parts)]
+#![feature(thread_id_value)]
 
 pub mod json;
+pub mod log;
+pub mod toml;
@@ -0,0 +1,99 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    time::{Duration, Instant}
+};
+
+/// Log file that is moved aside once it gets too big or old.
+///
+/// Rotated files are numbered from newest to oldest, server.log.1 is the
+/// last one written before server.log.
+pub(crate) struct RotatingFile {
+    path: String,
+    file: File,
+    size: u64,
+    opened: Instant,
+    max_size: Option<u64>,
+    interval: Option<Duration>,
+    keep: usize
+}
+
+impl RotatingFile {
+    pub(crate) fn open(path:&str, max_size:Option<u64>, interval:Option<Duration>, keep:usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_string(),
+            file, size,
+            opened: Instant::now(),
+            max_size, interval, keep
+        })
+    }
+
+    fn rotated(&self, index:usize) -> String {
+        format!("{}.{}", self.path, index)
+    }
+
+    fn needs_rotation(&self, next:u64) -> bool {
+        let too_big = self.max_size.is_some_and(|max|self.size > 0 && self.size + next > max);
+        let too_old = self.interval.is_some_and(|interval|self.opened.elapsed() >= interval);
+
+        too_big || too_old
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            //The oldest is replaced by the one before it.
+            for index in (1..self.keep).rev() {
+                let from = self.rotated(index);
+                if fs::exists(&from)? {
+                    fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+
+    pub(crate) fn write(&mut self, line:&str) -> io::Result<()> {
+        if self.needs_rotation(line.len() as u64) {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rotate_by_size() {
+        let dir = std::env::temp_dir().join(format!("util-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.log").to_string_lossy().to_string();
+
+        let mut file = RotatingFile::open(&path, Some(10), None, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(file.rotated(1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(file.rotated(2)).unwrap(), "second\n");
+        assert!(!fs::exists(file.rotated(3)).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+/// Logging
+///
+/// Messages are written with critical!, error!, warning!, info! and debug!,
+/// which take the same arguments as println!. Each line is sent to every
+/// configured output with the time, level and thread it came from.
+///
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicU8, Ordering}
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+pub use crate::{critical, error, warning, info, debug};
+
+mod file;
+use file::RotatingFile;
+
+/// Default number of rotated files kept.
+pub const DEFAULT_KEEP: usize = 5;
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static WRITER: LazyLock<Mutex<LogWriter>> = LazyLock::new(||Mutex::new(LogWriter(vec![Sink::Stdout])));
+
+/// Importance of a message, every level up to the one set is written.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Critical = 0,
+    Error,
+    Warning,
+    Info,
+    Debug
+}
+
+impl Level {
+    const ALL: [Level; 5] = [Level::Critical, Level::Error, Level::Warning, Level::Info, Level::Debug];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Critical => "CRITICAL",
+            Self::Error => "ERROR",
+            Self::Warning => "WARNING",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG"
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(value:&str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        Self::ALL.into_iter()
+            .find(|level|level.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(||format!("Unknown log level \"{}\"!", value))
+    }
+}
+
+/// Where messages are written.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Output {
+    Stdout,
+    Stderr,
+    File(String)
+}
+
+/// "stdout" and "stderr" are the standard streams, anything else is a file path.
+impl From<&str> for Output {
+    fn from(value:&str) -> Self {
+        match value.trim() {
+            "stdout" => Self::Stdout,
+            "stderr" => Self::Stderr,
+            path => Self::File(path.to_string())
+        }
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stdout => f.write_str("stdout"),
+            Self::Stderr => f.write_str("stderr"),
+            Self::File(path) => f.write_str(path)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    pub level: Level,
+    pub outputs: Vec<Output>,
+    /// Bytes a file can grow to before it is rotated.
+    pub max_size: Option<u64>,
+    /// Time a file is written to before it is rotated.
+    pub rotate_interval: Option<Duration>,
+    /// Number of rotated files kept, older ones are deleted.
+    pub keep: usize
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::Info,
+            outputs: vec![Output::Stdout],
+            max_size: None,
+            rotate_interval: None,
+            keep: DEFAULT_KEEP
+        }
+    }
+}
+
+enum Sink {
+    Stdout,
+    Stderr,
+    File(RotatingFile)
+}
+
+impl Sink {
+    fn write(&mut self, line:&str) -> io::Result<()> {
+        match self {
+            Self::Stdout => io::stdout().lock().write_all(line.as_bytes()),
+            Self::Stderr => io::stderr().lock().write_all(line.as_bytes()),
+            Self::File(file) => file.write(line)
+        }
+    }
+}
+
+/// Writes whole lines to each output, rotating files using the settings of the config.
+pub struct LogWriter(Vec<Sink>);
+
+impl LogWriter {
+    pub fn open(outputs:&[Output], config:&LogConfig) -> io::Result<Self> {
+        outputs.iter().map(|output|Ok(match output {
+            Output::Stdout => Sink::Stdout,
+            Output::Stderr => Sink::Stderr,
+            Output::File(path) => Sink::File(RotatingFile::open(path, config.max_size, config.rotate_interval, config.keep)?)
+        })).collect::<io::Result<Vec<_>>>().map(Self)
+    }
+
+    /// The line should end with a line break.
+    pub fn write_line(&mut self, line:&str) {
+        for sink in self.0.iter_mut() {
+            //Nowhere left to report it, so the line is sent to stderr instead.
+            if let Err(e) = sink.write(line) {
+                eprint!("Unable to write log: {}\n{}", e, line);
+            }
+        }
+    }
+}
+
+/// Replaces the outputs and level, files are opened before anything is changed.
+pub fn configure(config:&LogConfig) -> io::Result<()> {
+    let writer = LogWriter::open(&config.outputs, config)?;
+
+    set_writer(writer);
+    set_level(config.level);
+    Ok(())
+}
+
+/// Replaces the outputs with ones that were already opened.
+pub fn set_writer(writer:LogWriter) {
+    *WRITER.lock().unwrap() = writer;
+}
+
+pub fn set_level(level:Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    Level::ALL[LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+pub fn enabled(level:Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Used by the logging macros.
+pub fn write(level:Level, message:fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+
+    let thread = std::thread::current();
+    let line = format!(
+        "{} {:<8} [{}:{}] {}\n",
+        UtcTime::from(SystemTime::now()),
+        level.as_str(),
+        thread.name().unwrap_or("-"),
+        thread.id().as_u64(),
+        message
+    );
+
+    WRITER.lock().unwrap().write_line(&line);
+}
+
+/// Calendar date and time in UTC.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UtcTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millis: u32
+}
+
+impl From<SystemTime> for UtcTime {
+    fn from(time:SystemTime) -> Self {
+        let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = since.as_secs();
+        let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+        let seconds = (seconds % 86400) as u32;
+
+        Self {
+            year, month, day,
+            hour: seconds / 3600,
+            minute: (seconds / 60) % 60,
+            second: seconds % 60,
+            millis: since.subsec_millis()
+        }
+    }
+}
+
+/// RFC-3339, such as 2024-01-31T09:05:00.123Z.
+impl fmt::Display for UtcTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            self.year, self.month, self.day,
+            self.hour, self.minute, self.second, self.millis
+        )
+    }
+}
+
+/// Days since 1970-01-01 to a date.
+///
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days:i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[macro_export]
+macro_rules! critical {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Critical, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Error, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Warning, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Info, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Debug, format_args!($($arg)*)) };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_level() {
+        assert_eq!("warning".parse::<Level>(), Ok(Level::Warning));
+        assert_eq!(" DEBUG ".parse::<Level>(), Ok(Level::Debug));
+        assert!("verbose".parse::<Level>().is_err());
+        assert!(Level::Critical < Level::Debug);
+    }
+
+    #[test]
+    fn format_timestamp() {
+        assert_eq!(UtcTime::from(UNIX_EPOCH).to_string(), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            UtcTime::from(UNIX_EPOCH + Duration::from_millis(1709210096789)).to_string(),
+            "2024-02-29T12:34:56.789Z"
+        );
+    }
+
+    #[test]
+    fn parse_output() {
+        assert_eq!(Output::from("stdout"), Output::Stdout);
+        assert_eq!(Output::from("stderr"), Output::Stderr);
+        assert_eq!(Output::from("logs/server.log"), Output::File("logs/server.log".to_string()));
+    }
+}
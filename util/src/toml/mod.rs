@@ -0,0 +1,247 @@
+/// TOML
+///
+/// Parses the part of TOML used by config files: tables, arrays of tables,
+/// dotted keys, strings, integers, floats, booleans, arrays and inline tables.
+/// Dates and multi-line strings are reported as errors.
+///
+/// https://toml.io/en/v1.0.0
+///
+use std::fmt;
+
+mod parser;
+
+/// Line and column in the file, both starting at one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TomlError {
+    pub message: String,
+    pub position: Position
+}
+
+impl TomlError {
+    pub fn new<M:ToString>(message:M, position:Position) -> Self {
+        Self {
+            message: message.to_string(),
+            position
+        }
+    }
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for TomlError {}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TomlValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<TomlItem>),
+    Table(TomlTable)
+}
+
+impl TomlValue {
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Boolean(_) => "boolean",
+            Self::Array(_) => "array",
+            Self::Table(_) => "table"
+        }
+    }
+}
+
+/// Value with where it starts in the file.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TomlItem {
+    pub value: TomlValue,
+    pub position: Position
+}
+
+impl TomlItem {
+    fn expected<T>(&self, expected:&str) -> Result<T, TomlError> {
+        Err(TomlError::new(
+            format!("Expected {}, found {}", expected, self.value.type_of()),
+            self.position
+        ))
+    }
+
+    pub fn as_str(&self) -> Result<&str, TomlError> {
+        match &self.value {
+            TomlValue::String(value) => Ok(value),
+            _ => self.expected("a string")
+        }
+    }
+
+    pub fn as_integer(&self) -> Result<i64, TomlError> {
+        match self.value {
+            TomlValue::Integer(value) => Ok(value),
+            _ => self.expected("an integer")
+        }
+    }
+
+    /// Integers are converted, as 1 and 1.0 are the same number.
+    pub fn as_float(&self) -> Result<f64, TomlError> {
+        match self.value {
+            TomlValue::Float(value) => Ok(value),
+            TomlValue::Integer(value) => Ok(value as f64),
+            _ => self.expected("a number")
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, TomlError> {
+        match self.value {
+            TomlValue::Boolean(value) => Ok(value),
+            _ => self.expected("a boolean")
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&[TomlItem], TomlError> {
+        match &self.value {
+            TomlValue::Array(list) => Ok(list),
+            _ => self.expected("an array")
+        }
+    }
+
+    pub fn as_table(&self) -> Result<&TomlTable, TomlError> {
+        match &self.value {
+            TomlValue::Table(table) => Ok(table),
+            _ => self.expected("a table")
+        }
+    }
+}
+
+/// Keys are kept in the order they were written, along with where each key is.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TomlTable(Vec<(String, Position, TomlItem)>);
+
+impl TomlTable {
+    pub fn get(&self, key:&str) -> Option<&TomlItem> {
+        self.0.iter()
+            .find(|(name, _, _)|name == key)
+            .map(|(_, _, item)|item)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Position, &TomlItem)> {
+        self.0.iter().map(|(name, position, item)|(name.as_str(), *position, item))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn get_mut(&mut self, key:&str) -> Option<&mut TomlItem> {
+        self.0.iter_mut()
+            .find(|(name, _, _)|name == key)
+            .map(|(_, _, item)|item)
+    }
+
+    fn insert(&mut self, key:String, position:Position, item:TomlItem) {
+        self.0.push((key, position, item));
+    }
+}
+
+pub fn parse(text:&str) -> Result<TomlTable, TomlError> {
+    parser::Parser::new(text).parse()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(line:usize, column:usize) -> Position {
+        Position { line, column }
+    }
+
+    #[test]
+    fn parse_values() {
+        let table = parse(concat!(
+            "# comment\n",
+            "name = \"a \\\"quoted\\\" \\u00e9\" # trailing\n",
+            "path = 'C:\\dir'\n",
+            "count = 1_000\n",
+            "negative = -12\n",
+            "hex = 0xff\n",
+            "ratio = 2.5e1\n",
+            "enabled = true\n",
+            "list = [\n  1,\n  2, # two\n]\n",
+            "inline = { a = 1, b.c = \"x\" }\n"
+        )).unwrap();
+
+        assert_eq!(table.get("name").unwrap().as_str(), Ok("a \"quoted\" é"));
+        assert_eq!(table.get("path").unwrap().as_str(), Ok("C:\\dir"));
+        assert_eq!(table.get("count").unwrap().as_integer(), Ok(1000));
+        assert_eq!(table.get("negative").unwrap().as_integer(), Ok(-12));
+        assert_eq!(table.get("hex").unwrap().as_integer(), Ok(255));
+        assert_eq!(table.get("ratio").unwrap().as_float(), Ok(25.0));
+        assert_eq!(table.get("enabled").unwrap().as_bool(), Ok(true));
+        assert_eq!(table.get("list").unwrap().as_array().unwrap().len(), 2);
+
+        let inline = table.get("inline").unwrap().as_table().unwrap();
+        assert_eq!(inline.get("a").unwrap().as_integer(), Ok(1));
+        assert_eq!(inline.get("b").unwrap().as_table().unwrap().get("c").unwrap().as_str(), Ok("x"));
+    }
+
+    #[test]
+    fn parse_tables() {
+        let table = parse(concat!(
+            "top = 1\n",
+            "[log]\n",
+            "level = \"info\"\n",
+            "[log.access]\n",
+            "format = \"json\"\n",
+            "[[static]]\n",
+            "dir = \"a\"\n",
+            "[[static]]\n",
+            "dir = \"b\"\n"
+        )).unwrap();
+
+        let keys: Vec<&str> = table.iter().map(|(key, _, _)|key).collect();
+        assert_eq!(keys, ["top", "log", "static"]);
+
+        let log = table.get("log").unwrap().as_table().unwrap();
+        assert_eq!(log.get("level").unwrap().as_str(), Ok("info"));
+        assert_eq!(log.get("access").unwrap().as_table().unwrap().get("format").unwrap().as_str(), Ok("json"));
+
+        let dirs = table.get("static").unwrap().as_array().unwrap();
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(dirs[1].as_table().unwrap().get("dir").unwrap().as_str(), Ok("b"));
+        assert_eq!(dirs[1].position, at(8, 1));
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(parse("a = 1\nb = \n").unwrap_err().position, at(2, 5));
+        assert_eq!(parse("a = 1\na = 2").unwrap_err(), TomlError::new("Duplicate key \"a\"", at(2, 1)));
+        assert_eq!(parse("[x]\n[x]").unwrap_err().position, at(2, 1));
+        assert_eq!(parse("a = \"open").unwrap_err().position, at(1, 10));
+        assert_eq!(parse("a = 1 2").unwrap_err().position, at(1, 7));
+        assert_eq!(parse("date = 1979-05-27").unwrap_err().position, at(1, 8));
+        assert_eq!(parse("  port = 5000\n  host = localhost").unwrap_err().position, at(2, 10));
+
+        let item = &parse("port = \"80\"").unwrap().get("port").cloned().unwrap();
+        assert_eq!(item.as_integer().unwrap_err().to_string(), "Expected an integer, found string at line 1, column 8");
+    }
+}
@@ -0,0 +1,490 @@
+use super::{Position, TomlError, TomlItem, TomlTable, TomlValue};
+
+type Result<T> = std::result::Result<T, TomlError>;
+
+fn is_bare_key(c:char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Characters that can be part of a number, date or keyword.
+fn is_word(c:char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':')
+}
+
+pub(crate) struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+    /// Tables created by a header, which can not be given a second one.
+    defined: Vec<Vec<String>>
+}
+
+impl Parser {
+    pub(crate) fn new(text:&str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            index: 0,
+            line: 1,
+            column: 1,
+            defined: Vec::new()
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column
+        }
+    }
+
+    fn error<T, M:ToString>(&self, message:M) -> Result<T> {
+        Err(TomlError::new(message, self.position()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn peek_at(&self, offset:usize) -> Option<char> {
+        self.chars.get(self.index + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn expect(&mut self, expected:char) -> Result<()> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+                Ok(())
+            },
+            Some(c) => self.error(format!("Expected '{}', found '{}'", expected, c)),
+            None => self.error(format!("Expected '{}', found the end of the file", expected))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t') = self.peek() {
+            self.next();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.next();
+            }
+        }
+    }
+
+    /// Whitespace, comments and line breaks, which can be between array values.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+
+            match self.peek() {
+                Some('\n') => { self.next(); },
+                Some('\r') if self.peek_at(1) == Some('\n') => { self.next(); },
+                _ => break
+            }
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        self.skip_comment();
+
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            },
+            Some('\r') if self.peek_at(1) == Some('\n') => {
+                self.next();
+                self.next();
+                Ok(())
+            },
+            Some(c) => self.error(format!("Expected the end of the line, found '{}'", c))
+        }
+    }
+
+    pub(crate) fn parse(mut self) -> Result<TomlTable> {
+        let mut root = TomlTable::default();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_blank();
+
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    current = self.parse_header(&mut root)?;
+                },
+                Some(_) => {
+                    let table = table_at(&mut root, &current);
+                    self.parse_key_value(table)?;
+                }
+            }
+
+            self.expect_line_end()?;
+        }
+    }
+
+    /// [table] or [[array of tables]], returning the path to the table.
+    fn parse_header(&mut self, root:&mut TomlTable) -> Result<Vec<String>> {
+        let position = self.position();
+        self.expect('[')?;
+        let array = self.peek() == Some('[');
+        if array {
+            self.next();
+        }
+
+        self.skip_whitespace();
+        let keys = self.parse_keys()?;
+        self.skip_whitespace();
+        self.expect(']')?;
+        if array {
+            self.expect(']')?;
+        }
+
+        let path: Vec<String> = keys.iter().map(|(key, _)|key.clone()).collect();
+        let (last, parents) = keys.split_last().unwrap();
+        let mut table = root;
+        for (key, position) in parents {
+            table = child_table(table, key, *position)?;
+        }
+
+        let (key, _) = last;
+        if array {
+            let item = TomlItem {
+                value: TomlValue::Table(TomlTable::default()),
+                position
+            };
+
+            match table.get_mut(key) {
+                Some(TomlItem{ value: TomlValue::Array(list), .. }) => list.push(item),
+                Some(existing) => return Err(TomlError::new(
+                    format!("Key \"{}\" is already defined as a {}", key, existing.value.type_of()),
+                    position
+                )),
+                None => table.insert(key.clone(), position, TomlItem {
+                    value: TomlValue::Array(vec![item]),
+                    position
+                })
+            }
+        } else {
+            if self.defined.contains(&path) {
+                return Err(TomlError::new(format!("Table \"{}\" is already defined", path.join(".")), position));
+            }
+
+            child_table(table, key, position)?;
+            self.defined.push(path.clone());
+        }
+
+        Ok(path)
+    }
+
+    /// One or more keys seperated by dots.
+    fn parse_keys(&mut self) -> Result<Vec<(String, Position)>> {
+        let mut keys = Vec::new();
+
+        loop {
+            let position = self.position();
+            let key = match self.peek() {
+                Some('"') => self.parse_basic_string()?,
+                Some('\'') => self.parse_literal_string()?,
+                Some(c) if is_bare_key(c) => {
+                    let mut key = String::new();
+                    while let Some(c) = self.peek() && is_bare_key(c) {
+                        key.push(c);
+                        self.next();
+                    }
+                    key
+                },
+                Some(c) => return self.error(format!("Expected a key, found '{}'", c)),
+                None => return self.error("Expected a key, found the end of the file")
+            };
+            keys.push((key, position));
+
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(keys);
+            }
+
+            self.next();
+            self.skip_whitespace();
+        }
+    }
+
+    fn parse_key_value(&mut self, table:&mut TomlTable) -> Result<()> {
+        let keys = self.parse_keys()?;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        let item = self.parse_value()?;
+
+        let ((key, position), parents) = keys.split_last().unwrap();
+        let mut table = table;
+        for (key, position) in parents {
+            table = child_table(table, key, *position)?;
+        }
+
+        if table.get(key).is_some() {
+            return Err(TomlError::new(format!("Duplicate key \"{}\"", key), *position));
+        }
+
+        table.insert(key.clone(), *position, item);
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<TomlItem> {
+        let position = self.position();
+
+        let value = match self.peek() {
+            Some('"') => TomlValue::String(self.parse_basic_string()?),
+            Some('\'') => TomlValue::String(self.parse_literal_string()?),
+            Some('[') => TomlValue::Array(self.parse_array()?),
+            Some('{') => TomlValue::Table(self.parse_inline_table()?),
+            Some(c) if is_word(c) => self.parse_word()?,
+            Some(c) => return self.error(format!("Expected a value, found '{}'", c)),
+            None => return self.error("Expected a value, found the end of the file")
+        };
+
+        Ok(TomlItem { value, position })
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String> {
+        if self.peek_at(1) == Some('"') && self.peek_at(2) == Some('"') {
+            return self.error("Multi-line strings are not supported");
+        }
+
+        self.expect('"')?;
+        let mut value = String::new();
+
+        loop {
+            match self.peek() {
+                None | Some('\n') => return self.error("Unterminated string"),
+                Some('"') => {
+                    self.next();
+                    return Ok(value);
+                },
+                Some('\\') => {
+                    let position = self.position();
+                    self.next();
+                    value.push(self.parse_escape(position)?);
+                },
+                Some(c) => {
+                    value.push(c);
+                    self.next();
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self, position:Position) -> Result<char> {
+        let c = match self.next() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(c @ ('u' | 'U')) => {
+                let length = if c == 'u' { 4 } else { 8 };
+                let mut code = String::new();
+                for _ in 0..length {
+                    match self.next() {
+                        Some(c) if c.is_ascii_hexdigit() => code.push(c),
+                        _ => return Err(TomlError::new("Invalid unicode escape", position))
+                    }
+                }
+
+                return u32::from_str_radix(&code, 16).ok()
+                    .and_then(char::from_u32)
+                    .ok_or(TomlError::new("Invalid unicode escape", position));
+            },
+            _ => return Err(TomlError::new("Invalid escape sequence", position))
+        };
+
+        Ok(c)
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String> {
+        if self.peek_at(1) == Some('\'') && self.peek_at(2) == Some('\'') {
+            return self.error("Multi-line strings are not supported");
+        }
+
+        self.expect('\'')?;
+        let mut value = String::new();
+
+        loop {
+            match self.peek() {
+                None | Some('\n') => return self.error("Unterminated string"),
+                Some('\'') => {
+                    self.next();
+                    return Ok(value);
+                },
+                Some(c) => {
+                    value.push(c);
+                    self.next();
+                }
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Vec<TomlItem>> {
+        self.expect('[')?;
+        let mut list = Vec::new();
+
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.next();
+                return Ok(list);
+            }
+
+            list.push(self.parse_value()?);
+            self.skip_blank();
+
+            match self.peek() {
+                Some(',') => { self.next(); },
+                Some(']') => {},
+                Some(c) => return self.error(format!("Expected ',' or ']', found '{}'", c)),
+                None => return self.error("Unterminated array")
+            }
+        }
+    }
+
+    /// Has to be on a single line, without a trailing comma.
+    fn parse_inline_table(&mut self) -> Result<TomlTable> {
+        self.expect('{')?;
+        let mut table = TomlTable::default();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(table);
+        }
+
+        loop {
+            self.skip_whitespace();
+            self.parse_key_value(&mut table)?;
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => { self.next(); },
+                Some('}') => {
+                    self.next();
+                    return Ok(table);
+                },
+                Some(c) => return self.error(format!("Expected ',' or '}}', found '{}'", c)),
+                None => return self.error("Unterminated inline table")
+            }
+        }
+    }
+
+    /// Booleans and numbers.
+    fn parse_word(&mut self) -> Result<TomlValue> {
+        let position = self.position();
+        let mut word = String::new();
+        while let Some(c) = self.peek() && is_word(c) {
+            word.push(c);
+            self.next();
+        }
+
+        let invalid = |message:&str|Err(TomlError::new(format!("{} \"{}\"", message, word), position));
+
+        match word.as_str() {
+            "true" => return Ok(TomlValue::Boolean(true)),
+            "false" => return Ok(TomlValue::Boolean(false)),
+            _ => {}
+        }
+
+        let digits = word.trim_start_matches(['+', '-']);
+        if word.contains(':') || (digits.len() > 4 && digits.as_bytes()[4] == b'-') {
+            return invalid("Dates are not supported");
+        }
+
+        if word.starts_with('_') || word.ends_with('_') || word.contains("__") {
+            return invalid("Invalid number");
+        }
+        let number = word.replace('_', "");
+
+        for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+            if let Some(value) = number.strip_prefix(prefix) {
+                return match i64::from_str_radix(value, radix) {
+                    Ok(value) => Ok(TomlValue::Integer(value)),
+                    Err(_) => invalid("Invalid integer")
+                };
+            }
+        }
+
+        let unsigned = number.trim_start_matches(['+', '-']);
+        if unsigned.len() > 1 && unsigned.starts_with('0') && !unsigned.starts_with("0.") && !unsigned[1..].starts_with(['e', 'E']) {
+            return invalid("Leading zeros are not allowed in");
+        }
+
+        if number.contains(['.', 'e', 'E']) {
+            match number.parse() {
+                Ok(value) if unsigned.starts_with(|c:char|c.is_ascii_digit()) => Ok(TomlValue::Float(value)),
+                _ => invalid("Invalid float")
+            }
+        } else {
+            match number.parse() {
+                Ok(value) => Ok(TomlValue::Integer(value)),
+                Err(_) => invalid("Invalid value")
+            }
+        }
+    }
+}
+
+/// Table the key refers to, which is created if it does not exist.
+///
+/// An array of tables refers to the last table added to it.
+fn child_table<'t>(table:&'t mut TomlTable, key:&str, position:Position) -> Result<&'t mut TomlTable> {
+    if table.get(key).is_none() {
+        table.insert(key.to_string(), position, TomlItem {
+            value: TomlValue::Table(TomlTable::default()),
+            position
+        });
+    }
+
+    let item = table.get_mut(key).unwrap();
+    let type_of = item.value.type_of();
+    let table = match &mut item.value {
+        TomlValue::Table(table) => Some(table),
+        TomlValue::Array(list) => match list.last_mut() {
+            Some(TomlItem{ value: TomlValue::Table(table), .. }) => Some(table),
+            _ => None
+        },
+        _ => None
+    };
+
+    table.ok_or_else(||TomlError::new(format!("Key \"{}\" is already defined as a {}", key, type_of), position))
+}
+
+/// Table a header refers to, the path was checked when the header was parsed.
+fn table_at<'t>(root:&'t mut TomlTable, path:&[String]) -> &'t mut TomlTable {
+    let mut table = root;
+    for key in path {
+        table = child_table(table, key, Position { line: 0, column: 0 })
+            .expect("Header path was already checked!");
+    }
+
+    table
+}